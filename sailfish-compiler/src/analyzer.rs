@@ -1,6 +1,7 @@
 use crate::error::*;
+use syn::parse::{Parse, ParseStream, Result as ParseResult};
 use syn::visit_mut::VisitMut;
-use syn::Block;
+use syn::{Block, Expr, ExprField, ExprPath, Ident, Macro, Member, Token};
 
 #[derive(Clone)]
 pub struct Analyzer {}
@@ -21,6 +22,16 @@ impl Analyzer {
             Ok(())
         }
     }
+
+    /// Collect the references to template fields inside render macros.
+    ///
+    /// Field accesses are reported as `self.field`, and bare identifiers (which
+    /// refer to fields in `TemplateSimple`) are reported as is.
+    pub fn field_refs(&self, ast: &mut Block) -> Vec<String> {
+        let mut collector = FieldRefCollector { refs: Vec::new() };
+        collector.visit_block_mut(ast);
+        collector.refs
+    }
 }
 
 struct AnalyzerImpl {
@@ -30,3 +41,67 @@ struct AnalyzerImpl {
 impl VisitMut for AnalyzerImpl {
     // write code here
 }
+
+struct FieldRefCollector {
+    refs: Vec<String>,
+}
+
+impl FieldRefCollector {
+    fn push(&mut self, r: String) {
+        if !self.refs.contains(&r) {
+            self.refs.push(r);
+        }
+    }
+}
+
+impl VisitMut for FieldRefCollector {
+    fn visit_macro_mut(&mut self, i: &mut Macro) {
+        struct RenderMacroArgument {
+            #[allow(dead_code)]
+            context: Ident,
+            expr: Expr,
+        }
+
+        impl Parse for RenderMacroArgument {
+            fn parse(s: ParseStream) -> ParseResult<Self> {
+                let context = s.parse()?;
+                s.parse::<Token![,]>()?;
+                let expr = s.parse()?;
+
+                Ok(Self { context, expr })
+            }
+        }
+
+        let mut it = i.path.segments.iter();
+
+        if matches!(it.next(), Some(s) if s.ident == "__sf_rt")
+            && matches!(it.next(), Some(s) if s.ident == "render" || s.ident == "render_escaped")
+            && it.next().is_none()
+        {
+            if let Ok(mut arg) = syn::parse2::<RenderMacroArgument>(i.tokens.clone()) {
+                syn::visit_mut::visit_expr_mut(self, &mut arg.expr);
+            }
+        }
+    }
+
+    fn visit_expr_field_mut(&mut self, i: &mut ExprField) {
+        if let (Expr::Path(ref base), Member::Named(ref member)) = (&*i.base, &i.member) {
+            if base.qself.is_none() && base.path.is_ident("self") {
+                self.push(format!("self.{}", member));
+                return;
+            }
+        }
+
+        syn::visit_mut::visit_expr_field_mut(self, i);
+    }
+
+    fn visit_expr_path_mut(&mut self, i: &mut ExprPath) {
+        if i.qself.is_none() {
+            if let Some(ident) = i.path.get_ident() {
+                if ident != "self" {
+                    self.push(ident.to_string());
+                }
+            }
+        }
+    }
+}
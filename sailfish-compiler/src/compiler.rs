@@ -11,7 +11,7 @@ use crate::error::*;
 use crate::optimizer::Optimizer;
 use crate::parser::Parser;
 use crate::resolver::Resolver;
-use crate::translator::{TranslatedSource, Translator};
+use crate::translator::{SourceMap, TranslatedSource, Translator};
 use crate::util::{read_to_string, rustfmt_block};
 
 #[derive(Default)]
@@ -21,6 +21,9 @@ pub struct Compiler {
 
 pub struct CompilationReport {
     pub deps: Vec<PathBuf>,
+    pub field_refs: Vec<String>,
+    /// template which renders one of the named blocks selected by `__sf_fragment`
+    pub fragments: Option<TranslatedSource>,
 }
 
 impl Compiler {
@@ -32,9 +35,24 @@ impl Compiler {
         Self { config }
     }
 
+    fn parser(&self) -> Parser {
+        let mut parser = Parser::new().delimiter(self.config.delimiter);
+        if let Some((ref open, ref close)) = self.config.delimiters {
+            parser = parser.delimiters(open, close);
+        }
+        if let Some((ref open, ref close)) = self.config.comment {
+            parser = parser.comment(open, close);
+        }
+        parser
+    }
+
     fn translate_file_contents(&self, input: &Path) -> Result<TranslatedSource, Error> {
-        let parser = Parser::new().delimiter(self.config.delimiter);
-        let translator = Translator::new().escape(self.config.escape);
+        let parser = self.parser();
+        let translator = Translator::new()
+            .escape(self.config.escape)
+            .filters(self.config.filters.clone())
+            .filter_module(self.config.filter_module.clone())
+            .translator(self.config.translator.clone());
         let content = read_to_string(input)
             .chain_err(|| format!("Failed to open template file: {:?}", input))?;
 
@@ -50,12 +68,25 @@ impl Compiler {
             Ok(self.translate_file_contents(&*child_file)?.ast)
         });
 
-        let resolver = Resolver::new().include_handler(include_handler);
+        let resolver = Resolver::new()
+            .include_handler(include_handler)
+            .recursion_limit(self.config.recursion_limit);
         let mut tsource = self.translate_file_contents(input)?;
-        let mut report = CompilationReport { deps: Vec::new() };
+        let mut report = CompilationReport {
+            deps: Vec::new(),
+            field_refs: Vec::new(),
+            fragments: None,
+        };
 
         let r = resolver.resolve(input, &mut tsource.ast)?;
         report.deps = r.deps;
+        report.field_refs = Analyzer::new().field_refs(&mut tsource.ast);
+        if !r.fragments.is_empty() {
+            report.fragments = Some(TranslatedSource {
+                ast: fragments_block(r.fragments),
+                source_map: SourceMap::default(),
+            });
+        }
         Ok((tsource, report))
     }
 
@@ -113,9 +144,15 @@ impl Compiler {
             ))
         });
 
-        let parser = Parser::new().delimiter(self.config.delimiter);
-        let translator = Translator::new().escape(self.config.escape);
-        let resolver = Resolver::new().include_handler(include_handler);
+        let parser = self.parser();
+        let translator = Translator::new()
+            .escape(self.config.escape)
+            .filters(self.config.filters.clone())
+            .filter_module(self.config.filter_module.clone())
+            .translator(self.config.translator.clone());
+        let resolver = Resolver::new()
+            .include_handler(include_handler)
+            .recursion_limit(self.config.recursion_limit);
         let optimizer = Optimizer::new().rm_whitespace(self.config.rm_whitespace);
 
         let compile = || -> Result<String, Error> {
@@ -135,3 +172,20 @@ impl Compiler {
             })
     }
 }
+
+// dispatch to the named block contents
+fn fragments_block(fragments: Vec<(String, Block)>) -> Block {
+    let (names, bodies): (Vec<_>, Vec<_>) = fragments.into_iter().unzip();
+
+    syn::parse_quote! {{
+        match __sf_fragment {
+            #(#names => #bodies)*
+            _ => {
+                return Err(__sf_rt::RenderError::Msg(format!(
+                    "Unknown fragment: {:?}",
+                    __sf_fragment
+                )));
+            }
+        }
+    }}
+}
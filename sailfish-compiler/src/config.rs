@@ -3,8 +3,22 @@ use std::path::{Path, PathBuf};
 #[derive(Clone, Debug, Hash)]
 pub struct Config {
     pub delimiter: char,
+    /// opening and closing tags which override `delimiter`
+    pub delimiters: Option<(String, String)>,
+    /// alternative opening and closing tags for comments (e.g. `<%--` and `--%>`)
+    pub comment: Option<(String, String)>,
     pub escape: bool,
+    /// escaping rules applied to the escaped evaluation blocks
+    pub escape_mode: EscapeMode,
+    /// filter chain applied to every evaluation block
+    pub filters: Option<String>,
+    /// module path where the filter functions are looked up
+    pub filter_module: Option<String>,
+    /// expression which evaluates to the translation backend used by `<%~ %>` tag
+    pub translator: Option<String>,
     pub rm_whitespace: bool,
+    /// maximum depth of the recursive includes
+    pub recursion_limit: usize,
     pub template_dirs: Vec<PathBuf>,
     #[doc(hidden)]
     pub cache_dir: PathBuf,
@@ -17,14 +31,45 @@ impl Default for Config {
         Self {
             template_dirs: Vec::new(),
             delimiter: '%',
+            delimiters: None,
+            comment: None,
             escape: true,
+            escape_mode: EscapeMode::Html,
+            filters: None,
+            filter_module: None,
+            translator: None,
             cache_dir: Path::new(env!("OUT_DIR")).join("cache"),
             rm_whitespace: false,
+            recursion_limit: 8,
             _non_exhaustive: (),
         }
     }
 }
 
+/// Escaping rules for the evaluation blocks
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum EscapeMode {
+    Html,
+    Xml,
+}
+
+impl Default for EscapeMode {
+    fn default() -> Self {
+        EscapeMode::Html
+    }
+}
+
+impl EscapeMode {
+    /// Parse the escape mode name (`"html"` or `"xml"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "html" => Some(EscapeMode::Html),
+            "xml" => Some(EscapeMode::Xml),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(feature = "config")]
 mod imp {
     use serde::Deserialize;
@@ -74,10 +119,56 @@ mod imp {
                         config.delimiter = delimiter;
                     }
 
+                    if let Some((open, close)) = config_file.delimiters {
+                        if open.is_empty() || close.is_empty() {
+                            let mut e = error("`delimiters` must not be empty");
+                            e.source_file = Some(path.to_owned());
+                            return Err(e);
+                        }
+                        config.delimiters = Some((open, close));
+                    }
+
+                    if let Some((open, close)) = config_file.comment {
+                        if open.is_empty() || close.is_empty() {
+                            let mut e = error("`comment` must not be empty");
+                            e.source_file = Some(path.to_owned());
+                            return Err(e);
+                        }
+                        config.comment = Some((open, close));
+                    }
+
                     if let Some(escape) = config_file.escape {
                         config.escape = escape;
                     }
 
+                    if let Some(escape_mode) = config_file.escape_mode {
+                        config.escape_mode = EscapeMode::from_name(&escape_mode)
+                            .ok_or_else(|| {
+                                let mut e = error(format!(
+                                    "Invalid escape mode: `{}`",
+                                    escape_mode
+                                ));
+                                e.source_file = Some(path.to_owned());
+                                e
+                            })?;
+                    }
+
+                    if let Some(filters) = config_file.filters {
+                        config.filters = Some(filters);
+                    }
+
+                    if let Some(filter_module) = config_file.filter_module {
+                        config.filter_module = Some(filter_module);
+                    }
+
+                    if let Some(translator) = config_file.translator {
+                        config.translator = Some(translator);
+                    }
+
+                    if let Some(recursion_limit) = config_file.recursion_limit {
+                        config.recursion_limit = recursion_limit;
+                    }
+
                     if let Some(optimizations) = config_file.optimizations {
                         if let Some(rm_whitespace) = optimizations.rm_whitespace {
                             config.rm_whitespace = rm_whitespace;
@@ -103,7 +194,14 @@ mod imp {
     struct ConfigFile {
         template_dirs: Option<Vec<String>>,
         delimiter: Option<char>,
+        delimiters: Option<(String, String)>,
+        comment: Option<(String, String)>,
         escape: Option<bool>,
+        escape_mode: Option<String>,
+        filters: Option<String>,
+        filter_module: Option<String>,
+        translator: Option<String>,
+        recursion_limit: Option<usize>,
         optimizations: Option<Optimizations>,
     }
 
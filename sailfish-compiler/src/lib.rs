@@ -13,7 +13,7 @@ mod translator;
 mod util;
 
 pub use compiler::Compiler;
-pub use config::Config;
+pub use config::{Config, EscapeMode};
 pub use error::{Error, ErrorKind};
 
 #[cfg(feature = "procmacro")]
@@ -19,6 +19,8 @@ macro_rules! unwrap_or_break {
 #[derive(Clone, Debug)]
 pub struct Parser {
     delimiter: char,
+    delimiters: Option<(String, String)>,
+    comment: Option<(String, String)>,
 }
 
 impl Parser {
@@ -32,25 +34,55 @@ impl Parser {
         self
     }
 
+    /// change opening and closing tags (e.g. `{{` and `}}`)
+    ///
+    /// This setting takes precedence over `delimiter`. Both tags must not be empty.
+    pub fn delimiters(mut self, open: &str, close: &str) -> Self {
+        debug_assert!(!open.is_empty() && !close.is_empty());
+        self.delimiters = Some((open.to_owned(), close.to_owned()));
+        self
+    }
+
+    /// add alternative opening and closing tags for comments (e.g. `<%--` and
+    /// `--%>`)
+    ///
+    /// Both tags must not be empty.
+    pub fn comment(mut self, open: &str, close: &str) -> Self {
+        debug_assert!(!open.is_empty() && !close.is_empty());
+        self.comment = Some((open.to_owned(), close.to_owned()));
+        self
+    }
+
     /// parse source string
     pub fn parse<'a>(&self, source: &'a str) -> ParseStream<'a> {
-        let block_delimiter = Rc::new((
-            format!("<{}", self.delimiter),
-            format!("{}>", self.delimiter),
-        ));
+        let block_delimiter = Rc::new(match self.delimiters {
+            Some(ref delimiters) => delimiters.clone(),
+            None => (
+                format!("<{}", self.delimiter),
+                format!("{}>", self.delimiter),
+            ),
+        });
+
+        // opening tag is escaped by repeating its last character (e.g. `<%%`)
+        let delimiter = block_delimiter.0.chars().last().unwrap_or(self.delimiter);
 
         ParseStream {
             block_delimiter,
+            comment_delimiter: self.comment.clone().map(Rc::new),
             original_source: source,
             source,
-            delimiter: self.delimiter,
+            delimiter,
         }
     }
 }
 
 impl Default for Parser {
     fn default() -> Self {
-        Self { delimiter: '%' }
+        Self {
+            delimiter: '%',
+            delimiters: None,
+            comment: None,
+        }
     }
 }
 
@@ -58,11 +90,21 @@ impl Default for Parser {
 pub enum TokenKind {
     NestedTemplateOnce,
     BufferedCode { escape: bool },
+    Translation,
     Code,
     Comment,
     Text,
 }
 
+/// Whitespace trimming requested by a marker on the closing tag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Trim {
+    /// `-%>`: remove the following newline
+    Newline,
+    /// `_%>`: remove the following spaces and tabs
+    Whitespace,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Token<'a> {
     content: &'a str,
@@ -99,6 +141,7 @@ impl<'a> Token<'a> {
 #[derive(Clone, Debug)]
 pub struct ParseStream<'a> {
     block_delimiter: Rc<(String, String)>,
+    comment_delimiter: Option<Rc<(String, String)>>,
     pub(crate) original_source: &'a str,
     source: &'a str,
     delimiter: char,
@@ -148,6 +191,11 @@ impl<'a> ParseStream<'a> {
         let mut start = self.block_delimiter.0.len();
         let mut token_kind = TokenKind::Code;
 
+        // `<%_` trims the preceding whitespaces (see `tokenize_text`)
+        if self.source.as_bytes().get(start) == Some(&b'_') {
+            start += 1;
+        }
+
         // read flags
         match self.source.as_bytes().get(start).copied() {
             Some(b'#') => {
@@ -166,6 +214,11 @@ impl<'a> ParseStream<'a> {
                 token_kind = TokenKind::NestedTemplateOnce;
                 start += 1;
             }
+            // `~` cannot start any Rust statement, so it never conflicts with code
+            Some(b'~') => {
+                token_kind = TokenKind::Translation;
+                start += 1;
+            }
             _ => {}
         }
 
@@ -184,6 +237,8 @@ impl<'a> ParseStream<'a> {
                 .find(&*self.block_delimiter.1)
                 .ok_or_else(|| self.error("Unterminated comment block"))?;
 
+            // whitespace control markers are not recognized inside comments, since
+            // comments such as `<%# ---------- %>` often end with them
             self.take_n(start);
             let token = Token {
                 content: self.source[..pos].trim_end(),
@@ -200,41 +255,132 @@ impl<'a> ParseStream<'a> {
         {
             // closing bracket was found
             self.take_n(start);
-            let s = &self.source[..pos - self.block_delimiter.1.len()].trim_end_matches(
-                |c| matches!(c, ' ' | '\t' | '\r' | '\u{000B}' | '\u{000C}'),
-            );
+            let (s, trim) =
+                split_trim_marker(&self.source[..pos - self.block_delimiter.1.len()]);
+            let s = s.trim_end_matches(|c| {
+                matches!(c, ' ' | '\t' | '\r' | '\u{000B}' | '\u{000C}')
+            });
             let token = Token {
                 content: s,
                 offset: self.offset(),
                 kind: token_kind,
             };
             self.take_n(pos);
+            self.skip_whitespace(trim);
             Ok(token)
         } else {
             Err(self.error("Unterminated code block"))
         }
     }
 
+    /// read the comment enclosed by the alternative comment tags
+    fn tokenize_comment(&mut self) -> Result<Token<'a>, Error> {
+        let delimiter = Rc::clone(self.comment_delimiter.as_ref().unwrap());
+        let start = delimiter.0.len();
+        let pos = self.source[start..]
+            .find(&*delimiter.1)
+            .ok_or_else(|| self.error("Unterminated comment block"))?;
+
+        self.take_n(start);
+        let token = Token {
+            content: self.source[..pos].trim(),
+            offset: self.offset(),
+            kind: TokenKind::Comment,
+        };
+        self.take_n(pos + delimiter.1.len());
+        Ok(token)
+    }
+
     fn tokenize_text(&mut self) -> Result<Token<'a>, Error> {
         // TODO: allow buffer block inside code block
         let offset = self.offset();
-        let end = self
+        let mut end = self
             .source
             .find(&*self.block_delimiter.0)
             .unwrap_or_else(|| self.source.len());
+        if let Some(ref d) = self.comment_delimiter {
+            if let Some(pos) = self.source[..end].find(&*d.0) {
+                end = pos;
+            }
+        }
+        let mut content = self.take_n(end);
+
+        // `<%_` removes the spaces and tabs before the tag
+        if matches!(
+            self.source.get(self.block_delimiter.0.len()..),
+            Some(s) if s.starts_with('_')
+        ) {
+            content = content.trim_end_matches([' ', '\t']);
+        }
+
         let token = Token {
-            content: self.take_n(end),
+            content,
             offset,
             kind: TokenKind::Text,
         };
         Ok(token)
     }
+
+    /// read the contents until `<% endraw %>` tag as a text
+    fn tokenize_raw(&mut self) -> Result<Token<'a>, Error> {
+        let offset = self.offset();
+        let mut searched = 0;
+
+        while let Some(pos) = self.source[searched..].find(&*self.block_delimiter.0) {
+            let begin = searched + pos;
+            let mut tag = self.clone();
+            tag.take_n(begin);
+
+            if let Ok(t) = tag.tokenize_code() {
+                if t.kind == TokenKind::Code && t.content == "endraw" {
+                    let mut content = &self.source[..begin];
+                    if self.source[begin + self.block_delimiter.0.len()..]
+                        .starts_with('_')
+                    {
+                        content = content.trim_end_matches([' ', '\t']);
+                    }
+
+                    self.source = tag.source;
+                    return Ok(Token {
+                        content,
+                        offset,
+                        kind: TokenKind::Text,
+                    });
+                }
+            }
+
+            searched = begin + self.block_delimiter.0.len();
+        }
+
+        Err(self.error("Unterminated raw block"))
+    }
+
+    fn skip_whitespace(&mut self, trim: Option<Trim>) {
+        let end = match trim {
+            Some(Trim::Newline) => {
+                let line_end =
+                    self.source.find('\n').map_or(self.source.len(), |p| p + 1);
+                if self.source[..line_end].trim().is_empty() {
+                    line_end
+                } else {
+                    0
+                }
+            }
+            Some(Trim::Whitespace) => {
+                let rest = self.source.trim_start_matches([' ', '\t']);
+                self.source.len() - rest.len()
+            }
+            None => 0,
+        };
+        self.take_n(end);
+    }
 }
 
 impl<'a> Default for ParseStream<'a> {
     fn default() -> Self {
         Self {
             block_delimiter: Rc::new(("<%".to_owned(), "%>".to_owned())),
+            comment_delimiter: None,
             original_source: "",
             source: "",
             delimiter: '%',
@@ -250,28 +396,41 @@ impl<'a> Iterator for ParseStream<'a> {
             return None;
         }
 
-        let token = if self.source.starts_with(&*self.block_delimiter.0) {
+        let is_comment = matches!(
+            self.comment_delimiter,
+            Some(ref d) if self.source.starts_with(&*d.0)
+        );
+
+        let token = if is_comment {
+            self.tokenize_comment()
+        } else if self.source.starts_with(&*self.block_delimiter.0) {
             if !self.source[self.block_delimiter.0.len()..].starts_with(self.delimiter) {
-                self.tokenize_code()
+                match self.tokenize_code() {
+                    Ok(ref t) if t.kind == TokenKind::Code && t.content == "raw" => {
+                        self.tokenize_raw()
+                    }
+                    token => token,
+                }
             } else {
-                debug_assert_eq!(
-                    &self.source[..self.delimiter.len_utf8() * 2 + 1],
-                    format!("<{0}{0}", self.delimiter)
-                );
-
                 // Escape '<%%' token
                 let token = Token {
                     content: &self.source[..self.block_delimiter.0.len()],
                     offset: self.offset(),
                     kind: TokenKind::Text,
                 };
-                self.take_n(self.block_delimiter.0.len() * 2 - 1);
+                self.take_n(self.block_delimiter.0.len() + self.delimiter.len_utf8());
                 Ok(token)
             }
         } else {
             self.tokenize_text()
         };
 
+        // text may become empty after trimming the whitespaces
+        if matches!(token, Ok(ref t) if t.kind == TokenKind::Text && t.content.is_empty())
+        {
+            return self.next();
+        }
+
         Some(token)
     }
 }
@@ -284,6 +443,22 @@ impl<'a> TryInto<Vec<Token<'a>>> for ParseStream<'a> {
     }
 }
 
+/// split off the whitespace control marker at the end of the block
+fn split_trim_marker(content: &str) -> (&str, Option<Trim>) {
+    if let Some(s) = content.strip_suffix('-') {
+        (s, Some(Trim::Newline))
+    } else if let Some(s) = content.strip_suffix('_') {
+        // identifiers may end with '_' (e.g. `foo_`)
+        if s.is_empty() || s.ends_with(char::is_whitespace) {
+            (s, Some(Trim::Whitespace))
+        } else {
+            (content, None)
+        }
+    } else {
+        (content, None)
+    }
+}
+
 fn find_block_end(haystack: &str, delimiter: &str) -> Option<usize> {
     let mut remain = haystack;
 
@@ -497,4 +672,208 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn whitespace_control() {
+        let src =
+            "<ul>\n  <%_ for x in xs { -%>\n  <li>  <%= x _%>  </li>\n  <%_ } -%>\n</ul>";
+        let parser = Parser::new();
+        let tokens = parser.parse(src).into_vec().unwrap();
+        assert_eq!(
+            &tokens,
+            &[
+                Token {
+                    content: "<ul>\n",
+                    offset: 0,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "for x in xs {",
+                    offset: 11,
+                    kind: TokenKind::Code
+                },
+                Token {
+                    content: "  <li>  ",
+                    offset: 29,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "x",
+                    offset: 41,
+                    kind: TokenKind::BufferedCode { escape: true }
+                },
+                Token {
+                    content: "</li>\n",
+                    offset: 48,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "}",
+                    offset: 60,
+                    kind: TokenKind::Code
+                },
+                Token {
+                    content: "</ul>",
+                    offset: 66,
+                    kind: TokenKind::Text
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_underscore_identifier() {
+        let src = "<%= foo_%>\n";
+        let parser = Parser::new();
+        let tokens = parser.parse(src).into_vec().unwrap();
+        assert_eq!(
+            &tokens,
+            &[
+                Token {
+                    content: "foo_",
+                    offset: 4,
+                    kind: TokenKind::BufferedCode { escape: true }
+                },
+                Token {
+                    content: "\n",
+                    offset: 10,
+                    kind: TokenKind::Text
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_block() {
+        let src = "<% raw -%>\n<%= foo %> <%% <% } %>\n<% endraw %>!";
+        let parser = Parser::new();
+        let tokens = parser.parse(src).into_vec().unwrap();
+        assert_eq!(
+            &tokens,
+            &[
+                Token {
+                    content: "<%= foo %> <%% <% } %>\n",
+                    offset: 11,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "!",
+                    offset: 46,
+                    kind: TokenKind::Text
+                },
+            ]
+        );
+
+        let src = "<% raw %><%= foo %>";
+        assert!(parser.parse(src).into_vec().is_err());
+    }
+
+    #[test]
+    fn custom_delimiters() {
+        let src = "{{= a }} <% b %> {{{";
+        let parser = Parser::new().delimiters("{{", "}}");
+        let tokens = parser.parse(src).into_vec().unwrap();
+        assert_eq!(
+            &tokens,
+            &[
+                Token {
+                    content: "a",
+                    offset: 4,
+                    kind: TokenKind::BufferedCode { escape: true }
+                },
+                Token {
+                    content: " <% b %> ",
+                    offset: 8,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "{{",
+                    offset: 17,
+                    kind: TokenKind::Text
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn translation_tag() {
+        let src = "<%~ \"hello\", name = a %><%t = tick(); %>";
+        let tokens = Parser::new().parse(src).into_vec().unwrap();
+        assert_eq!(
+            &tokens,
+            &[
+                Token {
+                    content: "\"hello\", name = a",
+                    offset: 4,
+                    kind: TokenKind::Translation
+                },
+                Token {
+                    content: "t = tick();",
+                    offset: 26,
+                    kind: TokenKind::Code
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn comment_ending_with_dash() {
+        let src = "a<%# ---------- %>\nb";
+        let tokens = Parser::new().parse(src).into_vec().unwrap();
+        assert_eq!(
+            &tokens,
+            &[
+                Token {
+                    content: "a",
+                    offset: 0,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "----------",
+                    offset: 5,
+                    kind: TokenKind::Comment
+                },
+                Token {
+                    content: "\nb",
+                    offset: 18,
+                    kind: TokenKind::Text
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn comment_tags() {
+        let src = "a<%-- <%= b %> --%>c<%- d %>";
+        let parser = Parser::new().comment("<%--", "--%>");
+        let tokens = parser.parse(src).into_vec().unwrap();
+        assert_eq!(
+            &tokens,
+            &[
+                Token {
+                    content: "a",
+                    offset: 0,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "<%= b %>",
+                    offset: 5,
+                    kind: TokenKind::Comment
+                },
+                Token {
+                    content: "c",
+                    offset: 19,
+                    kind: TokenKind::Text
+                },
+                Token {
+                    content: "d",
+                    offset: 24,
+                    kind: TokenKind::BufferedCode { escape: false }
+                },
+            ]
+        );
+
+        let src = "<%-- a";
+        assert!(parser.parse(src).into_vec().is_err());
+    }
 }
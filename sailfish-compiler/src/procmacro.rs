@@ -9,10 +9,10 @@ use std::time::Duration;
 use std::{env, thread};
 use syn::parse::{ParseStream, Parser, Result as ParseResult};
 use syn::punctuated::Punctuated;
-use syn::{Fields, Ident, ItemStruct, LitBool, LitChar, LitStr, Token};
+use syn::{Fields, Ident, ItemStruct, LitBool, LitChar, LitInt, LitStr, Token};
 
 use crate::compiler::Compiler;
-use crate::config::Config;
+use crate::config::{Config, EscapeMode};
 use crate::error::*;
 use crate::util::filetime;
 
@@ -22,8 +22,18 @@ struct DeriveTemplateOptions {
     found_keys: Vec<Ident>,
     path: Option<LitStr>,
     delimiter: Option<LitChar>,
+    delimiters: Option<(LitStr, LitStr)>,
+    comment: Option<(LitStr, LitStr)>,
     escape: Option<LitBool>,
+    escape_mode: Option<LitStr>,
+    filters: Option<LitStr>,
+    filter_module: Option<LitStr>,
+    translator: Option<LitStr>,
     rm_whitespace: Option<LitBool>,
+    recursion_limit: Option<LitInt>,
+    bom: Option<LitBool>,
+    impl_display: Option<LitBool>,
+    used_fields: Option<LitBool>,
 }
 
 impl DeriveTemplateOptions {
@@ -45,10 +55,37 @@ impl DeriveTemplateOptions {
                     self.path = Some(s.parse::<LitStr>()?);
                 } else if key == "delimiter" {
                     self.delimiter = Some(s.parse::<LitChar>()?);
+                } else if key == "delimiters" {
+                    self.delimiters = Some(parse_tag_pair(s)?);
+                } else if key == "comment" {
+                    self.comment = Some(parse_tag_pair(s)?);
                 } else if key == "escape" {
                     self.escape = Some(s.parse::<LitBool>()?);
+                } else if key == "escape_mode" {
+                    let mode = s.parse::<LitStr>()?;
+                    if EscapeMode::from_name(&mode.value()).is_none() {
+                        return Err(syn::Error::new(
+                            mode.span(),
+                            "Escape mode must be either \"html\" or \"xml\".",
+                        ));
+                    }
+                    self.escape_mode = Some(mode);
+                } else if key == "filters" {
+                    self.filters = Some(s.parse::<LitStr>()?);
+                } else if key == "filter_module" {
+                    self.filter_module = Some(s.parse::<LitStr>()?);
+                } else if key == "translator" {
+                    self.translator = Some(s.parse::<LitStr>()?);
                 } else if key == "rm_whitespace" {
                     self.rm_whitespace = Some(s.parse::<LitBool>()?);
+                } else if key == "recursion_limit" {
+                    self.recursion_limit = Some(s.parse::<LitInt>()?);
+                } else if key == "bom" {
+                    self.bom = Some(s.parse::<LitBool>()?);
+                } else if key == "impl_display" {
+                    self.impl_display = Some(s.parse::<LitBool>()?);
+                } else if key == "used_fields" {
+                    self.used_fields = Some(s.parse::<LitBool>()?);
                 } else {
                     return Err(syn::Error::new(
                         key.span(),
@@ -71,19 +108,73 @@ impl DeriveTemplateOptions {
     }
 }
 
-fn merge_config_options(config: &mut Config, options: &DeriveTemplateOptions) {
+// parse the pair of opening and closing tags (e.g. `("{{", "}}")`)
+fn parse_tag_pair(s: ParseStream) -> ParseResult<(LitStr, LitStr)> {
+    let content;
+    syn::parenthesized!(content in s);
+    let open = content.parse::<LitStr>()?;
+    content.parse::<Token![,]>()?;
+    let close = content.parse::<LitStr>()?;
+    if !content.is_empty() {
+        return Err(content.error("Expected opening and closing tags"));
+    }
+    for tag in &[&open, &close] {
+        if tag.value().is_empty() {
+            return Err(syn::Error::new(tag.span(), "Delimiter must not be empty."));
+        }
+    }
+    Ok((open, close))
+}
+
+fn merge_config_options(
+    config: &mut Config,
+    options: &DeriveTemplateOptions,
+) -> Result<(), syn::Error> {
     if let Some(ref delimiter) = options.delimiter {
         config.delimiter = delimiter.value();
     }
+    if let Some((ref open, ref close)) = options.delimiters {
+        config.delimiters = Some((open.value(), close.value()));
+    }
+    if let Some((ref open, ref close)) = options.comment {
+        config.comment = Some((open.value(), close.value()));
+    }
     if let Some(ref escape) = options.escape {
         config.escape = escape.value;
     }
+    if let Some(ref escape_mode) = options.escape_mode {
+        config.escape_mode = EscapeMode::from_name(&escape_mode.value()).unwrap();
+    }
+    if let Some(ref filters) = options.filters {
+        config.filters = Some(filters.value());
+    }
+    if let Some(ref filter_module) = options.filter_module {
+        config.filter_module = Some(filter_module.value());
+    }
+    if let Some(ref translator) = options.translator {
+        config.translator = Some(translator.value());
+    }
     if let Some(ref rm_whitespace) = options.rm_whitespace {
         config.rm_whitespace = rm_whitespace.value;
     }
+    if let Some(ref recursion_limit) = options.recursion_limit {
+        config.recursion_limit = recursion_limit.base10_parse()?;
+    }
+
+    Ok(())
 }
 
 fn resolve_template_file(path: &str, template_dirs: &[PathBuf]) -> Option<PathBuf> {
+    // absolute paths are used as-is instead of being joined with template directories
+    let absolute = Path::new(path);
+    if absolute.is_absolute() {
+        return if absolute.is_file() {
+            Some(absolute.to_path_buf())
+        } else {
+            None
+        };
+    }
+
     for template_dir in template_dirs.iter().rev() {
         let p = template_dir.join(path);
         if p.is_file() {
@@ -145,9 +236,22 @@ fn with_compiler<T, F: FnOnce(Compiler) -> Result<T, Error>>(
     apply(compiler)
 }
 
+// (struct, include_bytes_seq, bom_seq, used_fields_impl, output file, fragments file,
+// options)
+type DeriveTemplateCommon = (
+    ItemStruct,
+    TokenStream,
+    TokenStream,
+    TokenStream,
+    String,
+    Option<String>,
+    DeriveTemplateOptions,
+);
+
 fn derive_template_common_impl(
     tokens: TokenStream,
-) -> Result<(ItemStruct, TokenStream, String), syn::Error> {
+    simple: bool,
+) -> Result<DeriveTemplateCommon, syn::Error> {
     let strct = syn::parse2::<ItemStruct>(tokens)?;
 
     let mut all_options = DeriveTemplateOptions::default();
@@ -191,14 +295,19 @@ fn derive_template_common_impl(
         resolve_template_file(&*path.value(), &*config.template_dirs)
             .and_then(|path| path.canonicalize().ok())
             .ok_or_else(|| {
-                syn::Error::new(
-                    path.span(),
-                    format!("Template file {:?} not found", path.value()),
-                )
+                let msg = if Path::new(&*path.value()).is_absolute() {
+                    format!(
+                        "Template file {:?} not found (absolute paths are not joined with template directories)",
+                        path.value()
+                    )
+                } else {
+                    format!("Template file {:?} not found", path.value())
+                };
+                syn::Error::new(path.span(), msg)
             })?
     };
 
-    merge_config_options(&mut config, &all_options);
+    merge_config_options(&mut config, &all_options)?;
 
     // Template compilation through this proc-macro uses a caching mechanism. Output file
     // names include a hash calculated from input file contents and compiler
@@ -234,13 +343,15 @@ fn derive_template_common_impl(
         }
     }
 
-    let deps = with_compiler(config, |compiler| {
+    let fragments_path = output_file.with_extension("fragments");
+    let (deps, field_refs) = with_compiler(config, |compiler| {
         let dep_path = output_file.with_extension("deps");
+        let fields_path = output_file.with_extension("fields");
         let lock_path = output_file.with_extension("lock");
         let lock = Lock::new(&lock_path);
         match lock {
             Ok(lock) => {
-                let (tsource, report) = compiler.resolve_file(&input_file)?;
+                let (tsource, mut report) = compiler.resolve_file(&input_file)?;
 
                 let output_filetime = filetime(&output_file);
                 let input_filetime = iter::once(&input_file)
@@ -286,8 +397,38 @@ fn derive_template_common_impl(
                     );
                 }
 
+                // Named blocks are compiled into a separate file which is included by
+                // `render_fragment_to()` method.
+                match report.fragments.take() {
+                    Some(fragments) if input_filetime > filetime(&fragments_path) => {
+                        compiler.compile_file(&input_file, fragments, &fragments_path)?;
+                        let _ = filetime::set_file_times(
+                            &fragments_path,
+                            input_filetime,
+                            input_filetime,
+                        );
+                    }
+                    Some(_) => {}
+                    None => {
+                        let _ = std::fs::remove_file(&fragments_path);
+                    }
+                }
+
+                // Write out field references for concurrent processes to reuse.
+                let mut fields_file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&fields_path)
+                    .unwrap_or_else(|e| {
+                        panic!("Failed to open {:?}: {}", fields_path, e)
+                    });
+                for field_ref in &report.field_refs {
+                    writeln!(&mut fields_file, "{}", field_ref).unwrap();
+                }
+
                 drop(lock);
-                Ok(report.deps)
+                Ok((report.deps, report.field_refs))
             }
             // Lock file exists, template is already (currently being?) compiled.
             Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
@@ -300,12 +441,18 @@ fn derive_template_common_impl(
                     thread::sleep(Duration::from_millis(10));
                 }
 
-                Ok(std::fs::read_to_string(&dep_path)
+                let deps = std::fs::read_to_string(&dep_path)
                     .unwrap()
                     .trim()
                     .lines()
                     .map(PathBuf::from)
-                    .collect())
+                    .collect();
+                let field_refs = std::fs::read_to_string(&fields_path)
+                    .unwrap_or_default()
+                    .lines()
+                    .map(String::from)
+                    .collect();
+                Ok((deps, field_refs))
             }
             Err(e) => panic!("{:?}: {}. Maybe try `cargo clean`?", lock_path, e),
         }
@@ -319,6 +466,17 @@ fn derive_template_common_impl(
         .to_str()
         .unwrap_or_else(|| panic!("Non UTF-8 file name: {:?}", output_file));
 
+    let fragments_file_string = if fragments_path.is_file() {
+        Some(
+            fragments_path
+                .to_str()
+                .unwrap_or_else(|| panic!("Non UTF-8 file name: {:?}", fragments_path))
+                .to_string(),
+        )
+    } else {
+        None
+    };
+
     let mut include_bytes_seq = quote! { include_bytes!(#input_file_string); };
     for dep in deps {
         if let Some(dep_string) = dep.to_str() {
@@ -326,12 +484,40 @@ fn derive_template_common_impl(
         }
     }
 
-    Ok((strct, include_bytes_seq, output_file_string.to_string()))
+    // UTF-8 byte order mark is only written by the top-level render methods, so that
+    // it precedes any content and is never duplicated by nested templates.
+    let bom_seq = if all_options.bom.as_ref().map(|b| b.value).unwrap_or(false) {
+        quote! { buf.push_str("\u{feff}"); }
+    } else {
+        TokenStream::new()
+    };
+
+    let used_fields_impl = if all_options
+        .used_fields
+        .as_ref()
+        .map(|b| b.value)
+        .unwrap_or(false)
+    {
+        derive_used_fields_impl(&strct, &field_refs, simple)
+    } else {
+        TokenStream::new()
+    };
+
+    Ok((
+        strct,
+        include_bytes_seq,
+        bom_seq,
+        used_fields_impl,
+        output_file_string.to_string(),
+        fragments_file_string,
+        all_options,
+    ))
 }
 
 fn derive_template_once_only_impl(
     strct: &ItemStruct,
     include_bytes_seq: &TokenStream,
+    bom_seq: &TokenStream,
     output_file_string: &String,
 ) -> TokenStream {
     let name = &strct.ident;
@@ -347,6 +533,7 @@ fn derive_template_once_only_impl(
                 static SIZE_HINT: SizeHint = SizeHint::new();
 
                 let mut buf = Buffer::with_capacity(SIZE_HINT.get());
+                #bom_seq
                 self.render_once_to(&mut buf)?;
                 SIZE_HINT.update(buf.len());
 
@@ -369,6 +556,7 @@ fn derive_template_once_only_impl(
 fn derive_template_mut_only_impl(
     strct: &ItemStruct,
     include_bytes_seq: &TokenStream,
+    bom_seq: &TokenStream,
     output_file_string: &String,
 ) -> TokenStream {
     let name = &strct.ident;
@@ -383,6 +571,7 @@ fn derive_template_mut_only_impl(
                 static SIZE_HINT: SizeHint = SizeHint::new();
 
                 let mut buf = Buffer::with_capacity(SIZE_HINT.get());
+                #bom_seq
                 self.render_mut_to(&mut buf)?;
                 SIZE_HINT.update(buf.len());
 
@@ -405,6 +594,7 @@ fn derive_template_mut_only_impl(
 fn derive_template_only_impl(
     strct: &ItemStruct,
     include_bytes_seq: &TokenStream,
+    bom_seq: &TokenStream,
     output_file_string: &String,
 ) -> TokenStream {
     let name = &strct.ident;
@@ -419,6 +609,7 @@ fn derive_template_only_impl(
                 static SIZE_HINT: SizeHint = SizeHint::new();
 
                 let mut buf = Buffer::with_capacity(SIZE_HINT.get());
+                #bom_seq
                 self.render_to(&mut buf)?;
                 SIZE_HINT.update(buf.len());
 
@@ -438,81 +629,244 @@ fn derive_template_only_impl(
     }
 }
 
-fn derive_template_once_impl(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
-    let (strct, include_bytes_seq, output_file_string) =
-        derive_template_common_impl(tokens)?;
+fn derive_used_fields_impl(
+    strct: &ItemStruct,
+    field_refs: &[String],
+    simple: bool,
+) -> TokenStream {
+    let name = &strct.ident;
+    let (impl_generics, ty_generics, where_clause) = strct.generics.split_for_impl();
+
+    // `TemplateSimple` destructures the fields into local variables
+    let used_fields = strct
+        .fields
+        .iter()
+        .filter_map(|f| f.ident.as_ref().map(|i| i.to_string()))
+        .filter(|field| {
+            let field_ref = if simple {
+                field.clone()
+            } else {
+                format!("self.{}", field)
+            };
+            field_refs.contains(&field_ref)
+        });
+
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Names of the struct fields referenced by the template
+            pub const USED_FIELDS: &'static [&'static str] = &[#(#used_fields),*];
+        }
+    }
+}
 
-    let mut output = TokenStream::new();
+// `receiver` is the receiver of the rendering methods of the derived trait, and
+// `bindings` are the statements to run before rendering the fragment
+fn derive_fragment_impl(
+    strct: &ItemStruct,
+    receiver: &TokenStream,
+    bindings: &TokenStream,
+    include_bytes_seq: &TokenStream,
+    fragments_file_string: &str,
+) -> TokenStream {
+    let name = &strct.ident;
+    let (impl_generics, ty_generics, where_clause) = strct.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Render the contents of the named block only
+            pub fn render_fragment(#receiver, name: &str) -> sailfish::RenderResult {
+                let mut buf = sailfish::runtime::Buffer::new();
+                self.render_fragment_to(name, &mut buf)?;
+                Ok(buf.into_string())
+            }
+
+            /// Render the contents of the named block only into the buffer
+            pub fn render_fragment_to(#receiver, __sf_fragment: &str, __sf_buf: &mut sailfish::runtime::Buffer) -> std::result::Result<(), sailfish::runtime::RenderError> {
+                // This line is required for cargo to track child templates
+                #include_bytes_seq;
+
+                use sailfish::runtime as __sf_rt;
+                #bindings
+                include!(#fragments_file_string);
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn derive_display_impl(strct: &ItemStruct) -> TokenStream {
+    let name = &strct.ident;
+    let (impl_generics, ty_generics, where_clause) = strct.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics std::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let mut buf = sailfish::runtime::Buffer::new();
+                sailfish::Template::render_to(self, &mut buf).map_err(|_| std::fmt::Error)?;
+                f.write_str(buf.as_str())
+            }
+        }
+    }
+}
+
+// `Display` can only be implemented for templates which can be rendered by reference
+fn check_impl_display(options: &DeriveTemplateOptions) -> Result<(), syn::Error> {
+    match options.impl_display {
+        Some(ref lit) if lit.value => Err(syn::Error::new(
+            lit.span(),
+            "`impl_display` option requires `#[derive(Template)]`",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn derive_template_once_impl(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
+    let (
+        strct,
+        include_bytes_seq,
+        bom_seq,
+        used_fields_impl,
+        output_file_string,
+        fragments_file_string,
+        options,
+    ) = derive_template_common_impl(tokens, false)?;
+    check_impl_display(&options)?;
+
+    let mut output = used_fields_impl;
 
     output.append_all(derive_template_once_only_impl(
         &strct,
         &include_bytes_seq,
+        &bom_seq,
         &output_file_string,
     ));
 
+    if let Some(ref fragments_file_string) = fragments_file_string {
+        output.append_all(derive_fragment_impl(
+            &strct,
+            &quote!(self),
+            &TokenStream::new(),
+            &include_bytes_seq,
+            fragments_file_string,
+        ));
+    }
+
     Ok(output)
 }
 
 fn derive_template_mut_impl(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
-    let (strct, include_bytes_seq, output_file_string) =
-        derive_template_common_impl(tokens)?;
-
-    let mut output = TokenStream::new();
+    let (
+        strct,
+        include_bytes_seq,
+        bom_seq,
+        used_fields_impl,
+        output_file_string,
+        fragments_file_string,
+        options,
+    ) = derive_template_common_impl(tokens, false)?;
+    check_impl_display(&options)?;
+
+    let mut output = used_fields_impl;
 
     output.append_all(derive_template_once_only_impl(
         &strct,
         &include_bytes_seq,
+        &bom_seq,
         &output_file_string,
     ));
 
     output.append_all(derive_template_mut_only_impl(
         &strct,
         &include_bytes_seq,
+        &bom_seq,
         &output_file_string,
     ));
 
+    if let Some(ref fragments_file_string) = fragments_file_string {
+        output.append_all(derive_fragment_impl(
+            &strct,
+            &quote!(&mut self),
+            &TokenStream::new(),
+            &include_bytes_seq,
+            fragments_file_string,
+        ));
+    }
+
     Ok(output)
 }
 
 fn derive_template_impl(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
-    let (strct, include_bytes_seq, output_file_string) =
-        derive_template_common_impl(tokens)?;
-
-    let mut output = TokenStream::new();
+    let (
+        strct,
+        include_bytes_seq,
+        bom_seq,
+        used_fields_impl,
+        output_file_string,
+        fragments_file_string,
+        options,
+    ) = derive_template_common_impl(tokens, false)?;
+
+    let mut output = used_fields_impl;
 
     output.append_all(derive_template_once_only_impl(
         &strct,
         &include_bytes_seq,
+        &bom_seq,
         &output_file_string,
     ));
 
     output.append_all(derive_template_mut_only_impl(
         &strct,
         &include_bytes_seq,
+        &bom_seq,
         &output_file_string,
     ));
 
     output.append_all(derive_template_only_impl(
         &strct,
         &include_bytes_seq,
+        &bom_seq,
         &output_file_string,
     ));
 
+    if let Some(ref fragments_file_string) = fragments_file_string {
+        output.append_all(derive_fragment_impl(
+            &strct,
+            &quote!(&self),
+            &TokenStream::new(),
+            &include_bytes_seq,
+            fragments_file_string,
+        ));
+    }
+
+    if options.impl_display.map(|b| b.value).unwrap_or(false) {
+        output.append_all(derive_display_impl(&strct));
+    }
+
     Ok(output)
 }
 
 fn derive_template_simple_impl(tokens: TokenStream) -> Result<TokenStream, syn::Error> {
-    let (strct, include_bytes_seq, output_file_string) =
-        derive_template_common_impl(tokens)?;
+    let (
+        strct,
+        include_bytes_seq,
+        bom_seq,
+        used_fields_impl,
+        output_file_string,
+        fragments_file_string,
+        options,
+    ) = derive_template_common_impl(tokens, true)?;
+    check_impl_display(&options)?;
 
     let name = &strct.ident;
 
     let field_names: Punctuated<Ident, Token![,]> = match strct.fields {
-        Fields::Named(fields) => fields
+        Fields::Named(ref fields) => fields
             .named
-            .into_iter()
+            .iter()
             .map(|f| {
-                f.ident.expect(
+                f.ident.clone().expect(
                     "Internal error: Failed to get field name (error code: 73621)",
                 )
             })
@@ -531,13 +885,16 @@ fn derive_template_simple_impl(tokens: TokenStream) -> Result<TokenStream, syn::
     // render_once method always results in the same code.
     // This method can be implemented in `sailfish` crate, but I found that performance
     // drops when the implementation is written in `sailfish` crate.
-    Ok(quote! {
+    let mut output = quote! {
+        #used_fields_impl
+
         impl #impl_generics sailfish::TemplateSimple for #name #ty_generics #where_clause {
             fn render_once(self) -> sailfish::RenderResult {
                 use sailfish::runtime::{Buffer, SizeHint};
                 static SIZE_HINT: SizeHint = SizeHint::new();
 
                 let mut buf = Buffer::with_capacity(SIZE_HINT.get());
+                #bom_seq
                 self.render_once_to(&mut buf)?;
                 SIZE_HINT.update(buf.len());
 
@@ -555,7 +912,20 @@ fn derive_template_simple_impl(tokens: TokenStream) -> Result<TokenStream, syn::
                 Ok(())
             }
         }
-    })
+    };
+
+    if let Some(ref fragments_file_string) = fragments_file_string {
+        // fragments may not refer to all the fields
+        output.append_all(derive_fragment_impl(
+            &strct,
+            &quote!(self),
+            &quote!(#[allow(unused_variables)] let #name { #field_names } = self;),
+            &include_bytes_seq,
+            fragments_file_string,
+        ));
+    }
+
+    Ok(output)
 }
 
 pub fn derive_template_once(tokens: TokenStream) -> TokenStream {
@@ -573,3 +943,56 @@ pub fn derive_template(tokens: TokenStream) -> TokenStream {
 pub fn derive_template_simple(tokens: TokenStream) -> TokenStream {
     derive_template_simple_impl(tokens).unwrap_or_else(|e| e.to_compile_error())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_absolute_path() {
+        let dir = env::temp_dir().join(format!("sailfish-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("absolute.stpl");
+        std::fs::write(&file, "Hello, World!").unwrap();
+
+        // absolute paths bypass template directories
+        let template_dirs = vec![PathBuf::from("templates")];
+        assert_eq!(
+            resolve_template_file(file.to_str().unwrap(), &template_dirs),
+            Some(file.clone())
+        );
+
+        // relative paths are joined with template directories
+        assert_eq!(
+            resolve_template_file("absolute.stpl", std::slice::from_ref(&dir)),
+            Some(dir.join("absolute.stpl"))
+        );
+
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(
+            resolve_template_file(file.to_str().unwrap(), &template_dirs),
+            None
+        );
+
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn include_absolute_path() {
+        let dir =
+            env::temp_dir().join(format!("sailfish-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("partials")).unwrap();
+        let child = dir.join("partials").join("child.stpl");
+        std::fs::write(&child, "Hello, World!").unwrap();
+        let parent = dir.join("parent.stpl");
+        std::fs::write(&parent, format!("<% include!({:?}); %>", child)).unwrap();
+
+        // absolute paths in includes are not joined with the directory of the parent
+        let (tsource, report) = Compiler::new().resolve_file(&parent).unwrap();
+        assert_eq!(report.deps, vec![child.clone()]);
+        let ast = &tsource.ast;
+        assert!(quote!(#ast).to_string().contains("\"Hello, World!\""));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
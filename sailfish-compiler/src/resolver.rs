@@ -1,8 +1,12 @@
-use quote::quote;
+use proc_macro2::Span;
+use quote::{quote, ToTokens};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use syn::parse::{Parse, ParseStream, Result as ParseResult};
+use syn::punctuated::Punctuated;
 use syn::visit_mut::VisitMut;
-use syn::{Block, Expr, ExprBlock, LitStr, Macro, Stmt};
+use syn::{Block, Expr, ExprBlock, Ident, Label, Lifetime, LitStr, Macro, Stmt, Token};
 
 use crate::error::*;
 
@@ -26,6 +30,7 @@ macro_rules! return_if_some {
 #[derive(Clone)]
 pub struct Resolver<'h> {
     include_handler: Arc<dyn 'h + Fn(&Path) -> Result<Block, Error>>,
+    recursion_limit: usize,
 }
 
 impl<'h> Resolver<'h> {
@@ -36,9 +41,17 @@ impl<'h> Resolver<'h> {
                     "You cannot use `include` macro inside templates".to_owned()
                 )))
             }),
+            recursion_limit: 8,
         }
     }
 
+    /// maximum depth of the recursive includes
+    #[inline]
+    pub fn recursion_limit(mut self, new: usize) -> Resolver<'h> {
+        self.recursion_limit = new;
+        self
+    }
+
     #[inline]
     pub fn include_handler(
         mut self,
@@ -59,19 +72,38 @@ impl<'h> Resolver<'h> {
             deps: Vec::new(),
             error: None,
             include_handler: Arc::clone(&self.include_handler),
+            recursion_limit: self.recursion_limit,
+            include_stack: Vec::new(),
+            include_count: 0,
         };
-        child.visit_block_mut(ast);
+        child.resolve_template(ast)?;
 
-        if let Some(e) = child.error {
-            Err(e)
-        } else {
-            Ok(ResolveReport { deps: child.deps })
+        let mut collector = FragmentCollector {
+            fragments: Vec::new(),
+        };
+        collector.visit_block_mut(ast);
+
+        let mut remover = LabelRemover {
+            prefixes: &[BLOCK_LABEL_PREFIX, SLOT_LABEL_PREFIX],
+        };
+        remover.visit_block_mut(ast);
+        expand_cycles(ast)?;
+        for (_, fragment) in collector.fragments.iter_mut() {
+            remover.visit_block_mut(fragment);
+            expand_cycles(fragment)?;
         }
+
+        Ok(ResolveReport {
+            deps: child.deps,
+            fragments: collector.fragments,
+        })
     }
 }
 
 pub struct ResolveReport {
     pub deps: Vec<PathBuf>,
+    /// contents of the named blocks in the resolved template
+    pub fragments: Vec<(String, Block)>,
 }
 
 struct ResolverImpl<'h> {
@@ -79,12 +111,104 @@ struct ResolverImpl<'h> {
     deps: Vec<PathBuf>,
     error: Option<Error>,
     include_handler: Arc<dyn 'h + Fn(&Path) -> Result<Block, Error>>,
+    recursion_limit: usize,
+    // includes which are being resolved
+    include_stack: Vec<IncludeFrame>,
+    include_count: usize,
+}
+
+struct IncludeFrame {
+    path: PathBuf,
+    params: Vec<Ident>,
+    // name of the function to include the template recursively
+    func: Ident,
+    has_slots: bool,
+    recursive: bool,
 }
 
 impl<'h> ResolverImpl<'h> {
+    // resolve includes and template inheritance of the current template
+    fn resolve_template(&mut self, ast: &mut Block) -> Result<(), Error> {
+        self.visit_block_mut(ast);
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        let parent_template_file = match self.take_extends(ast)? {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        if self.path_stack.contains(&parent_template_file) {
+            let msg = format!("cyclic extends detected: {:?}", parent_template_file);
+            return Err(make_error!(ErrorKind::AnalyzeError(msg)));
+        }
+
+        // parse and translate the parent template
+        let mut parent = (*self.include_handler)(&parent_template_file)
+            .chain_err(|| format!("Failed to extend {:?}", parent_template_file))?;
+
+        self.path_stack.push(parent_template_file);
+        self.resolve_template(&mut parent)?;
+
+        let parent_template_file = self.path_stack.pop().unwrap();
+        if self.deps.iter().all(|p| p != &parent_template_file) {
+            self.deps.push(parent_template_file);
+        }
+
+        // override the blocks in the parent template with the child template blocks.
+        // contents outside of blocks in the child template are discarded.
+        let mut collector = BlockCollector {
+            prefix: BLOCK_LABEL_PREFIX,
+            blocks: HashMap::new(),
+        };
+        collector.visit_block_mut(ast);
+
+        let mut replacer = BlockReplacer {
+            prefix: BLOCK_LABEL_PREFIX,
+            blocks: collector.blocks,
+        };
+        replacer.visit_block_mut(&mut parent);
+
+        *ast = parent;
+        Ok(())
+    }
+
+    // remove `extends` directive from the top level of template, and returns the
+    // path to the parent template
+    fn take_extends(&mut self, ast: &mut Block) -> Result<Option<PathBuf>, Error> {
+        let idx = ast.stmts.iter().position(|stmt| {
+            matches!(*stmt, Stmt::Macro(ref sm) if sm.mac.path.is_ident("__sf_extends"))
+        });
+
+        let stmt = match idx {
+            Some(idx) => ast.stmts.remove(idx),
+            None => return Ok(None),
+        };
+
+        let arg = match stmt {
+            Stmt::Macro(ref sm) => sm.mac.parse_body::<LitStr>()?.value(),
+            _ => unreachable!(),
+        };
+
+        Ok(Some(self.resolve_path(&arg)))
+    }
+
+    // TODO: How should arguments be interpreted on Windows?
+    // relative paths are joined with the directory of the current template, while
+    // absolute paths are used as-is
+    fn resolve_path(&self, arg: &str) -> PathBuf {
+        let path = Path::new(arg);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.path_stack.last().unwrap().parent().unwrap().join(path)
+        }
+    }
+
     fn resolve_include(&mut self, mac: &Macro) -> Result<Expr, Error> {
-        let arg = match syn::parse2::<LitStr>(mac.tokens.clone()) {
-            Ok(l) => l.value(),
+        let IncludeArgs { path, params } = match syn::parse2(mac.tokens.clone()) {
+            Ok(args) => args,
             Err(e) => {
                 let mut e = Error::from(e);
                 e.chains.push(ErrorKind::AnalyzeError(
@@ -93,14 +217,17 @@ impl<'h> ResolverImpl<'h> {
                 return Err(e);
             }
         };
+        let arg = path.value();
 
         // resolve include! for rust file
         if arg.ends_with(".rs") {
-            let absolute_path = if Path::new(&*arg).is_absolute() {
-                PathBuf::from(&arg[1..])
-            } else {
-                self.path_stack.last().unwrap().parent().unwrap().join(arg)
-            };
+            if !params.is_empty() {
+                return Err(make_error!(ErrorKind::AnalyzeError(
+                    "cannot pass arguments when including rust file".to_owned()
+                )));
+            }
+
+            let absolute_path = self.resolve_path(&arg);
 
             return if let Some(absolute_path_str) = absolute_path.to_str() {
                 Ok(syn::parse2(quote! { include!(#absolute_path_str) }).unwrap())
@@ -114,34 +241,331 @@ impl<'h> ResolverImpl<'h> {
         }
 
         // resolve the template file path
-        // TODO: How should arguments be interpreted on Windows?
-        let child_template_file = if Path::new(&*arg).is_absolute() {
-            // absolute imclude
-            PathBuf::from(&arg[1..])
-        } else {
-            // relative include
-            self.path_stack.last().unwrap().parent().unwrap().join(arg)
-        };
+        let child_template_file = self.resolve_path(&arg);
+        self.include_template(
+            child_template_file,
+            &params,
+            SLOT_LABEL_PREFIX,
+            HashMap::new(),
+        )
+    }
+
+    // inline the child template, overriding its slots (or blocks if `prefix` is
+    // `BLOCK_LABEL_PREFIX`) with the given contents
+    fn include_template(
+        &mut self,
+        child_template_file: PathBuf,
+        params: &[(Ident, Expr)],
+        prefix: &'static str,
+        overrides: HashMap<String, Block>,
+    ) -> Result<Expr, Error> {
+        let child_template_file = child_template_file
+            .canonicalize()
+            .unwrap_or(child_template_file);
+        if let Some(frame) = self
+            .include_stack
+            .iter_mut()
+            .rev()
+            .find(|f| f.path == child_template_file)
+        {
+            return include_recursively(frame, params, !overrides.is_empty());
+        }
 
         // parse and translate the child template
         let mut blk = (*self.include_handler)(&*child_template_file).chain_err(|| {
             format!("Failed to include {:?}", child_template_file.clone())
         })?;
 
+        let func = Ident::new(
+            &format!("__sf_include_fn{}", self.include_count),
+            Span::call_site(),
+        );
+        self.include_count += 1;
+        self.include_stack.push(IncludeFrame {
+            path: child_template_file.clone(),
+            params: params.iter().map(|(name, _)| name.clone()).collect(),
+            func,
+            has_slots: !overrides.is_empty(),
+            recursive: false,
+        });
         self.path_stack.push(child_template_file);
         syn::visit_mut::visit_block_mut(self, &mut blk);
 
+        let frame = self.include_stack.pop().unwrap();
         let child_template_file = self.path_stack.pop().unwrap();
         if self.deps.iter().all(|p| p != &child_template_file) {
             self.deps.push(child_template_file);
         }
 
+        // slots which are not filled by the caller render their default contents
+        BlockReplacer {
+            prefix,
+            blocks: overrides,
+        }
+        .visit_block_mut(&mut blk);
+        LabelRemover {
+            prefixes: if prefix == BLOCK_LABEL_PREFIX {
+                &[BLOCK_LABEL_PREFIX, SLOT_LABEL_PREFIX]
+            } else {
+                &[SLOT_LABEL_PREFIX]
+            },
+        }
+        .visit_block_mut(&mut blk);
+
+        // arguments are bound to local variables in the child template
+        let param_names = params.iter().map(|(name, _)| name);
+        let param_values = params.iter().map(|(_, value)| value);
+
+        let mut blk = if frame.recursive {
+            // the template which includes itself is rendered by a closure, which
+            // receives the function to include the template again
+            let func = &frame.func;
+            let limit = self.recursion_limit;
+            let path = frame.path.to_string_lossy();
+            syn::parse2::<Block>(quote! {{
+                __sf_rt::recursive_include(
+                    (#(#param_values,)*),
+                    __sf_buf,
+                    #limit,
+                    #path,
+                    |#func, (#(#param_names,)*), __sf_buf| {
+                        #blk
+                        Ok(())
+                    },
+                )?;
+            }})
+            .unwrap()
+        } else if params.is_empty() {
+            blk
+        } else {
+            // all arguments are evaluated before binding any of them, so that an
+            // argument can refer to the variable shadowed by another argument
+            syn::parse2::<Block>(quote! {{
+                let (#(#param_names,)*) = (#(#param_values,)*);
+                #blk
+            }})
+            .unwrap()
+        };
+
+        // discard the partial output of the child template if rendering failed.
+        // Nested includes are rolled back by the outermost include, since errors
+        // are always propagated to the caller of the template.
+        if self.include_stack.is_empty() {
+            CommitInserter::default().visit_block_mut(&mut blk);
+            blk = syn::parse2::<Block>(quote! {{
+                let mut __sf_include_guard = __sf_rt::IncludeGuard::new(__sf_buf);
+                let __sf_buf = __sf_include_guard.buf();
+                #blk
+                __sf_include_guard.commit();
+            }})
+            .unwrap();
+        }
+
         Ok(Expr::Block(ExprBlock {
             attrs: Vec::new(),
             label: None,
             block: blk,
         }))
     }
+
+    // resolve `include_dyn!("dir/*/file.stpl", key, ...)`: every template matching
+    // the pattern is inlined, and one of them is selected by the key at runtime
+    fn resolve_dynamic_include(&mut self, mac: &Macro) -> Result<Expr, Error> {
+        let DynamicIncludeArgs {
+            pattern,
+            key,
+            params,
+        } = mac.parse_body::<DynamicIncludeArgs>().map_err(|e| {
+            let mut e = Error::from(e);
+            e.chains.push(ErrorKind::AnalyzeError(
+                "invalid arguments for `include_dyn` macro".to_owned(),
+            ));
+            e
+        })?;
+
+        let mut arms = Vec::new();
+        for (name, child_template_file) in self.find_candidates(&pattern.value())? {
+            let expr = self.include_template(
+                child_template_file,
+                &params,
+                SLOT_LABEL_PREFIX,
+                HashMap::new(),
+            )?;
+            arms.push(quote! { #name => #expr, });
+        }
+
+        Ok(syn::parse2(quote! {
+            match AsRef::<str>::as_ref(&(#key)) {
+                #(#arms)*
+                __sf_other => {
+                    return Err(__sf_rt::RenderError::Msg(format!(
+                        "No template found for {:?} in {:?}",
+                        __sf_other,
+                        #pattern
+                    )));
+                }
+            }
+        })
+        .unwrap())
+    }
+
+    // list the template files which match the pattern containing a `*` wildcard
+    fn find_candidates(&self, pattern: &str) -> Result<Vec<(String, PathBuf)>, Error> {
+        let error = |msg: String| make_error!(ErrorKind::AnalyzeError(msg));
+
+        if pattern.matches('*').count() != 1 {
+            return Err(error(format!(
+                "include_dyn pattern must contain exactly one `*`: {:?}",
+                pattern
+            )));
+        }
+
+        // the wildcard matches a part of the single path component
+        let star = pattern.find('*').unwrap();
+        let component_start = pattern[..star].rfind('/').map_or(0, |p| p + 1);
+        let component_end = pattern[star..]
+            .find('/')
+            .map_or(pattern.len(), |p| star + p);
+        let prefix = &pattern[component_start..star];
+        let suffix = &pattern[star + 1..component_end];
+        let rest = pattern[component_end..].trim_start_matches('/');
+
+        let dir = self.resolve_path(&pattern[..component_start]);
+        let entries = std::fs::read_dir(&dir)
+            .chain_err(|| format!("Failed to read directory {:?}", dir))?;
+
+        let mut candidates = Vec::new();
+        for entry in entries {
+            let entry =
+                entry.chain_err(|| format!("Failed to read directory {:?}", dir))?;
+            let file_name = entry.file_name();
+            let name = match file_name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if name.len() < prefix.len() + suffix.len()
+                || !name.starts_with(prefix)
+                || !name.ends_with(suffix)
+            {
+                continue;
+            }
+
+            let mut path = entry.path();
+            if !rest.is_empty() {
+                path.push(rest);
+            }
+            if path.is_file() {
+                let key = &name[prefix.len()..name.len() - suffix.len()];
+                candidates.push((key.to_owned(), path));
+            }
+        }
+
+        if candidates.is_empty() {
+            return Err(error(format!(
+                "No template matches the pattern {:?}",
+                pattern
+            )));
+        }
+
+        candidates.sort();
+        Ok(candidates)
+    }
+
+    // resolve `include` directive with the slot contents (or `embed` directive with
+    // the block contents) in the body
+    fn resolve_include_with_slots(
+        &mut self,
+        mac: &Macro,
+        prefix: &'static str,
+        body: &mut Block,
+    ) -> Result<Expr, Error> {
+        let IncludeArgs { path, params } = mac.parse_body::<IncludeArgs>()?;
+        let arg = path.value();
+        if arg.ends_with(".rs") {
+            let what = if prefix == BLOCK_LABEL_PREFIX {
+                "override blocks"
+            } else {
+                "fill slots"
+            };
+            return Err(make_error!(ErrorKind::AnalyzeError(format!(
+                "cannot {} of rust file",
+                what
+            ))));
+        }
+
+        // resolve nested includes in the slot contents
+        syn::visit_mut::visit_block_mut(self, body);
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        // contents outside of slots are discarded
+        let mut collector = BlockCollector {
+            prefix,
+            blocks: HashMap::new(),
+        };
+        collector.visit_block_mut(body);
+
+        let child_template_file = self.resolve_path(&arg);
+        self.include_template(child_template_file, &params, prefix, collector.blocks)
+    }
+}
+
+// arguments for `include` macro: `include!("path", name = value, ...)`
+struct IncludeArgs {
+    path: LitStr,
+    params: Vec<(Ident, Expr)>,
+}
+
+impl Parse for IncludeArgs {
+    fn parse(s: ParseStream) -> ParseResult<Self> {
+        let path = s.parse()?;
+        let params = parse_include_params(s)?;
+
+        Ok(Self { path, params })
+    }
+}
+
+// parse the trailing `, name = value` arguments
+fn parse_include_params(s: ParseStream) -> ParseResult<Vec<(Ident, Expr)>> {
+    let mut params = Vec::new();
+
+    while !s.is_empty() {
+        s.parse::<Token![,]>()?;
+        if s.is_empty() {
+            break;
+        }
+
+        let name = s.parse::<Ident>()?;
+        s.parse::<Token![=]>()?;
+        let value = s.parse::<Expr>()?;
+        params.push((name, value));
+    }
+
+    Ok(params)
+}
+
+// arguments for `include_dyn` macro: `include_dyn!("pattern", key, name = value, ...)`
+struct DynamicIncludeArgs {
+    pattern: LitStr,
+    key: Expr,
+    params: Vec<(Ident, Expr)>,
+}
+
+impl Parse for DynamicIncludeArgs {
+    fn parse(s: ParseStream) -> ParseResult<Self> {
+        let pattern = s.parse()?;
+        s.parse::<Token![,]>()?;
+        let key = s.parse()?;
+        let params = parse_include_params(s)?;
+
+        Ok(Self {
+            pattern,
+            key,
+            params,
+        })
+    }
 }
 
 impl<'h> VisitMut for ResolverImpl<'h> {
@@ -160,11 +584,29 @@ impl<'h> VisitMut for ResolverImpl<'h> {
                     return;
                 }
             }
+        } else if sm.mac.path.is_ident("include_dyn") {
+            match self.resolve_dynamic_include(&sm.mac) {
+                Ok(e) => *i = Stmt::Expr(e, None),
+                Err(e) => {
+                    self.error = Some(e);
+                }
+            }
         }
     }
 
     fn visit_expr_mut(&mut self, i: &mut Expr) {
         return_if_some!(self.error);
+        // resolve `include` directive with slots
+        if let Expr::Block(ref mut eb) = *i {
+            if let Some((mac, prefix)) = take_embed_macro(eb) {
+                match self.resolve_include_with_slots(&mac, prefix, &mut eb.block) {
+                    Ok(e) => *i = e,
+                    Err(e) => self.error = Some(e),
+                }
+                return;
+            }
+        }
+
         let em = matches_or_else!(*i, Expr::Macro(ref mut em), em, {
             syn::visit_mut::visit_expr_mut(self, i);
             return;
@@ -179,6 +621,361 @@ impl<'h> VisitMut for ResolverImpl<'h> {
                     return;
                 }
             }
+        } else if em.mac.path.is_ident("include_dyn") {
+            match self.resolve_dynamic_include(&em.mac) {
+                Ok(e) => *i = e,
+                Err(e) => {
+                    self.error = Some(e);
+                }
+            }
+        }
+    }
+}
+
+const BLOCK_LABEL_PREFIX: &str = "__sf_block_";
+const SLOT_LABEL_PREFIX: &str = "__sf_slot_";
+
+fn label_name(i: &ExprBlock, prefix: &str) -> Option<String> {
+    let label = i.label.as_ref()?;
+    let name = label.name.ident.to_string();
+    name.strip_prefix(prefix).map(|s| s.to_owned())
+}
+
+// take `include` directive (`{ __sf_embed!(...); ... }`) or `embed` directive
+// (`{ __sf_embed_blocks!(...); ... }`) from the block, and returns the macro with the
+// label prefix of the overridden contents
+fn take_embed_macro(i: &mut ExprBlock) -> Option<(Macro, &'static str)> {
+    let prefix = match i.block.stmts.first() {
+        Some(Stmt::Macro(ref sm)) if sm.mac.path.is_ident("__sf_embed") => {
+            SLOT_LABEL_PREFIX
+        }
+        Some(Stmt::Macro(ref sm)) if sm.mac.path.is_ident("__sf_embed_blocks") => {
+            BLOCK_LABEL_PREFIX
+        }
+        _ => return None,
+    };
+
+    match i.block.stmts.remove(0) {
+        Stmt::Macro(sm) => Some((sm.mac, prefix)),
+        _ => unreachable!(),
+    }
+}
+
+// collect the outermost labeled blocks
+struct BlockCollector {
+    prefix: &'static str,
+    blocks: HashMap<String, Block>,
+}
+
+impl VisitMut for BlockCollector {
+    fn visit_expr_block_mut(&mut self, i: &mut ExprBlock) {
+        if let Some(name) = label_name(i, self.prefix) {
+            let blk = std::mem::replace(
+                &mut i.block,
+                Block {
+                    brace_token: Default::default(),
+                    stmts: Vec::new(),
+                },
+            );
+            self.blocks.entry(name).or_insert(blk);
+        } else {
+            syn::visit_mut::visit_expr_block_mut(self, i);
+        }
+    }
+}
+
+// replace the labeled blocks with the collected blocks
+struct BlockReplacer {
+    prefix: &'static str,
+    blocks: HashMap<String, Block>,
+}
+
+impl VisitMut for BlockReplacer {
+    fn visit_expr_block_mut(&mut self, i: &mut ExprBlock) {
+        if let Some(blk) =
+            label_name(i, self.prefix).and_then(|name| self.blocks.remove(&name))
+        {
+            i.block = blk;
+        }
+
+        syn::visit_mut::visit_expr_block_mut(self, i);
+    }
+}
+
+// copy the contents of the named blocks which remain after resolution
+struct FragmentCollector {
+    fragments: Vec<(String, Block)>,
+}
+
+impl VisitMut for FragmentCollector {
+    fn visit_expr_block_mut(&mut self, i: &mut ExprBlock) {
+        if let Some(name) = label_name(i, BLOCK_LABEL_PREFIX) {
+            if self.fragments.iter().all(|(n, _)| *n != name) {
+                // `Block` does not implement `Clone`, so copy it through tokens
+                let tokens = i.block.to_token_stream();
+                let blk = syn::parse2::<Block>(tokens)
+                    .expect("Internal error: Failed to copy block contents");
+                self.fragments.push((name, blk));
+            }
+        }
+
+        syn::visit_mut::visit_expr_block_mut(self, i);
+    }
+}
+
+// expand `cycle!(a, b, ...)` into the expression which rotates through the arguments
+// each time it is evaluated. Counters are declared at the beginning of the template.
+fn expand_cycles(ast: &mut Block) -> Result<(), Error> {
+    let mut expander = CycleExpander {
+        count: 0,
+        error: None,
+    };
+    expander.visit_block_mut(ast);
+    if let Some(e) = expander.error {
+        return Err(e);
+    }
+
+    let counters = (0..expander.count).map(|n| {
+        let counter = Ident::new(&format!("__sf_cycle{}", n), Span::call_site());
+        syn::parse2::<Stmt>(quote! { let mut #counter = 0usize; }).unwrap()
+    });
+    ast.stmts.splice(0..0, counters);
+    Ok(())
+}
+
+struct CycleExpander {
+    count: usize,
+    error: Option<Error>,
+}
+
+impl CycleExpander {
+    fn expand(&mut self, mac: &Macro) -> Result<Expr, Error> {
+        let args = mac
+            .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+            .map_err(|e| {
+                let mut e = Error::from(e);
+                e.chains.push(ErrorKind::AnalyzeError(
+                    "invalid arguments for `cycle` macro".to_owned(),
+                ));
+                e
+            })?;
+
+        if args.is_empty() {
+            return Err(make_error!(ErrorKind::AnalyzeError(
+                "`cycle` macro requires at least one argument".to_owned()
+            )));
+        }
+
+        let counter = Ident::new(&format!("__sf_cycle{}", self.count), Span::call_site());
+        self.count += 1;
+
+        let len = args.len();
+        let indices = 0..len;
+        let args = args.iter();
+        Ok(syn::parse2(quote! {{
+            let __sf_index = #counter % #len;
+            #counter += 1;
+            match __sf_index {
+                #(#indices => #args,)*
+                _ => unreachable!(),
+            }
+        }})
+        .unwrap())
+    }
+}
+
+impl VisitMut for CycleExpander {
+    fn visit_expr_mut(&mut self, i: &mut Expr) {
+        return_if_some!(self.error);
+        if let Expr::Macro(ref em) = *i {
+            if em.mac.path.is_ident("cycle") {
+                match self.expand(&em.mac) {
+                    Ok(e) => *i = e,
+                    Err(e) => self.error = Some(e),
+                }
+                return;
+            }
+        }
+
+        syn::visit_mut::visit_expr_mut(self, i);
+    }
+
+    // expressions inside render macros are not visited by default
+    fn visit_macro_mut(&mut self, i: &mut Macro) {
+        return_if_some!(self.error);
+        let mut it = i.path.segments.iter();
+        if !(matches!(it.next(), Some(s) if s.ident == "__sf_rt")
+            && matches!(it.next(), Some(s) if s.ident == "render" || s.ident == "render_escaped")
+            && it.next().is_none())
+        {
+            return;
+        }
+
+        let parser = |s: ParseStream| -> ParseResult<(Ident, Expr)> {
+            let context = s.parse()?;
+            s.parse::<Token![,]>()?;
+            Ok((context, s.parse()?))
+        };
+
+        if let Ok((context, mut expr)) = i.parse_body_with(parser) {
+            self.visit_expr_mut(&mut expr);
+            i.tokens = quote! { #context, #expr };
+        }
+    }
+}
+
+// call the function to include the template which is being resolved
+fn include_recursively(
+    frame: &mut IncludeFrame,
+    params: &[(Ident, Expr)],
+    has_slots: bool,
+) -> Result<Expr, Error> {
+    if frame.has_slots || has_slots {
+        let msg = format!(
+            "cannot fill slots of the template which includes itself: {:?}",
+            frame.path
+        );
+        return Err(make_error!(ErrorKind::AnalyzeError(msg)));
+    }
+
+    // arguments are passed in the same order as the outer include
+    let mut args = Vec::with_capacity(frame.params.len());
+    for name in &frame.params {
+        match params.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => args.push(value),
+            None => {
+                let msg = format!(
+                    "missing argument `{}` for recursive include of {:?}",
+                    name, frame.path
+                );
+                return Err(make_error!(ErrorKind::AnalyzeError(msg)));
+            }
         }
     }
+    if let Some((name, _)) = params.iter().find(|(n, _)| !frame.params.contains(n)) {
+        let msg = format!(
+            "unexpected argument `{}` for recursive include of {:?}",
+            name, frame.path
+        );
+        return Err(make_error!(ErrorKind::AnalyzeError(msg)));
+    }
+
+    frame.recursive = true;
+    let func = &frame.func;
+    Ok(syn::parse2(quote! {{
+        #func((#(#args,)*), __sf_buf)?;
+    }})
+    .unwrap())
+}
+
+// commit the include guard before leaving the included template.
+//
+// The contents of the included template are inlined without a closure to keep the
+// semantics of `break`, `continue` and `return`, so the guard rolls back the buffer
+// when it is dropped by an error exit. Other exits are successful, and commit the
+// guard before jumping out of the template. Closures and items are not visited since
+// control flow cannot escape from them.
+#[derive(Default)]
+struct CommitInserter {
+    // loops and labeled blocks inside the included template
+    targets: Vec<(Option<Lifetime>, bool)>,
+}
+
+impl CommitInserter {
+    // whether `break` or `continue` jumps out of the included template
+    fn escapes(&self, label: &Option<Lifetime>) -> bool {
+        match *label {
+            Some(ref label) => !self.targets.iter().any(|t| t.0.as_ref() == Some(label)),
+            None => !self.targets.iter().any(|t| t.1),
+        }
+    }
+
+    // visit the body of a loop or labeled block
+    fn visit_target(&mut self, label: &Option<Label>, is_loop: bool, body: &mut Block) {
+        self.targets
+            .push((label.as_ref().map(|l| l.name.clone()), is_loop));
+        self.visit_block_mut(body);
+        self.targets.pop();
+    }
+}
+
+impl VisitMut for CommitInserter {
+    fn visit_expr_mut(&mut self, i: &mut Expr) {
+        match *i {
+            Expr::Closure(_) | Expr::Async(_) => {}
+            Expr::ForLoop(ref mut ef) => {
+                self.visit_expr_mut(&mut ef.expr);
+                self.visit_target(&ef.label, true, &mut ef.body);
+            }
+            Expr::While(ref mut ew) => {
+                // `break` in the condition exits the loop itself
+                self.targets
+                    .push((ew.label.as_ref().map(|l| l.name.clone()), true));
+                self.visit_expr_mut(&mut ew.cond);
+                self.visit_block_mut(&mut ew.body);
+                self.targets.pop();
+            }
+            Expr::Loop(ref mut el) => self.visit_target(&el.label, true, &mut el.body),
+            Expr::Block(ref mut eb) if eb.label.is_some() => {
+                self.visit_target(&eb.label, false, &mut eb.block)
+            }
+            Expr::Break(ref mut eb) if self.escapes(&eb.label) => {
+                let label = &eb.label;
+                *i = match eb.expr {
+                    Some(ref mut expr) => {
+                        self.visit_expr_mut(expr);
+                        syn::parse2(quote! {{
+                            let __sf_value = #expr;
+                            __sf_include_guard.commit();
+                            break #label __sf_value
+                        }})
+                    }
+                    None => syn::parse2(quote! {{
+                        __sf_include_guard.commit();
+                        break #label
+                    }}),
+                }
+                .unwrap();
+            }
+            Expr::Continue(ref ec) if self.escapes(&ec.label) => {
+                let label = &ec.label;
+                *i = syn::parse2(quote! {{
+                    __sf_include_guard.commit();
+                    continue #label
+                }})
+                .unwrap();
+            }
+            Expr::Return(ref mut er) => {
+                if let Some(ref mut expr) = er.expr {
+                    self.visit_expr_mut(expr);
+                    *i = syn::parse2(quote! {{
+                        let __sf_result: std::result::Result<(), __sf_rt::RenderError> = #expr;
+                        if __sf_result.is_ok() {
+                            __sf_include_guard.commit();
+                        }
+                        return __sf_result;
+                    }})
+                    .unwrap();
+                }
+            }
+            _ => syn::visit_mut::visit_expr_mut(self, i),
+        }
+    }
+
+    fn visit_item_mut(&mut self, _: &mut syn::Item) {}
+}
+
+// remove labels of blocks, which are no longer needed after resolution
+struct LabelRemover {
+    prefixes: &'static [&'static str],
+}
+
+impl VisitMut for LabelRemover {
+    fn visit_expr_block_mut(&mut self, i: &mut ExprBlock) {
+        if self.prefixes.iter().any(|p| label_name(i, p).is_some()) {
+            i.label = None;
+        }
+
+        syn::visit_mut::visit_expr_block_mut(self, i);
+    }
 }
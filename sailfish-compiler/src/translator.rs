@@ -1,7 +1,12 @@
 use proc_macro2::{Span, TokenStream};
 use quote::ToTokens;
+use syn::ext::IdentExt;
 use syn::parse::{Parse, ParseStream as SynParseStream, Result as ParseResult};
-use syn::{BinOp, Block, Expr};
+use syn::punctuated::Punctuated;
+use syn::{
+    parenthesized, BinOp, Block, Expr, ExprBinary, FnArg, Ident, LitBool, LitStr, Pat,
+    PatType, Token,
+};
 
 use crate::error::*;
 use crate::parser::{ParseStream, Token, TokenKind};
@@ -10,12 +15,20 @@ use crate::parser::{ParseStream, Token, TokenKind};
 #[derive(Clone, Debug, Default)]
 pub struct Translator {
     escape: bool,
+    filters: Option<String>,
+    filter_module: Option<String>,
+    translator: Option<String>,
 }
 
 impl Translator {
     #[inline]
     pub fn new() -> Self {
-        Self { escape: true }
+        Self {
+            escape: true,
+            filters: None,
+            filter_module: None,
+            translator: None,
+        }
     }
 
     #[inline]
@@ -24,6 +37,27 @@ impl Translator {
         self
     }
 
+    /// filter chain (e.g. `trim | lower`) applied to every evaluation block
+    #[inline]
+    pub fn filters(mut self, new: Option<String>) -> Self {
+        self.filters = new;
+        self
+    }
+
+    /// module path where the filter functions are looked up
+    #[inline]
+    pub fn filter_module(mut self, new: Option<String>) -> Self {
+        self.filter_module = new;
+        self
+    }
+
+    /// expression which evaluates to the translation backend used by `<%~ %>` tag
+    #[inline]
+    pub fn translator(mut self, new: Option<String>) -> Self {
+        self.translator = new;
+        self
+    }
+
     pub fn translate<'a>(
         &self,
         token_iter: ParseStream<'a>,
@@ -31,6 +65,15 @@ impl Translator {
         let original_source = token_iter.original_source;
 
         let mut ps = SourceBuilder::new(self.escape);
+        if let Some(ref filters) = self.filters {
+            ps.default_filters = parse_filter_chain(filters)?;
+        }
+        if let Some(ref filter_module) = self.filter_module {
+            ps.filter_module = parse_filter_module(filter_module)?;
+        }
+        if let Some(ref translator) = self.translator {
+            ps.translator = parse_translator(translator)?;
+        }
         ps.reserve(original_source.len());
         ps.feed_tokens(token_iter)?;
 
@@ -80,6 +123,30 @@ struct SourceBuilder {
     escape: bool,
     source: String,
     source_map: SourceMap,
+    block_stack: Vec<OpenTag>,
+    brace_stack: Vec<Brace>,
+    for_else_count: usize,
+    default_filters: Vec<(String, Option<String>)>,
+    filter_module: String,
+    translator: String,
+}
+
+// kind of the braces opened inside code blocks
+#[derive(Clone, Copy)]
+enum Brace {
+    // `start` is the position of `for` token, and `body` is the position after the
+    // opening brace
+    For { start: usize, body: usize },
+    // `capture name {`
+    Capture,
+    Other,
+}
+
+// directive which requires the corresponding `end` directive
+struct OpenTag {
+    keyword: &'static str,
+    name: String,
+    offset: usize,
 }
 
 impl SourceBuilder {
@@ -88,6 +155,12 @@ impl SourceBuilder {
             escape,
             source: String::from("{\n"),
             source_map: SourceMap::default(),
+            block_stack: Vec::new(),
+            brace_stack: Vec::new(),
+            for_else_count: 0,
+            default_filters: Vec::new(),
+            filter_module: String::from("sailfish::runtime::filter"),
+            translator: String::from("self.translator"),
         }
     }
 
@@ -106,15 +179,357 @@ impl SourceBuilder {
     }
 
     fn write_code<'a>(&mut self, token: &Token<'a>) -> Result<(), Error> {
+        if let Ok(directive) = syn::parse_str::<Directive>(token.as_str()) {
+            return self.write_directive(token, directive);
+        }
+
+        if is_else_branch(token.as_str()) {
+            if let Some(&Brace::For { start, body }) = self.brace_stack.last() {
+                self.brace_stack.pop();
+                self.write_for_else(token, start, body);
+                self.brace_stack.push(Brace::Other);
+                return Ok(());
+            }
+        }
+
+        if let Some(binding) = with_binding(token.as_str()) {
+            self.write_with(token, binding);
+            return Ok(());
+        }
+
+        if let Some(name) = capture_name(token.as_str()) {
+            self.write_capture(token, &name);
+            return Ok(());
+        }
+
+        if token.as_str().trim() == "}" {
+            if let Some(Brace::Capture) = self.brace_stack.last() {
+                self.brace_stack.pop();
+                self.source
+                    .push_str("}\n__sf_rt::Rendered::_new(__sf_buf)\n};\n");
+                return Ok(());
+            }
+        }
+
+        let (closes, opens) = unmatched_braces(token.as_str());
+        for _ in 0..closes {
+            if let Some(Brace::Capture) = self.brace_stack.pop() {
+                let mut err = make_error!(ErrorKind::ParseError(
+                    "capture block must be closed by `<% } %>` tag".to_owned()
+                ));
+                err.offset = Some(token.offset());
+                return Err(err);
+            }
+        }
+
         // TODO: automatically add missing tokens (e.g. ';', '{')
+        let start = self.source.len();
         self.write_token(token);
+        let body = self.source.len();
         self.source.push('\n');
+
+        if closes == 0 && opens == 1 && is_for_loop(token.as_str()) {
+            self.brace_stack.push(Brace::For { start, body });
+        } else {
+            self.brace_stack
+                .extend(std::iter::repeat_with(|| Brace::Other).take(opens));
+        }
+
         Ok(())
     }
 
+    // `for ... { ... } else { ... }`: the else branch is rendered only if the loop
+    // body was never executed.
+    fn write_for_else<'a>(&mut self, token: &Token<'a>, start: usize, body: usize) {
+        let flag = format!("__sf_for_empty{}", self.for_else_count);
+        self.for_else_count += 1;
+
+        // insert the flag variable declaration before the loop, and clear the flag
+        // at the beginning of loop body
+        self.insert_source(body, &format!(" {} = false;", flag));
+        self.insert_source(start, &format!("let mut {} = true;\n", flag));
+
+        self.source_map.entries.push(SourceMapEntry {
+            original: token.offset(),
+            new: self.source.len(),
+            length: 1,
+        });
+        self.source.push_str("} if ");
+        self.source.push_str(&flag);
+        self.source.push_str(" {\n");
+    }
+
+    // `with pat = expr { ... }`: the expression is evaluated once and bound to
+    // the pattern until the closing brace
+    fn write_with<'a>(&mut self, token: &Token<'a>, binding: WithBinding) {
+        self.source_map.entries.push(SourceMapEntry {
+            original: token.offset(),
+            new: self.source.len(),
+            length: 1,
+        });
+        self.source.push_str("{\nlet ");
+        self.source
+            .push_str(&binding.pat.into_token_stream().to_string());
+        self.source.push_str(" = ");
+        self.source
+            .push_str(&binding.expr.into_token_stream().to_string());
+        self.source.push_str(";\n");
+        self.brace_stack.push(Brace::Other);
+    }
+
+    // `capture name { ... }`: the contents are rendered into a new buffer, which is
+    // assigned to the variable
+    fn write_capture<'a>(&mut self, token: &Token<'a>, name: &Ident) {
+        self.source_map.entries.push(SourceMapEntry {
+            original: token.offset(),
+            new: self.source.len(),
+            length: 1,
+        });
+        self.source.push_str("let ");
+        self.source.push_str(&name.to_string());
+        self.source.push_str(
+            " = {\n\
+             let mut __sf_buf = __sf_rt::Buffer::new();\n\
+             {\n\
+             let __sf_buf = &mut __sf_buf;\n",
+        );
+        self.brace_stack.push(Brace::Capture);
+    }
+
+    fn insert_source(&mut self, pos: usize, s: &str) {
+        self.source.insert_str(pos, s);
+        for entry in self.source_map.entries.iter_mut() {
+            if entry.new >= pos {
+                entry.new += s.len();
+            }
+        }
+    }
+
+    fn write_directive<'a>(
+        &mut self,
+        token: &Token<'a>,
+        directive: Directive,
+    ) -> Result<(), Error> {
+        match directive {
+            Directive::Extends(path) => {
+                self.source.push_str("__sf_extends!(");
+                self.source.push_str(&path.into_token_stream().to_string());
+                self.source.push_str(");\n");
+            }
+            Directive::Block(name) => {
+                let name = name.unraw().to_string();
+                self.source.push_str("'__sf_block_");
+                self.source.push_str(&name);
+                self.source.push_str(": {\n");
+                self.open_tag("block", name, token);
+            }
+            Directive::Slot(name) => {
+                let name = name.unraw().to_string();
+                self.source.push_str("'__sf_slot_");
+                self.source.push_str(&name);
+                self.source.push_str(": {\n");
+                self.open_tag("slot", name, token);
+            }
+            Directive::Include(args) => {
+                self.source.push_str("{\n__sf_embed!(");
+                self.source.push_str(&args.to_string());
+                self.source.push_str(");\n");
+                self.open_tag("include", String::new(), token);
+            }
+            Directive::Embed(args) => {
+                self.source.push_str("{\n__sf_embed_blocks!(");
+                self.source.push_str(&args.to_string());
+                self.source.push_str(");\n");
+                self.open_tag("embed", String::new(), token);
+            }
+            Directive::Call(args) => {
+                // the body is rendered into a new buffer, and the included template
+                // can render it by calling `caller()`
+                self.source.push_str(
+                    "{\n\
+                     let __sf_caller = {\n\
+                     let mut __sf_buf = __sf_rt::Buffer::new();\n\
+                     {\n\
+                     let __sf_buf = &mut __sf_buf;\n",
+                );
+                self.open_tag("call", args.to_string(), token);
+            }
+            Directive::Def(name, params) => {
+                // fragment is compiled into a closure which renders the contents
+                // into a new buffer
+                self.source.push_str("let ");
+                self.source.push_str(&name.to_string());
+                self.source.push_str(" = |");
+                self.source
+                    .push_str(&params.into_token_stream().to_string());
+                self.source.push_str(
+                    "| -> std::result::Result<__sf_rt::Rendered, __sf_rt::RenderError> {\n\
+                     let mut __sf_buf = __sf_rt::Buffer::new();\n\
+                     {\n\
+                     let __sf_buf = &mut __sf_buf;\n",
+                );
+                self.open_tag("def", name.unraw().to_string(), token);
+            }
+            Directive::Autoescape(enabled) => {
+                // the region does not introduce a new scope
+                self.open_tag("autoescape", enabled.to_string(), token);
+            }
+            Directive::Switch(expr) => {
+                self.source.push_str("match ");
+                self.source.push_str(&expr.into_token_stream().to_string());
+                self.source.push_str(" {\n");
+                self.open_tag("switch", String::new(), token);
+            }
+            Directive::Case(pat, guard) => {
+                // close the previous arm
+                if self.in_tag("case") {
+                    self.block_stack.pop();
+                    self.source.push_str("}\n");
+                }
+                if !self.in_tag("switch") {
+                    let mut err = make_error!(ErrorKind::ParseError(
+                        "Unexpected `case` outside of `switch`".to_owned()
+                    ));
+                    err.offset = Some(token.offset());
+                    return Err(err);
+                }
+
+                self.source.push_str(&pat.into_token_stream().to_string());
+                if let Some(guard) = guard {
+                    self.source.push_str(" if ");
+                    self.source.push_str(&guard.into_token_stream().to_string());
+                }
+                self.source.push_str(" => {\n");
+                self.open_tag("case", String::new(), token);
+            }
+            Directive::Spaceless => {
+                // the contents are rendered into a new buffer, and whitespaces
+                // between tags are removed when the block is closed
+                self.source.push_str(
+                    "{\n\
+                     let mut __sf_spaceless = __sf_rt::Buffer::new();\n\
+                     {\n\
+                     let __sf_buf = &mut __sf_spaceless;\n",
+                );
+                self.open_tag("spaceless", String::new(), token);
+            }
+            Directive::End(keyword, name) => {
+                if keyword == "switch" && self.in_tag("case") {
+                    self.block_stack.pop();
+                    self.source.push_str("}\n");
+                }
+                let opened = self.close_tag(keyword, name, token)?;
+                if keyword == "def" {
+                    self.source
+                        .push_str("}\nOk(__sf_rt::Rendered::_new(__sf_buf))\n};\n");
+                } else if keyword == "call" {
+                    self.source
+                        .push_str("}\n__sf_rt::Rendered::_new(__sf_buf)\n};\ninclude!(");
+                    self.source.push_str(&opened.name);
+                    self.source.push_str(", caller = || &__sf_caller);\n}\n");
+                } else if keyword == "autoescape" {
+                    // nothing to close
+                } else if keyword == "spaceless" {
+                    self.source.push_str(
+                        "}\n\
+                         __sf_rt::render!(__sf_buf, \
+                         __sf_rt::filter::spaceless(__sf_spaceless.as_str()));\n\
+                         }\n",
+                    );
+                } else {
+                    self.source.push_str("}\n");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn open_tag<'a>(&mut self, keyword: &'static str, name: String, token: &Token<'a>) {
+        self.block_stack.push(OpenTag {
+            keyword,
+            name,
+            offset: token.offset(),
+        });
+    }
+
+    // check if the evaluation blocks should be escaped at the current position
+    fn escape_enabled(&self) -> bool {
+        self.block_stack
+            .iter()
+            .rev()
+            .find(|t| t.keyword == "autoescape")
+            .map_or(self.escape, |t| t.name == "true")
+    }
+
+    // render macro for the evaluation block. The HTML escaping is also used in
+    // `xml` escape mode, since it only produces the entities valid in XML.
+    fn render_method(&self, escape: bool) -> &'static str {
+        if escape && self.escape_enabled() {
+            "render_escaped"
+        } else {
+            "render"
+        }
+    }
+
+    // check if the innermost directive is `keyword`
+    fn in_tag(&self, keyword: &str) -> bool {
+        matches!(self.block_stack.last(), Some(t) if t.keyword == keyword)
+    }
+
+    fn close_tag<'a>(
+        &mut self,
+        keyword: &'static str,
+        name: Option<Ident>,
+        token: &Token<'a>,
+    ) -> Result<OpenTag, Error> {
+        let error = |msg: String| {
+            let mut err = make_error!(ErrorKind::ParseError(msg));
+            err.offset = Some(token.offset());
+            err
+        };
+
+        let opened = self.block_stack.pop().ok_or_else(|| {
+            error(format!(
+                "Unexpected `end{0}` without matching `{0}`",
+                keyword
+            ))
+        })?;
+
+        if opened.keyword != keyword {
+            return Err(error(format!(
+                "Expected `end{}`, found `end{}`",
+                opened.keyword, keyword
+            )));
+        }
+
+        if let Some(name) = name {
+            if name.unraw() != opened.name {
+                return Err(error(format!(
+                    "Expected `end{0} {1}`, found `end{0} {2}`",
+                    keyword, opened.name, name
+                )));
+            }
+        }
+
+        Ok(opened)
+    }
+
     fn write_text<'a>(&mut self, token: &Token<'a>) -> Result<(), Error> {
         use std::fmt::Write;
 
+        // whitespaces between `switch` and the first `case` are ignored
+        if self.in_tag("switch") {
+            if token.as_str().trim().is_empty() {
+                return Ok(());
+            }
+            let mut err = make_error!(ErrorKind::ParseError(
+                "Unexpected text between `switch` and `case`".to_owned()
+            ));
+            err.offset = Some(token.offset());
+            return Err(err);
+        }
+
         // if error has occured at the first byte of `render_text!` macro, it
         // will be mapped to the first byte of text
         self.source_map.entries.push(SourceMapEntry {
@@ -135,7 +550,7 @@ impl SourceBuilder {
         token: &Token<'a>,
         escape: bool,
     ) -> Result<(), Error> {
-        self.write_buffered_code_with_suffix(token, escape, "")
+        self.write_buffered_code_with_suffix(token, escape, "", true)
     }
 
     fn write_buffered_code_with_suffix<'a>(
@@ -143,6 +558,7 @@ impl SourceBuilder {
         token: &Token<'a>,
         escape: bool,
         suffix: &str,
+        default_filters: bool,
     ) -> Result<(), Error> {
         // parse and split off filter
         let code_block = syn::parse_str::<CodeBlock>(token.as_str()).map_err(|e| {
@@ -151,49 +567,48 @@ impl SourceBuilder {
             err.offset = into_offset(token.as_str(), span).map(|p| token.offset() + p);
             err
         })?;
-        let method = if self.escape && escape {
-            "render_escaped"
-        } else {
-            "render"
-        };
-
+        let method = self.render_method(escape);
         self.source.push_str("__sf_rt::");
         self.source.push_str(method);
         self.source.push_str("!(__sf_buf, ");
 
-        if let Some(filter) = code_block.filter {
+        let mut filters: Vec<_> = code_block
+            .filters
+            .into_iter()
+            .map(Filter::into_parts)
+            .collect();
+        if default_filters {
+            filters.extend(self.default_filters.iter().cloned());
+        }
+
+        if !filters.is_empty() {
             let expr_str = format!("{}{}", code_block.expr.into_token_stream(), suffix);
-            let (name, extra_args) = match filter {
-                Filter::Ident(i) => (i.to_string(), None),
-                Filter::Call(c) => (
-                    c.func.into_token_stream().to_string(),
-                    Some(c.args.into_token_stream().to_string()),
-                ),
-            };
 
-            self.source.push_str("sailfish::runtime::filter::");
-            self.source.push_str(&*name);
-            self.source.push('(');
+            // the last filter becomes the outermost function call
+            for (name, _) in filters.iter().rev() {
+                self.source.push_str(&self.filter_module);
+                self.source.push_str("::");
+                self.source.push_str(name);
+                self.source.push_str("(&(");
+            }
 
-            // arguments to filter function
-            {
-                self.source.push_str("&(");
-                let entry = SourceMapEntry {
-                    original: token.offset(),
-                    new: self.source.len(),
-                    length: expr_str.len(),
-                };
-                self.source_map.entries.push(entry);
-                self.source.push_str(&expr_str);
-                self.source.push(')');
+            let entry = SourceMapEntry {
+                original: token.offset(),
+                new: self.source.len(),
+                length: expr_str.len(),
+            };
+            self.source_map.entries.push(entry);
+            self.source.push_str(&expr_str);
 
+            // arguments to filter functions
+            for (_, extra_args) in filters {
+                self.source.push(')');
                 if let Some(extra_args) = extra_args {
                     self.source.push_str(", ");
-                    self.source.push_str(&*extra_args);
+                    self.source.push_str(&extra_args);
                 }
+                self.source.push(')');
             }
-
-            self.source.push(')');
         } else {
             self.write_token(token);
             self.source.push_str(suffix);
@@ -204,6 +619,44 @@ impl SourceBuilder {
         Ok(())
     }
 
+    // `<%~ "key", name = value %>` renders the message looked up by the
+    // translation backend
+    fn write_translation<'a>(&mut self, token: &Token<'a>) -> Result<(), Error> {
+        let translation = syn::parse_str::<Translation>(token.as_str()).map_err(|e| {
+            let span = e.span();
+            let mut err = make_error!(ErrorKind::RustSyntaxError(e));
+            err.offset = into_offset(token.as_str(), span).map(|p| token.offset() + p);
+            err
+        })?;
+        let method = self.render_method(true);
+
+        self.source_map.entries.push(SourceMapEntry {
+            original: token.offset(),
+            new: self.source.len(),
+            length: 1,
+        });
+        self.source.push_str("__sf_rt::");
+        self.source.push_str(method);
+        self.source.push_str("!(__sf_buf, ");
+        self.source.push_str("__sf_rt::i18n::translate(&(");
+        self.source.push_str(&self.translator);
+        self.source.push_str("), ");
+        self.source
+            .push_str(&translation.key.into_token_stream().to_string());
+        self.source.push_str(", &[");
+        for (name, value) in translation.args {
+            self.source.push_str("(\"");
+            self.source.push_str(&name.unraw().to_string());
+            self.source.push_str("\", &(");
+            self.source.push_str(&value.into_token_stream().to_string());
+            self.source.push_str(") as &dyn std::fmt::Display), ");
+        }
+        self.source.push_str("])");
+        self.source.push_str(");\n");
+
+        Ok(())
+    }
+
     pub fn feed_tokens<'a>(&mut self, token_iter: ParseStream<'a>) -> Result<(), Error> {
         let mut it = token_iter.peekable();
         while let Some(token) = it.next() {
@@ -214,10 +667,12 @@ impl SourceBuilder {
                 TokenKind::BufferedCode { escape } => {
                     self.write_buffered_code(&token, escape)?
                 }
+                TokenKind::Translation => self.write_translation(&token)?,
                 TokenKind::NestedTemplateOnce => self.write_buffered_code_with_suffix(
                     &token,
                     false,
                     ".render_once()?",
+                    false,
                 )?,
                 TokenKind::Text => {
                     // concatenate repeated text token
@@ -248,6 +703,15 @@ impl SourceBuilder {
     }
 
     pub fn finalize(mut self) -> Result<TranslatedSource, Error> {
+        if let Some(opened) = self.block_stack.pop() {
+            let mut err = make_error!(ErrorKind::ParseError(format!(
+                "Unterminated `{}` directive",
+                opened.keyword
+            )));
+            err.offset = Some(opened.offset);
+            return Err(err);
+        }
+
         self.source.push_str("\n}");
         match syn::parse_str::<Block>(&*self.source) {
             Ok(ast) => Ok(TranslatedSource {
@@ -270,11 +734,149 @@ impl SourceBuilder {
     }
 }
 
+// template-level directives written inside code blocks
+enum Directive {
+    Extends(LitStr),
+    Block(Ident),
+    Slot(Ident),
+    Include(TokenStream),
+    Call(TokenStream),
+    Embed(TokenStream),
+    Def(Ident, Punctuated<PatType, Token![,]>),
+    Spaceless,
+    Autoescape(bool),
+    Switch(Box<Expr>),
+    Case(Box<Pat>, Option<Box<Expr>>),
+    End(&'static str, Option<Ident>),
+}
+
+impl Parse for Directive {
+    fn parse(s: SynParseStream) -> ParseResult<Self> {
+        let keyword = s.call(Ident::parse_any)?;
+
+        let directive = if keyword == "extends" {
+            Directive::Extends(s.parse()?)
+        } else if keyword == "block" {
+            Directive::Block(s.call(Ident::parse_any)?)
+        } else if keyword == "slot" {
+            Directive::Slot(s.call(Ident::parse_any)?)
+        } else if keyword == "include" && s.peek(LitStr) {
+            Directive::Include(s.parse()?)
+        } else if keyword == "embed" && s.peek(LitStr) {
+            Directive::Embed(s.parse()?)
+        } else if keyword == "call" && s.peek(LitStr) {
+            Directive::Call(s.parse()?)
+        } else if keyword == "def" {
+            let name = s.parse()?;
+            let content;
+            parenthesized!(content in s);
+            let params = content.parse_terminated(
+                |s| match s.parse::<FnArg>()? {
+                    FnArg::Typed(t) => Ok(t),
+                    FnArg::Receiver(r) => {
+                        Err(syn::Error::new_spanned(r, "Unexpected `self` parameter"))
+                    }
+                },
+                Token![,],
+            )?;
+            Directive::Def(name, params)
+        } else if keyword == "spaceless" {
+            Directive::Spaceless
+        } else if keyword == "autoescape" {
+            Directive::Autoescape(s.parse::<LitBool>()?.value)
+        } else if keyword == "switch" {
+            Directive::Switch(s.parse()?)
+        } else if keyword == "case" {
+            let pat = s.call(Pat::parse_multi_with_leading_vert)?;
+            let guard = if s.parse::<Option<Token![if]>>()?.is_some() {
+                Some(s.parse()?)
+            } else {
+                None
+            };
+            Directive::Case(Box::new(pat), guard)
+        } else if let Some(end) = [
+            "block",
+            "slot",
+            "include",
+            "embed",
+            "call",
+            "def",
+            "spaceless",
+            "autoescape",
+            "switch",
+        ]
+        .iter()
+        .find(|k| keyword == format!("end{}", k))
+        {
+            if s.is_empty()
+                || [
+                    "include",
+                    "embed",
+                    "call",
+                    "spaceless",
+                    "autoescape",
+                    "switch",
+                ]
+                .contains(end)
+            {
+                Directive::End(end, None)
+            } else {
+                Directive::End(end, Some(s.call(Ident::parse_any)?))
+            }
+        } else {
+            return Err(s.error("Unknown directive"));
+        };
+
+        if !s.is_empty() {
+            return Err(s.error("Unexpected token after directive"));
+        }
+
+        Ok(directive)
+    }
+}
+
+// contents of translation tag
+struct Translation {
+    key: LitStr,
+    args: Vec<(Ident, Expr)>,
+}
+
+impl Parse for Translation {
+    fn parse(s: SynParseStream) -> ParseResult<Self> {
+        let key = s.parse()?;
+        let mut args = Vec::new();
+        while !s.is_empty() {
+            s.parse::<Token![,]>()?;
+            if s.is_empty() {
+                break;
+            }
+            let name = s.call(Ident::parse_any)?;
+            s.parse::<Token![=]>()?;
+            args.push((name, s.parse()?));
+        }
+
+        Ok(Self { key, args })
+    }
+}
+
 enum Filter {
     Ident(syn::Ident),
     Call(syn::ExprCall),
 }
 
+impl Filter {
+    // split into the function name and extra arguments
+    fn into_parts(self) -> (String, Option<String>) {
+        match self {
+            Filter::Ident(i) => (i.to_string(), None),
+            Filter::Call(c) => (
+                c.func.into_token_stream().to_string(),
+                Some(c.args.into_token_stream().to_string()),
+            ),
+        }
+    }
+}
+
 impl ToTokens for Filter {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
@@ -287,64 +889,251 @@ impl ToTokens for Filter {
 struct CodeBlock {
     #[allow(dead_code)]
     expr: Box<Expr>,
-    filter: Option<Filter>,
+    /// filters in the order of application
+    filters: Vec<Filter>,
+}
+
+impl CodeBlock {
+    fn split_filter(b: ExprBinary) -> ParseResult<(Box<Expr>, Option<Filter>)> {
+        match *b.right {
+            Expr::Call(c) => {
+                if let Expr::Path(ref p) = *c.func {
+                    if p.path.get_ident().is_some() {
+                        Ok((b.left, Some(Filter::Call(c))))
+                    } else {
+                        Err(syn::Error::new_spanned(p, "Invalid filter name"))
+                    }
+                } else {
+                    // if function in right side is not a path, fallback to
+                    // normal evaluation block
+                    Ok((b.left, None))
+                }
+            }
+            Expr::Path(p) => {
+                if let Some(i) = p.path.get_ident() {
+                    Ok((b.left, Some(Filter::Ident(i.clone()))))
+                } else {
+                    Err(syn::Error::new_spanned(p, "Invalid filter name"))
+                }
+            }
+            _ => Err(syn::Error::new_spanned(b, "Expected filter")),
+        }
+    }
+
+    // `a | f | g` is parsed as `(a | f) | g`, so filters in the left operand
+    // also belong to the chain
+    fn is_chained_filter(expr: &Expr) -> bool {
+        match expr {
+            Expr::Binary(b) if matches!(b.op, BinOp::BitOr(_)) => match *b.right {
+                Expr::Path(_) => true,
+                Expr::Call(ref c) => matches!(*c.func, Expr::Path(_)),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
 }
 
 impl Parse for CodeBlock {
     fn parse(s: SynParseStream) -> ParseResult<Self> {
-        let main = s.parse::<Expr>()?;
-
-        let code_block = match main {
-            Expr::Binary(b) if matches!(b.op, BinOp::BitOr(_)) => {
-                match *b.right {
-                    Expr::Call(c) => {
-                        if let Expr::Path(ref p) = *c.func {
-                            if p.path.get_ident().is_some() {
-                                CodeBlock {
-                                    expr: b.left,
-                                    filter: Some(Filter::Call(c)),
-                                }
-                            } else {
-                                return Err(syn::Error::new_spanned(
-                                    p,
-                                    "Invalid filter name",
-                                ));
-                            }
-                        } else {
-                            // if function in right side is not a path, fallback to
-                            // normal evaluation block
-                            CodeBlock {
-                                expr: b.left,
-                                filter: None,
-                            }
+        let mut expr = Box::new(s.parse::<Expr>()?);
+        let mut filters = Vec::new();
+
+        if matches!(*expr, Expr::Binary(ref b) if matches!(b.op, BinOp::BitOr(_))) {
+            loop {
+                let b = match *expr {
+                    Expr::Binary(b) => b,
+                    _ => unreachable!(),
+                };
+                let (left, filter) = Self::split_filter(b)?;
+                expr = left;
+
+                match filter {
+                    Some(filter) => filters.push(filter),
+                    None => break,
+                }
+
+                if !Self::is_chained_filter(&expr) {
+                    break;
+                }
+            }
+        }
+
+        filters.reverse();
+        Ok(CodeBlock { expr, filters })
+    }
+}
+
+fn parse_filter_chain(chain: &str) -> Result<Vec<(String, Option<String>)>, Error> {
+    if chain.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    match syn::parse_str::<CodeBlock>(&format!("_ | {}", chain)) {
+        Ok(code_block) if matches!(*code_block.expr, Expr::Infer(_)) => Ok(code_block
+            .filters
+            .into_iter()
+            .map(Filter::into_parts)
+            .collect()),
+        _ => Err(make_error!(ErrorKind::ConfigError(format!(
+            "Invalid filter chain: `{}`",
+            chain
+        )))),
+    }
+}
+
+fn parse_filter_module(path: &str) -> Result<String, Error> {
+    match syn::parse_str::<syn::Path>(path) {
+        Ok(path) => Ok(path.into_token_stream().to_string()),
+        Err(_) => Err(make_error!(ErrorKind::ConfigError(format!(
+            "Invalid filter module: `{}`",
+            path
+        )))),
+    }
+}
+
+fn parse_translator(expr: &str) -> Result<String, Error> {
+    match syn::parse_str::<Expr>(expr) {
+        Ok(expr) => Ok(expr.into_token_stream().to_string()),
+        Err(_) => Err(make_error!(ErrorKind::ConfigError(format!(
+            "Invalid translator: `{}`",
+            expr
+        )))),
+    }
+}
+
+// count the unmatched closing braces and opening braces in the code fragment
+fn unmatched_braces(code: &str) -> (usize, usize) {
+    let (mut closes, mut opens) = (0, 0);
+    let mut it = code.char_indices().peekable();
+
+    while let Some((i, c)) = it.next() {
+        match c {
+            '{' => opens += 1,
+            '}' => {
+                if opens > 0 {
+                    opens -= 1;
+                } else {
+                    closes += 1;
+                }
+            }
+            '/' if code[i..].starts_with("//") => {
+                // line comment
+                while it.next_if(|&(_, c)| c != '\n').is_some() {}
+            }
+            '/' if code[i..].starts_with("/*") => {
+                // block comment
+                let end = code[i + 2..].find("*/").map_or(code.len(), |p| i + p + 4);
+                while it.next_if(|&(j, _)| j < end).is_some() {}
+            }
+            'r' if code[i + 1..].starts_with('"')
+                || code[i + 1..].starts_with("#\"")
+                || code[i + 1..].starts_with("##") =>
+            {
+                // raw string literal
+                let hashes = code[i + 1..].chars().take_while(|&c| c == '#').count();
+                let terminator = format!("\"{}", "#".repeat(hashes));
+                let content = i + 2 + hashes;
+                let end = code
+                    .get(content..)
+                    .and_then(|s| s.find(&*terminator))
+                    .map_or(code.len(), |p| content + p + terminator.len());
+                while it.next_if(|&(j, _)| j < end).is_some() {}
+            }
+            '"' => {
+                // string literal
+                while let Some((_, c)) = it.next() {
+                    match c {
+                        '\\' => {
+                            it.next();
                         }
+                        '"' => break,
+                        _ => {}
                     }
-                    Expr::Path(p) => {
-                        if let Some(i) = p.path.get_ident() {
-                            CodeBlock {
-                                expr: b.left,
-                                filter: Some(Filter::Ident(i.clone())),
+                }
+            }
+            '\'' => {
+                // character literal or lifetime
+                let mut chars = code[i + 1..].chars();
+                match (chars.next(), chars.next()) {
+                    (Some('\\'), _) => {
+                        it.next();
+                        for (_, c) in it.by_ref() {
+                            if c == '\'' {
+                                break;
                             }
-                        } else {
-                            return Err(syn::Error::new_spanned(
-                                p,
-                                "Invalid filter name",
-                            ));
                         }
                     }
-                    _ => {
-                        return Err(syn::Error::new_spanned(b, "Expected filter"));
+                    (Some(_), Some('\'')) => {
+                        it.next();
+                        it.next();
                     }
+                    _ => {}
                 }
             }
-            _ => CodeBlock {
-                expr: Box::new(main),
-                filter: None,
-            },
-        };
+            _ => {}
+        }
+    }
+
+    (closes, opens)
+}
+
+// check if the code fragment is the beginning of for loop (e.g. `for x in xs {`)
+fn is_for_loop(code: &str) -> bool {
+    let mut code = code.trim_start();
+
+    // skip loop label
+    if code.starts_with('\'') {
+        match code.find(':') {
+            Some(pos) => code = code[pos + 1..].trim_start(),
+            None => return false,
+        }
+    }
+
+    code.starts_with("for")
+        && code[3..].starts_with(|c: char| c.is_whitespace() || c == '(')
+        && code.trim_end().ends_with('{')
+}
+
+// binding in `with pat = expr {`
+struct WithBinding {
+    pat: Pat,
+    expr: Expr,
+}
+
+impl Parse for WithBinding {
+    fn parse(s: SynParseStream) -> ParseResult<Self> {
+        let pat = s.call(Pat::parse_single)?;
+        s.parse::<Token![=]>()?;
+        let expr = s.parse()?;
+        Ok(Self { pat, expr })
+    }
+}
+
+fn with_binding(code: &str) -> Option<WithBinding> {
+    let rest = code.trim().strip_prefix("with")?.strip_suffix('{')?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    syn::parse_str::<WithBinding>(rest).ok()
+}
 
-        Ok(code_block)
+// extract the variable name from `capture name {`
+fn capture_name(code: &str) -> Option<Ident> {
+    let rest = code.trim().strip_prefix("capture")?.strip_suffix('{')?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
     }
+    syn::parse_str::<Ident>(rest.trim()).ok()
+}
+
+// check if the code fragment is `} else {`
+fn is_else_branch(code: &str) -> bool {
+    let code = code.trim();
+    code.starts_with('}')
+        && code.ends_with('{')
+        && code.len() >= 2
+        && code[1..code.len() - 1].trim() == "else"
 }
 
 fn into_offset(source: &str, span: Span) -> Option<usize> {
@@ -377,6 +1166,12 @@ mod tests {
             escape: true,
             source: String::with_capacity(token_iter.original_source.len()),
             source_map: SourceMap::default(),
+            block_stack: Vec::new(),
+            brace_stack: Vec::new(),
+            for_else_count: 0,
+            default_filters: Vec::new(),
+            filter_module: String::from("sailfish::runtime::filter"),
+            translator: String::from("self.translator"),
         };
         ps.feed_tokens(token_iter.clone()).unwrap();
         Translator::new().translate(token_iter).unwrap();
@@ -391,6 +1186,12 @@ mod tests {
             escape: true,
             source: String::with_capacity(token_iter.original_source.len()),
             source_map: SourceMap::default(),
+            block_stack: Vec::new(),
+            brace_stack: Vec::new(),
+            for_else_count: 0,
+            default_filters: Vec::new(),
+            filter_module: String::from("sailfish::runtime::filter"),
+            translator: String::from("self.translator"),
         };
         ps.feed_tokens(token_iter.clone()).unwrap();
         assert_eq!(
@@ -413,6 +1214,12 @@ mod tests {
             escape: true,
             source: String::with_capacity(token_iter.original_source.len()),
             source_map: SourceMap::default(),
+            block_stack: Vec::new(),
+            brace_stack: Vec::new(),
+            for_else_count: 0,
+            default_filters: Vec::new(),
+            filter_module: String::from("sailfish::runtime::filter"),
+            translator: String::from("self.translator"),
         };
         ps.feed_tokens(token_iter.clone()).unwrap();
         assert_eq!(
@@ -425,4 +1232,252 @@ mod tests {
             r#"{ __sf_rt :: render_text ! (__sf_buf , "outer ") ; __sf_rt :: render ! (__sf_buf , sailfish :: runtime :: filter :: upper (& (inner . render_once () ?))) ; __sf_rt :: render_text ! (__sf_buf , " outer") ; }"#
         );
     }
+
+    #[test]
+    fn translate_block() {
+        let src =
+            r#"<% extends "base.stpl" %><% block content %>foo<% endblock content %>"#;
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ __sf_extends ! ("base.stpl") ; '__sf_block_content : { __sf_rt :: render_text ! (__sf_buf , "foo") ; } }"#
+        );
+    }
+
+    #[test]
+    fn translate_unbalanced_block() {
+        let translate = |src| Translator::new().translate(Parser::new().parse(src));
+        assert!(translate("<% block a %>").is_err());
+        assert!(translate("<% endblock %>").is_err());
+        assert!(translate("<% block a %><% endblock b %>").is_err());
+        assert!(translate("<% slot a %><% endblock a %>").is_err());
+        assert!(translate(r#"<% include "a.stpl" %>"#).is_err());
+        assert!(translate(r#"<% include "a.stpl" %><% endinclude %>"#).is_ok());
+    }
+
+    #[test]
+    fn count_unmatched_braces() {
+        assert_eq!(unmatched_braces("for x in xs {"), (0, 1));
+        assert_eq!(unmatched_braces("} else {"), (1, 1));
+        assert_eq!(unmatched_braces("let p = Point { x: 1, y: 2 };"), (0, 0));
+        assert_eq!(unmatched_braces(r#"if s == "{" {"#), (0, 1));
+        assert_eq!(unmatched_braces(r##"if s == r#"}"# {"##), (0, 1));
+        assert_eq!(unmatched_braces("if c == '{' { // }"), (0, 1));
+        assert_eq!(unmatched_braces("fn f<'a>(x: &'a str) { /* } */"), (0, 1));
+        assert_eq!(unmatched_braces("} }"), (2, 0));
+    }
+
+    #[test]
+    fn translate_for_else() {
+        let src = "<% for x in xs { %>a<% } else { %>b<% } %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ let mut __sf_for_empty0 = true ; for x in xs { __sf_for_empty0 = false ; __sf_rt :: render_text ! (__sf_buf , "a") ; } if __sf_for_empty0 { __sf_rt :: render_text ! (__sf_buf , "b") ; } }"#
+        );
+    }
+
+    #[test]
+    fn translate_filter_chain() {
+        let src = "<%= name | upper | truncate(20) %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ __sf_rt :: render_escaped ! (__sf_buf , sailfish :: runtime :: filter :: truncate (& (sailfish :: runtime :: filter :: upper (& (name))) , 20)) ; }"#
+        );
+    }
+
+    #[test]
+    fn translate_default_filters() {
+        let src = "<%= a %><%- b | upper %><%+ c %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .filters(Some("trim | truncate(5)".to_owned()))
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ __sf_rt :: render_escaped ! (__sf_buf , sailfish :: runtime :: filter :: truncate (& (sailfish :: runtime :: filter :: trim (& (a))) , 5)) ; __sf_rt :: render ! (__sf_buf , sailfish :: runtime :: filter :: truncate (& (sailfish :: runtime :: filter :: trim (& (sailfish :: runtime :: filter :: upper (& (b))))) , 5)) ; __sf_rt :: render ! (__sf_buf , c . render_once () ?) ; }"#
+        );
+
+        let token_iter = Parser::new().parse(src);
+        assert!(Translator::new()
+            .filters(Some("a + b".to_owned()))
+            .translate(token_iter)
+            .is_err());
+    }
+
+    #[test]
+    fn translate_filter_module() {
+        let src = "<%= a | my_filter | upper %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .filter_module(Some("crate::filters".to_owned()))
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ __sf_rt :: render_escaped ! (__sf_buf , crate :: filters :: upper (& (crate :: filters :: my_filter (& (a))))) ; }"#
+        );
+
+        let token_iter = Parser::new().parse(src);
+        assert!(Translator::new()
+            .filter_module(Some("crate::".to_owned()))
+            .translate(token_iter)
+            .is_err());
+    }
+
+    #[test]
+    fn translate_translation_tag() {
+        let src = r#"<%~ "welcome.title", name = user.name %>"#;
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ __sf_rt :: render_escaped ! (__sf_buf , __sf_rt :: i18n :: translate (& (self . translator) , "welcome.title" , & [("name" , & (user . name) as & dyn std :: fmt :: Display) ,])) ; }"#
+        );
+
+        let token_iter = Parser::new().parse(r#"<%~ "hello" %>"#);
+        assert_eq!(
+            &Translator::new()
+                .translator(Some("self.i18n.locale(lang)".to_owned()))
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ __sf_rt :: render_escaped ! (__sf_buf , __sf_rt :: i18n :: translate (& (self . i18n . locale (lang)) , "hello" , & [])) ; }"#
+        );
+
+        let token_iter = Parser::new().parse(r#"<%~ "hello" %>"#);
+        assert!(Translator::new()
+            .translator(Some("self.".to_owned()))
+            .translate(token_iter)
+            .is_err());
+    }
+
+    #[test]
+    fn translate_capture() {
+        let src = "<% capture title { %>Hello <%= name %><% } %><%- title %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ let title = { let mut __sf_buf = __sf_rt :: Buffer :: new () ; { let __sf_buf = & mut __sf_buf ; __sf_rt :: render_text ! (__sf_buf , "Hello ") ; __sf_rt :: render_escaped ! (__sf_buf , name) ; } __sf_rt :: Rendered :: _new (__sf_buf) } ; __sf_rt :: render ! (__sf_buf , title) ; }"#
+        );
+
+        let src = "<% capture title { %>Hello<% } let a = 1; %>";
+        let token_iter = Parser::new().parse(src);
+        assert!(Translator::new().translate(token_iter).is_err());
+    }
+
+    #[test]
+    fn translate_spaceless() {
+        let src = "<% spaceless %><p>\n  <%= a %> </p>\n  <br>\n<% endspaceless %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ { let mut __sf_spaceless = __sf_rt :: Buffer :: new () ; { let __sf_buf = & mut __sf_spaceless ; __sf_rt :: render_text ! (__sf_buf , "<p>\n  ") ; __sf_rt :: render_escaped ! (__sf_buf , a) ; __sf_rt :: render_text ! (__sf_buf , " </p>\n  <br>\n") ; } __sf_rt :: render ! (__sf_buf , __sf_rt :: filter :: spaceless (__sf_spaceless . as_str ())) ; } }"#
+        );
+    }
+
+    #[test]
+    fn translate_call() {
+        let src = r#"<% call "panel.stpl", title = "Info" %>body<% endcall %>"#;
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ { let __sf_caller = { let mut __sf_buf = __sf_rt :: Buffer :: new () ; { let __sf_buf = & mut __sf_buf ; __sf_rt :: render_text ! (__sf_buf , "body") ; } __sf_rt :: Rendered :: _new (__sf_buf) } ; include ! ("panel.stpl" , title = "Info" , caller = || & __sf_caller) ; } }"#
+        );
+    }
+
+    #[test]
+    fn translate_switch() {
+        let src = "<% switch a %>\n  <% case Some(1) | None %>one<% case _ if b %>two<% endswitch %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ match a { Some (1) | None => { __sf_rt :: render_text ! (__sf_buf , "one") ; } _ if b => { __sf_rt :: render_text ! (__sf_buf , "two") ; } } }"#
+        );
+
+        let src = "<% switch a %>text<% case _ %><% endswitch %>";
+        let token_iter = Parser::new().parse(src);
+        assert!(Translator::new().translate(token_iter).is_err());
+
+        let src = "<% case _ %>";
+        let token_iter = Parser::new().parse(src);
+        assert!(Translator::new().translate(token_iter).is_err());
+    }
+
+    #[test]
+    fn translate_with() {
+        let src = "<% with (a, b) = f() { %><%= a %><% } %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ { let (a , b) = f () ; __sf_rt :: render_escaped ! (__sf_buf , a) ; } }"#
+        );
+    }
+
+    #[test]
+    fn translate_autoescape() {
+        let src = "<% autoescape false %><%= a %><% autoescape true %><%= b %><% endautoescape %><%= c %><% endautoescape %><%= d %>";
+        let token_iter = Parser::new().parse(src);
+        assert_eq!(
+            &Translator::new()
+                .translate(token_iter)
+                .unwrap()
+                .ast
+                .into_token_stream()
+                .to_string(),
+            r#"{ __sf_rt :: render ! (__sf_buf , a) ; __sf_rt :: render_escaped ! (__sf_buf , b) ; __sf_rt :: render ! (__sf_buf , c) ; __sf_rt :: render_escaped ! (__sf_buf , d) ; }"#
+        );
+    }
 }
@@ -0,0 +1,12 @@
+#![deny(unused_must_use)]
+
+use sailfish::TemplateOnce;
+use sailfish_macros::TemplateOnce;
+
+#[derive(TemplateOnce)]
+#[template(path = "empty.stpl")]
+struct UnusedResult;
+
+fn main() {
+    UnusedResult.render_once();
+}
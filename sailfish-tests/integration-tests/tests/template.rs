@@ -2,7 +2,7 @@
 extern crate sailfish_macros;
 
 use integration_tests::assert_string_eq;
-use sailfish::runtime::RenderResult;
+use sailfish::runtime::{Buffer, RenderResult};
 use sailfish::{Template, TemplateMut, TemplateOnce};
 use std::path::PathBuf;
 
@@ -100,6 +100,23 @@ fn custom_delimiter() {
     assert_render("custom_delimiter", CustomDelimiter);
 }
 
+#[derive(Template)]
+#[template(path = "custom_delimiters.stpl")]
+#[template(delimiters = ("{{", "}}"))]
+struct CustomDelimiters<'a> {
+    items: Vec<&'a str>,
+}
+
+#[test]
+fn custom_delimiters() {
+    assert_render(
+        "custom_delimiters",
+        CustomDelimiters {
+            items: vec!["a&b", "c"],
+        },
+    );
+}
+
 #[derive(Template)]
 #[template(path = "include.stpl")]
 struct Include<'a> {
@@ -116,6 +133,43 @@ fn test_include() {
     );
 }
 
+#[derive(Template)]
+#[template(path = "include_error.stpl")]
+struct IncludeError {
+    fail: bool,
+}
+
+#[test]
+fn test_include_error() {
+    assert_render("include_error", IncludeError { fail: false });
+
+    let mut buf = Buffer::new();
+    buf.push_str("prefix ");
+    let err = IncludeError { fail: true }.render_to(&mut buf).unwrap_err();
+    assert_eq!(err.to_string(), "include failed");
+
+    // partial output of the failed include should be discarded
+    assert_eq!(buf.as_str(), "prefix before ");
+}
+
+#[derive(Template)]
+#[template(path = "include_loop.stpl")]
+struct IncludeLoop;
+
+#[test]
+fn test_include_loop() {
+    assert_render("include_loop", IncludeLoop);
+}
+
+#[derive(Template)]
+#[template(path = "include_swap.stpl")]
+struct IncludeSwap;
+
+#[test]
+fn test_include_swap() {
+    assert_render("include_swap", IncludeSwap);
+}
+
 #[derive(Template)]
 #[template(path = "continue_break.stpl", rm_whitespace = true)]
 struct ContinueBreak;
@@ -221,6 +275,22 @@ fn test_comment() {
     assert_render("comment", Comment {})
 }
 
+#[derive(Template)]
+#[template(path = "comment_tags.stpl", comment = ("<%--", "--%>"))]
+struct CommentTags<'a> {
+    message: &'a str,
+}
+
+#[test]
+fn test_comment_tags() {
+    assert_render(
+        "comment_tags",
+        CommentTags {
+            message: "<b>hello</b>",
+        },
+    );
+}
+
 #[derive(Template)]
 #[template(path = "rust_macro.stpl", rm_whitespace = true)]
 struct RustMacro {
@@ -269,6 +339,24 @@ struct TruncateFilter;
 fn test_truncate_filter() {
     assert_render("truncate_filter", TruncateFilter);
 }
+
+#[derive(Template)]
+#[template(path = "filter_chain.stpl")]
+struct FilterChain<'a> {
+    name: &'a str,
+    flags: u32,
+}
+
+#[test]
+fn test_filter_chain() {
+    assert_render(
+        "filter_chain",
+        FilterChain {
+            name: "sailfish",
+            flags: 1,
+        },
+    );
+}
 #[derive(Template)]
 #[template(path = "json_filter.stpl")]
 struct JsonFilter {
@@ -353,3 +441,576 @@ fn test_method() {
         },
     );
 }
+
+#[derive(Template)]
+#[template(path = "bom.stpl", bom = true)]
+struct Bom<'a> {
+    rows: &'a [(u32, &'a str)],
+}
+
+#[test]
+fn test_bom() {
+    let rows = [(1, "foo"), (2, "bar")];
+    let output = Bom { rows: &rows }.render().unwrap();
+    assert_eq!(output, "\u{feff}id,name\n1,foo\n2,bar\n");
+    assert_eq!(output.matches('\u{feff}').count(), 1);
+
+    // byte order mark is only emitted by the top-level render methods
+    let mut buf = Buffer::new();
+    Bom { rows: &rows }.render_to(&mut buf).unwrap();
+    assert!(!buf.as_str().starts_with('\u{feff}'));
+}
+
+#[derive(Template)]
+#[template(path = "noescape.stpl", impl_display = true)]
+struct ImplDisplay<'a> {
+    raw: &'a str,
+}
+
+#[test]
+fn test_impl_display() {
+    let template = ImplDisplay {
+        raw: "<b>World</b>",
+    };
+    assert_eq!(format!("{}", template), "raw: <b>World</b>");
+    assert_eq!(template.to_string(), template.render().unwrap());
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "used_fields.stpl", used_fields = true)]
+#[allow(dead_code)]
+struct UsedFields {
+    title: String,
+    items: Vec<u32>,
+    secret: String,
+}
+
+#[test]
+fn test_used_fields() {
+    assert_eq!(UsedFields::USED_FIELDS, &["title", "items"]);
+}
+
+#[derive(Template)]
+#[template(path = "extends.stpl")]
+struct Extends<'a> {
+    title: &'a str,
+    items: Vec<&'a str>,
+}
+
+#[test]
+fn test_extends() {
+    assert_render(
+        "extends",
+        Extends {
+            title: "<Items>",
+            items: vec!["foo", "bar"],
+        },
+    );
+}
+
+#[test]
+fn test_render_fragment() {
+    let page = Extends {
+        title: "<Items>",
+        items: vec!["foo", "bar"],
+    };
+
+    assert_eq!(page.render_fragment("title").unwrap(), "&lt;Items&gt;");
+    assert_eq!(
+        page.render_fragment("content").unwrap(),
+        "\n<ul>\n<li>foo</li>\n<li>bar</li>\n</ul>\n"
+    );
+    assert_eq!(page.render_fragment("footer").unwrap(), "&copy; sailfish");
+    assert!(page.render_fragment("missing").is_err());
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "extends.stpl")]
+struct ExtendsOnce<'a> {
+    title: &'a str,
+    items: Vec<&'a str>,
+}
+
+#[derive(TemplateMut)]
+#[template(path = "extends.stpl")]
+struct ExtendsMut<'a> {
+    title: &'a str,
+    items: Vec<&'a str>,
+}
+
+#[test]
+fn test_render_fragment_once_mut() {
+    let page = ExtendsOnce {
+        title: "<Items>",
+        items: vec!["foo"],
+    };
+    assert_eq!(page.render_fragment("title").unwrap(), "&lt;Items&gt;");
+
+    let mut page = ExtendsMut {
+        title: "<Items>",
+        items: vec!["foo"],
+    };
+    assert_eq!(
+        page.render_fragment("content").unwrap(),
+        "\n<ul>\n<li>foo</li>\n</ul>\n"
+    );
+}
+
+#[derive(Template)]
+#[template(path = "extends_nested.stpl")]
+struct ExtendsNested<'a> {
+    title: &'a str,
+    items: Vec<&'a str>,
+}
+
+#[test]
+fn test_extends_nested() {
+    assert_render(
+        "extends_nested",
+        ExtendsNested {
+            title: "sailfish",
+            items: vec!["baz"],
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "include_params.stpl")]
+struct IncludeParams<'a> {
+    items: Vec<(&'a str, String)>,
+}
+
+#[test]
+fn test_include_params() {
+    assert_render(
+        "include_params",
+        IncludeParams {
+            items: vec![("foo", "<bar>".to_owned()), ("baz", "qux".to_owned())],
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "call.stpl")]
+struct Call<'a> {
+    title: &'a str,
+    name: &'a str,
+}
+
+#[test]
+fn test_call() {
+    assert_render(
+        "call",
+        Call {
+            title: "Info",
+            name: "<world>",
+        },
+    );
+}
+
+enum Status {
+    Ok,
+    Error(u16),
+}
+
+#[derive(Template)]
+#[template(path = "switch.stpl")]
+struct Switch {
+    statuses: Vec<Status>,
+}
+
+#[test]
+fn test_switch() {
+    assert_render(
+        "switch",
+        Switch {
+            statuses: vec![Status::Ok, Status::Error(503), Status::Error(404)],
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "with.stpl")]
+struct With {
+    prices: Vec<u32>,
+}
+
+#[test]
+fn test_with() {
+    assert_render(
+        "with",
+        With {
+            prices: vec![10, 20, 30],
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "embed.stpl")]
+struct Embed<'a> {
+    message: &'a str,
+}
+
+#[test]
+fn test_embed() {
+    assert_render("embed", Embed { message: "<hello>" });
+}
+
+#[derive(Template)]
+#[template(path = "autoescape.stpl")]
+struct Autoescape<'a> {
+    title: &'a str,
+    content: &'a str,
+}
+
+#[test]
+fn test_autoescape() {
+    assert_render(
+        "autoescape",
+        Autoescape {
+            title: "<Title>",
+            content: "<p>Sanitized <b>HTML</b></p>",
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "translation.stpl")]
+struct Translation<'a> {
+    name: &'a str,
+    translator: &'a sailfish::runtime::i18n::Catalog,
+}
+
+#[test]
+fn test_translation() {
+    use sailfish::runtime::i18n::Catalog;
+
+    let mut en = Catalog::new();
+    en.insert("welcome.title", "Welcome, {name}!");
+    let mut ja = Catalog::new();
+    ja.insert("welcome.title", "ようこそ、{name}さん!");
+
+    assert_render(
+        "translation",
+        Translation {
+            name: "<Alice>",
+            translator: &en,
+        },
+    );
+    assert_eq!(
+        Translation {
+            name: "Alice",
+            translator: &ja,
+        }
+        .render_once()
+        .unwrap(),
+        "<h1>ようこそ、Aliceさん!</h1>\n<p>missing.key</p>"
+    );
+}
+
+#[derive(Template)]
+#[template(path = "capture.stpl")]
+struct Capture<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_capture() {
+    assert_render("capture", Capture { name: "<world>" });
+}
+
+#[derive(Template)]
+#[template(path = "spaceless.stpl")]
+struct Spaceless<'a> {
+    items: &'a [&'a str],
+}
+
+#[test]
+fn test_spaceless() {
+    assert_render(
+        "spaceless",
+        Spaceless {
+            items: &["  <b>foo</b>\n", "<b>bar</b>"],
+        },
+    );
+}
+
+struct Node {
+    name: &'static str,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(name: &'static str, children: Vec<Node>) -> Self {
+        Self { name, children }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "tree.stpl")]
+struct Tree {
+    root: Node,
+}
+
+#[derive(Template)]
+#[template(path = "tree.stpl", recursion_limit = 2)]
+struct ShallowTree {
+    root: Node,
+}
+
+#[test]
+fn test_recursive_include() {
+    assert_render(
+        "tree",
+        Tree {
+            root: Node::new(
+                "a",
+                vec![
+                    Node::new("b", vec![Node::new("c", vec![])]),
+                    Node::new("d", vec![]),
+                ],
+            ),
+        },
+    );
+
+    let deep = Node::new("a", vec![Node::new("b", vec![Node::new("c", vec![])])]);
+    let err = ShallowTree { root: deep }.render().unwrap_err();
+    assert!(err.to_string().starts_with("Recursion limit (2) exceeded"));
+
+    let shallow = Node::new("a", vec![Node::new("b", vec![])]);
+    assert!(ShallowTree { root: shallow }.render().is_ok());
+
+    // the depth is checked while rendering
+    let chain = |depth| {
+        (1..depth).fold(Node::new("a", vec![]), |node, _| Node::new("a", vec![node]))
+    };
+    assert!(Tree { root: chain(8) }.render().is_ok());
+    assert!(Tree { root: chain(9) }.render().is_err());
+}
+
+#[derive(Template)]
+#[template(path = "slots.stpl")]
+struct Slots<'a> {
+    message: &'a str,
+    label: &'a str,
+}
+
+#[test]
+fn test_slots() {
+    assert_render(
+        "slots",
+        Slots {
+            message: "<hello>",
+            label: "OK",
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "loop_meta.stpl")]
+struct LoopMeta<'a> {
+    items: Vec<&'a str>,
+}
+
+#[test]
+fn test_loop_meta() {
+    assert_render(
+        "loop_meta",
+        LoopMeta {
+            items: vec!["foo", "bar", "baz"],
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "for_else.stpl")]
+struct ForElse<'a> {
+    items: Vec<&'a str>,
+    empty: Vec<&'a str>,
+}
+
+#[test]
+fn test_for_else() {
+    assert_render(
+        "for_else",
+        ForElse {
+            items: vec!["foo", "<bar>"],
+            empty: Vec::new(),
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "whitespace_control.stpl")]
+struct WhitespaceControl<'a> {
+    items: Vec<&'a str>,
+    show_code: bool,
+}
+
+#[test]
+fn test_whitespace_control() {
+    assert_render(
+        "whitespace_control",
+        WhitespaceControl {
+            items: vec!["foo", "bar"],
+            show_code: true,
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "raw.stpl")]
+struct Raw<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_raw() {
+    assert_render("raw", Raw { name: "sailfish" });
+}
+
+#[derive(Template)]
+#[template(path = "def.stpl")]
+struct Def<'a> {
+    status: &'a str,
+}
+
+#[test]
+fn test_def() {
+    assert_render("def", Def { status: "draft" });
+}
+
+#[derive(Template)]
+#[template(path = "default_filters.stpl", filters = "trim")]
+struct DefaultFilters<'a> {
+    title: &'a str,
+}
+
+#[test]
+fn test_default_filters() {
+    assert_render(
+        "default_filters",
+        DefaultFilters {
+            title: "  <Hello>\n",
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "include_dyn.stpl")]
+struct IncludeDyn<'a> {
+    theme: String,
+    title: &'a str,
+}
+
+#[test]
+fn test_include_dyn() {
+    assert_render(
+        "include_dyn",
+        IncludeDyn {
+            theme: "dark".to_owned(),
+            title: "<Home>",
+        },
+    );
+
+    let light = IncludeDyn {
+        theme: "light".to_owned(),
+        title: "Home",
+    };
+    assert_eq!(
+        light.render().unwrap(),
+        "<header class=\"light\">Home</header><p>body</p>"
+    );
+
+    let unknown = IncludeDyn {
+        theme: "blue".to_owned(),
+        title: "Home",
+    };
+    assert!(unknown.render().is_err());
+}
+
+#[derive(Template)]
+#[template(path = "cycle.stpl")]
+struct Cycle<'a> {
+    rows: Vec<&'a str>,
+}
+
+#[test]
+fn test_cycle() {
+    assert_render(
+        "cycle",
+        Cycle {
+            rows: vec!["a", "b", "c"],
+        },
+    );
+}
+
+mod filters {
+    pub use sailfish::runtime::filter::*;
+    use sailfish::runtime::{Buffer, Render, RenderError};
+
+    pub struct Shout<'a, T: ?Sized>(&'a T);
+
+    impl<'a, T: Render + ?Sized> Render for Shout<'a, T> {
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            self.0.render(b)?;
+            b.push('!');
+            Ok(())
+        }
+
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            self.0.render_escaped(b)?;
+            b.push('!');
+            Ok(())
+        }
+    }
+
+    pub fn shout<T: Render + ?Sized>(expr: &T) -> Shout<'_, T> {
+        Shout(expr)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "user_filter.stpl", filter_module = "crate::filters")]
+struct UserFilter<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_user_filter() {
+    assert_render("user_filter", UserFilter { name: " <hello> " });
+}
+
+#[derive(Template)]
+#[template(path = "xml_escape.stpl", escape_mode = "xml")]
+struct XmlEscape<'a> {
+    title: &'a str,
+    items: Vec<&'a str>,
+}
+
+#[test]
+fn test_xml_escape() {
+    assert_render(
+        "xml_escape",
+        XmlEscape {
+            title: "Tom's <blog>",
+            items: vec!["A & B", "\"quoted\""],
+        },
+    );
+}
+
+#[derive(Template)]
+#[template(path = "fmt_filter.stpl")]
+struct FmtFilter {
+    prices: Vec<(&'static str, f64)>,
+}
+
+#[test]
+fn test_fmt_filter() {
+    assert_render(
+        "fmt_filter",
+        FmtFilter {
+            prices: vec![("apple", 1.5), ("melon", 12.25)],
+        },
+    );
+}
@@ -247,6 +247,25 @@ fn test_filter2() {
     assert_render_simple("filter2", Filter2);
 }
 
+#[derive(TemplateSimple)]
+#[template(path = "fragment_s.stpl")]
+struct Fragment<'a> {
+    name: &'a str,
+    footer: &'a str,
+}
+
+#[test]
+fn test_render_fragment() {
+    let page = Fragment {
+        name: "<World>",
+        footer: "bye",
+    };
+    assert_eq!(
+        page.render_fragment("greeting").unwrap(),
+        "Hello, &lt;World&gt;!"
+    );
+}
+
 #[derive(TemplateSimple)]
 #[template(path = "truncate_filter.stpl")]
 struct TruncateFilter;
@@ -300,4 +319,18 @@ mod unix {
     fn test_include_rust() {
         assert_render_simple("include_rust", IncludeRust { value: 58 });
     }
+
+    #[derive(TemplateSimple)]
+    #[template(path = "used_fields_s.stpl", used_fields = true)]
+    #[allow(dead_code)]
+    struct UsedFields {
+        title: String,
+        items: Vec<u32>,
+        _secret: String,
+    }
+
+    #[test]
+    fn test_used_fields() {
+        assert_eq!(UsedFields::USED_FIELDS, &["title", "items"]);
+    }
 }
@@ -55,6 +55,7 @@ pub trait TemplateSimple: Sized {
     /// the next time, buffer will be pre-allocated based on the cached length.
     ///
     /// If you don't want this behaviour, you can use `render_once_to` method instead.
+    #[must_use = "the rendered contents are discarded unless the result is used"]
     fn render_once(self) -> runtime::RenderResult;
 
     /// Render the template and append the result to `buf`.
@@ -103,6 +104,7 @@ pub trait TemplateOnce: Sized {
     /// the next time, buffer will be pre-allocated based on the cached length.
     ///
     /// If you don't want this behaviour, you can use `render_once_to` method instead.
+    #[must_use = "the rendered contents are discarded unless the result is used"]
     fn render_once(self) -> runtime::RenderResult;
 
     /// Render the template and append the result to `buf`.
@@ -151,6 +153,7 @@ pub trait TemplateMut: TemplateOnce {
     /// the next time, buffer will be pre-allocated based on the cached length.
     ///
     /// If you don't want this behaviour, you can use `render_to` method instead.
+    #[must_use = "the rendered contents are discarded unless the result is used"]
     fn render_mut(&mut self) -> runtime::RenderResult;
 
     /// Render the template and append the result to `buf`.
@@ -210,6 +213,7 @@ pub trait Template: TemplateMut {
     /// the next time, buffer will be pre-allocated based on the cached length.
     ///
     /// If you don't want this behaviour, you can use `render_to` method instead.
+    #[must_use = "the rendered contents are discarded unless the result is used"]
     fn render(&self) -> runtime::RenderResult;
 
     /// Render the template and append the result to `buf`.
@@ -114,6 +114,27 @@ impl Buffer {
         self.reserve_internal(size);
     }
 
+    /// Returns the current position which can be passed to `rollback` later
+    #[inline]
+    pub fn checkpoint(&self) -> usize {
+        self.len
+    }
+
+    /// Discard the contents written after `checkpoint`
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `checkpoint` is greater than `len()`, or does not lie
+    /// on a UTF-8 character boundary.
+    #[inline]
+    pub fn rollback(&mut self, checkpoint: usize) {
+        assert!(
+            checkpoint <= self.len && self.as_str().is_char_boundary(checkpoint),
+            "invalid buffer checkpoint"
+        );
+        self.len = checkpoint;
+    }
+
     #[inline]
     #[doc(hidden)]
     pub fn clear(&mut self) {
@@ -477,6 +498,35 @@ mod tests {
         assert_eq!(buffer.lock().unwrap().as_str(), "a".repeat(ITERS * THREADS));
     }
 
+    #[test]
+    fn checkpoint() {
+        let mut buf = Buffer::from("foo");
+        let checkpoint = buf.checkpoint();
+        assert_eq!(checkpoint, 3);
+
+        buf.push_str("bar");
+        buf.push('é');
+        buf.rollback(checkpoint);
+        assert_eq!(buf.as_str(), "foo");
+
+        buf.push_str("baz");
+        assert_eq!(buf.as_str(), "foobaz");
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_overflow() {
+        let mut buf = Buffer::from("foo");
+        buf.rollback(4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rollback_char_boundary() {
+        let mut buf = Buffer::from("é");
+        buf.rollback(1);
+    }
+
     #[test]
     #[should_panic]
     fn reserve_overflow() {
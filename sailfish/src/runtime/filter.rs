@@ -1,7 +1,12 @@
 //! Build-in filters
 
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
 use std::fmt;
+use std::hash::Hash;
 use std::ptr;
+use std::time::{Duration, SystemTime};
 
 use super::{Buffer, Render, RenderError};
 
@@ -64,23 +69,47 @@ impl<'a, T: Render + ?Sized> Render for Upper<'a, T> {
     fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
         let old_len = b.len();
         self.0.render(b)?;
-
-        let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
-        let s = content.to_uppercase();
-        unsafe { b._set_len(old_len) };
-        b.push_str(&*s);
-        Ok(())
+        convert_case_tail(b, old_len, true)
     }
 
     fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
         let old_len = b.len();
         self.0.render_escaped(b)?;
+        convert_case_tail(b, old_len, true)
+    }
+}
 
-        let s = b.as_str()[old_len..].to_uppercase();
-        unsafe { b._set_len(old_len) };
-        b.push_str(&*s);
-        Ok(())
+// convert the case of the contents rendered after `old_len`
+//
+// ASCII-only contents are converted in place without allocation.
+fn convert_case_tail(
+    b: &mut Buffer,
+    old_len: usize,
+    upper: bool,
+) -> Result<(), RenderError> {
+    let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+
+    if content.is_ascii() {
+        let len = content.len();
+        // SAFETY: changing the case of ASCII characters keeps the contents valid UTF-8
+        let bytes =
+            unsafe { std::slice::from_raw_parts_mut(b.as_mut_ptr().add(old_len), len) };
+        if upper {
+            bytes.make_ascii_uppercase();
+        } else {
+            bytes.make_ascii_lowercase();
+        }
+        return Ok(());
     }
+
+    let s = if upper {
+        content.to_uppercase()
+    } else {
+        content.to_lowercase()
+    };
+    unsafe { b._set_len(old_len) };
+    b.push_str(&s);
+    Ok(())
 }
 
 /// convert the rendered contents to uppercase
@@ -108,22 +137,13 @@ impl<'a, T: Render + ?Sized> Render for Lower<'a, T> {
     fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
         let old_len = b.len();
         self.0.render(b)?;
-
-        let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
-        let s = content.to_lowercase();
-        unsafe { b._set_len(old_len) };
-        b.push_str(&*s);
-        Ok(())
+        convert_case_tail(b, old_len, false)
     }
 
     fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
         let old_len = b.len();
         self.0.render_escaped(b)?;
-
-        let s = b.as_str()[old_len..].to_lowercase();
-        unsafe { b._set_len(old_len) };
-        b.push_str(&*s);
-        Ok(())
+        convert_case_tail(b, old_len, false)
     }
 }
 
@@ -215,6 +235,64 @@ pub fn trim<T: Render + ?Sized>(expr: &T) -> Trim<T> {
     Trim(expr)
 }
 
+/// Helper struct for 'spaceless' filter
+pub struct Spaceless<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Spaceless<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        spaceless_impl(b, old_len)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render_escaped(b)?;
+        spaceless_impl(b, old_len)
+    }
+}
+
+fn spaceless_impl(b: &mut Buffer, old_len: usize) -> Result<(), RenderError> {
+    let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+
+    let mut result = String::with_capacity(new_contents.len());
+    let mut rest = new_contents;
+    while let Some(pos) = rest.find('>') {
+        result.push_str(&rest[..=pos]);
+        rest = &rest[pos + 1..];
+
+        let trimmed = rest.trim_start();
+        if trimmed.starts_with('<') {
+            rest = trimmed;
+        }
+    }
+    result.push_str(rest);
+
+    unsafe { b._set_len(old_len) };
+    b.push_str(&result);
+    Ok(())
+}
+
+/// Remove whitespaces between HTML tags from rendered results
+///
+/// # Examples
+///
+/// ```text
+/// <%- "<p>\n  <a href=\"/\">Home</a>\n</p>" | spaceless %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <p><a href="/">Home</a></p>
+/// ```
+#[inline]
+pub fn spaceless<T: Render + ?Sized>(expr: &T) -> Spaceless<'_, T> {
+    Spaceless(expr)
+}
+
 /// Helper struct for 'truncate' filter
 pub struct Truncate<'a, T: ?Sized>(&'a T, usize);
 
@@ -269,206 +347,7559 @@ pub fn truncate<T: Render + ?Sized>(expr: &T, limit: usize) -> Truncate<T> {
     Truncate(expr, limit)
 }
 
-cfg_json! {
-    /// Helper struct for 'json' filter
-    pub struct Json<'a, T: ?Sized>(&'a T);
+#[derive(Clone, Copy)]
+enum TruncateUnit {
+    Chars,
+    #[cfg(feature = "unicode-segmentation")]
+    Graphemes,
+    Words,
+}
 
-    impl<'a, T: serde::Serialize + ?Sized> Render for Json<'a, T> {
-        #[inline]
-        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
-            struct Writer<'a>(&'a mut Buffer);
+/// Helper struct for 'truncate_chars', 'truncate_graphemes', 'truncate_words' and
+/// 'truncate_with' filter
+pub struct TruncateBy<'a, T: ?Sized> {
+    expr: &'a T,
+    limit: usize,
+    unit: TruncateUnit,
+    suffix: &'a str,
+    count_suffix: bool,
+}
 
-            impl<'a> std::io::Write for Writer<'a> {
-                #[inline]
-                fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-                    let buf = unsafe { std::str::from_utf8_unchecked(buf) };
-                    self.0.push_str(buf);
-                    Ok(buf.len())
-                }
+impl<'a, T: ?Sized> TruncateBy<'a, T> {
+    #[inline]
+    fn new(expr: &'a T, limit: usize, unit: TruncateUnit) -> Self {
+        Self {
+            expr,
+            limit,
+            unit,
+            suffix: "...",
+            count_suffix: false,
+        }
+    }
 
-                #[inline]
-                fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
-                    self.write(buf).map(|_| {})
-                }
+    /// Set the string appended to the truncated contents (default: `...`)
+    #[inline]
+    pub fn suffix(mut self, suffix: &'a str) -> Self {
+        self.suffix = suffix;
+        self
+    }
 
-                #[inline]
-                fn flush(&mut self) -> std::io::Result<()> {
-                    Ok(())
-                }
-            }
+    /// Count the suffix toward the limit, so that the truncated contents
+    /// including the suffix fit in the limit. The suffix is shortened if the suffix
+    /// alone exceeds the limit.
+    ///
+    /// This has no effect when truncating by words.
+    #[inline]
+    pub fn count_suffix(mut self, count_suffix: bool) -> Self {
+        self.count_suffix = count_suffix;
+        self
+    }
 
-            serde_json::to_writer(Writer(b), self.0)
-                .map_err(|e| RenderError::new(&e.to_string()))
+    // HTML entities are counted as single characters in the escaped contents
+    fn truncate_impl(
+        &self,
+        b: &mut Buffer,
+        old_len: usize,
+        escaped: bool,
+    ) -> Result<(), RenderError> {
+        let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+
+        let mut idx = match truncate_offset(new_contents, self.limit, self.unit, escaped)
+        {
+            Some(idx) => idx,
+            None => return Ok(()),
+        };
+        let mut suffix = self.suffix;
+        if self.count_suffix && !matches!(self.unit, TruncateUnit::Words) {
+            // shorten the suffix if the suffix alone exceeds the limit
+            if let Some(end) = truncate_offset(suffix, self.limit, self.unit, false) {
+                suffix = &suffix[..end];
+            }
+            let limit = self.limit - unit_count(suffix, self.unit);
+            idx = truncate_offset(new_contents, limit, self.unit, escaped).unwrap_or(idx);
         }
 
-        #[inline]
-        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
-            use super::escape::escape_to_buf;
+        let idx = new_contents[..idx].trim_end().len();
+        unsafe { b._set_len(old_len + idx) };
+        if escaped {
+            super::escape::escape_to_buf(suffix, b);
+        } else {
+            b.push_str(suffix);
+        }
+        Ok(())
+    }
+}
 
-            struct Writer<'a>(&'a mut Buffer);
+impl<'a, T: Render + ?Sized> Render for TruncateBy<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        self.truncate_impl(b, old_len, false)
+    }
 
-            impl<'a> std::io::Write for Writer<'a> {
-                #[inline]
-                fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-                    let buf = unsafe { std::str::from_utf8_unchecked(buf) };
-                    escape_to_buf(buf, self.0);
-                    Ok(buf.len())
-                }
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render_escaped(b)?;
+        self.truncate_impl(b, old_len, true)
+    }
+}
 
-                #[inline]
-                fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
-                    self.write(buf).map(|_| {})
+/// returns the byte offset where the contents should be cut
+fn truncate_offset(
+    s: &str,
+    limit: usize,
+    unit: TruncateUnit,
+    escaped: bool,
+) -> Option<usize> {
+    match unit {
+        TruncateUnit::Words => {
+            let mut words = 0;
+            let mut in_word = false;
+            for (i, c) in s.char_indices() {
+                if c.is_whitespace() {
+                    if in_word && words == limit {
+                        // cut at the end of the last word if more words follow
+                        let rest = &s[i..];
+                        return if rest.trim_start().is_empty() {
+                            None
+                        } else {
+                            Some(i)
+                        };
+                    }
+                    in_word = false;
+                } else if !in_word {
+                    if words == limit {
+                        return Some(i);
+                    }
+                    in_word = true;
+                    words += 1;
                 }
-
-                #[inline]
-                fn flush(&mut self) -> std::io::Result<()> {
-                    Ok(())
+            }
+            None
+        }
+        _ => {
+            let mut pos = 0;
+            for _ in 0..limit {
+                if pos == s.len() {
+                    return None;
                 }
+                let rest = &s[pos..];
+                pos += match entity_len(rest) {
+                    Some(len) if escaped => len,
+                    _ => first_unit_len(rest, unit),
+                };
             }
+            if pos < s.len() {
+                Some(pos)
+            } else {
+                None
+            }
+        }
+    }
+}
 
-            serde_json::to_writer(Writer(b), self.0)
-                .map_err(|e| RenderError::new(&e.to_string()))
+/// returns the byte length of the first character or grapheme cluster
+fn first_unit_len(s: &str, unit: TruncateUnit) -> usize {
+    match unit {
+        #[cfg(feature = "unicode-segmentation")]
+        TruncateUnit::Graphemes => {
+            use unicode_segmentation::UnicodeSegmentation;
+
+            s.graphemes(true).next().map_or(0, str::len)
         }
+        _ => s.chars().next().map_or(0, char::len_utf8),
     }
+}
 
-    /// Serialize the given data structure as JSON into the buffer
+/// returns the byte length of the HTML entity at the start of the string
+fn entity_len(s: &str) -> Option<usize> {
+    let end = s
+        .strip_prefix('&')?
+        .bytes()
+        .take(32)
+        .position(|c| c == b';')?;
+    let name = &s[1..end + 1];
+    let name = name.strip_prefix('#').unwrap_or(name);
+    if !name.is_empty() && name.bytes().all(|c| c.is_ascii_alphanumeric()) {
+        Some(end + 2)
+    } else {
+        None
+    }
+}
+
+/// returns the length of the string in the given unit
+fn unit_count(s: &str, unit: TruncateUnit) -> usize {
+    match unit {
+        TruncateUnit::Chars => s.chars().count(),
+        #[cfg(feature = "unicode-segmentation")]
+        TruncateUnit::Graphemes => {
+            use unicode_segmentation::UnicodeSegmentation;
+
+            s.graphemes(true).count()
+        }
+        TruncateUnit::Words => 0,
+    }
+}
+
+/// limit the number of characters in the rendered contents, appends '...' if
+/// truncated
+///
+/// Unlike `truncate`, HTML entities in the escaped contents are counted as single
+/// characters, so that they are never cut in the middle.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "foo<br>bar" | truncate_chars(6) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// foo&lt;br...
+/// ```
+#[inline]
+pub fn truncate_chars<T: Render + ?Sized>(expr: &T, limit: usize) -> TruncateBy<'_, T> {
+    TruncateBy::new(expr, limit, TruncateUnit::Chars)
+}
+
+cfg_unicode_segmentation! {
+    /// limit the number of grapheme clusters in the rendered contents, appends
+    /// '...' if truncated
+    ///
+    /// Combining marks and emoji sequences are never separated from their base
+    /// characters.
     ///
     /// # Examples
     ///
     /// ```text
-    /// {
-    ///     "name": "JSON example",
-    ///     "data": <%- data | json %>
-    /// }
+    /// <%= "👨‍👩‍👧👨‍👩‍👧" | truncate_graphemes(1) %>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// 👨‍👩‍👧...
     /// ```
     #[inline]
-    pub fn json<T: serde::Serialize + ?Sized>(expr: &T) -> Json<T> {
-        Json(expr)
+    pub fn truncate_graphemes<T: Render + ?Sized>(
+        expr: &T,
+        limit: usize,
+    ) -> TruncateBy<'_, T> {
+        TruncateBy::new(expr, limit, TruncateUnit::Graphemes)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// limit the number of whitespace-separated words in the rendered contents,
+/// appends '...' if truncated
+///
+/// # Examples
+///
+/// ```text
+/// <%= "The quick brown fox jumps" | truncate_words(3) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// The quick brown...
+/// ```
+#[inline]
+pub fn truncate_words<T: Render + ?Sized>(expr: &T, limit: usize) -> TruncateBy<'_, T> {
+    TruncateBy::new(expr, limit, TruncateUnit::Words)
+}
 
-    fn assert_render<T: Render>(expr: &T, expected: &str) {
-        let mut buf = Buffer::new();
-        Render::render(expr, &mut buf).unwrap();
-        assert_eq!(buf.as_str(), expected);
-    }
+/// limit the number of characters in the rendered contents, appends `suffix` if
+/// truncated
+///
+/// If `count_suffix` is `true`, the suffix counts toward the limit, so that the
+/// result never exceeds `limit` characters. If the suffix alone is longer than the
+/// limit, only the first `limit` characters of the suffix are rendered. The same
+/// options are also available for the other truncate filters with `suffix` and
+/// `count_suffix` methods.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Hello, world!" | truncate_with(8, "…", true) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Hello,…
+/// ```
+#[inline]
+pub fn truncate_with<'a, T: Render + ?Sized>(
+    expr: &'a T,
+    limit: usize,
+    suffix: &'a str,
+    count_suffix: bool,
+) -> TruncateBy<'a, T> {
+    TruncateBy::new(expr, limit, TruncateUnit::Chars)
+        .suffix(suffix)
+        .count_suffix(count_suffix)
+}
 
-    fn assert_render_escaped<T: Render>(expr: &T, expected: &str) {
-        let mut buf = Buffer::new();
-        Render::render_escaped(expr, &mut buf).unwrap();
-        assert_eq!(buf.as_str(), expected);
-    }
+/// Helper struct for 'truncate_middle' filter
+pub struct TruncateMiddle<'a, T: ?Sized> {
+    expr: &'a T,
+    limit: usize,
+}
 
-    #[test]
-    fn test_lower() {
-        assert_render(&lower(""), "");
-        assert_render_escaped(&lower(""), "");
+impl<'a, T: Render + ?Sized> TruncateMiddle<'a, T> {
+    // returns the byte ranges of the head and tail to keep, or `None` if the
+    // contents fit in the limit
+    fn split(&self, s: &str) -> Option<(usize, usize)> {
+        let count = s.chars().count();
+        if count <= self.limit {
+            return None;
+        }
 
-        assert_render(&lower("lorem ipsum"), "lorem ipsum");
-        assert_render(&lower("LOREM IPSUM"), "lorem ipsum");
+        let keep = self.limit.saturating_sub(1);
+        let head = (keep + 1) / 2;
+        let tail = keep / 2;
+        let head_end = s.char_indices().nth(head).map_or(s.len(), |(i, _)| i);
+        let tail_start = s
+            .char_indices()
+            .nth(count - tail)
+            .map_or(s.len(), |(i, _)| i);
+        Some((head_end, tail_start))
+    }
 
-        assert_render_escaped(&lower("hElLo, WOrLd!"), "hello, world!");
-        assert_render_escaped(&lower("hElLo, WOrLd!"), "hello, world!");
+    fn render_impl(&self, b: &mut Buffer, escape: bool) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
 
-        assert_render_escaped(&lower("<h1>TITLE</h1>"), "&lt;h1&gt;title&lt;/h1&gt;");
-        assert_render_escaped(&lower("<<&\"\">>"), "&lt;&lt;&amp;&quot;&quot;&gt;&gt;");
+        let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        let result = match self.split(content) {
+            Some((head_end, tail_start)) => {
+                let mut result = String::with_capacity(content.len());
+                result.push_str(&content[..head_end]);
+                if self.limit > 0 {
+                    result.push('…');
+                }
+                result.push_str(&content[tail_start..]);
+                result
+            }
+            None if escape => content.to_owned(),
+            None => return Ok(()),
+        };
 
-        // non-ascii
-        assert_render(&lower("aBcＡｂｃ"), "abcａｂｃ");
-        assert_render(&lower("ὈΔΥΣΣΕΎΣ"), "ὀδυσσεύς");
+        unsafe { b._set_len(old_len) };
+        if escape {
+            super::escape::escape_to_buf(&result, b);
+        } else {
+            b.push_str(&result);
+        }
+        Ok(())
     }
+}
 
-    #[test]
-    fn test_upper() {
-        assert_render(&upper(""), "");
-        assert_render_escaped(&upper(""), "");
+impl<'a, T: Render + ?Sized> Render for TruncateMiddle<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, false)
+    }
 
-        assert_render(&upper("lorem ipsum"), "LOREM IPSUM");
-        assert_render(&upper("LOREM IPSUM"), "LOREM IPSUM");
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // truncate before escaping not to break the entities
+        self.render_impl(b, true)
+    }
+}
 
-        assert_render(&upper("hElLo, WOrLd!"), "HELLO, WORLD!");
-        assert_render(&upper("hElLo, WOrLd!"), "HELLO, WORLD!");
+/// limit the number of characters in the rendered contents by replacing the
+/// middle part with '…'
+///
+/// The start and the end of the contents are kept, which is useful for long file
+/// paths and hashes. The result contains at most `limit` characters including
+/// the ellipsis.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "/home/user/projects/sailfish/src/lib.rs" | truncate_middle(21) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// /home/user…src/lib.rs
+/// ```
+#[inline]
+pub fn truncate_middle<T: Render + ?Sized>(
+    expr: &T,
+    limit: usize,
+) -> TruncateMiddle<'_, T> {
+    TruncateMiddle { expr, limit }
+}
 
-        // non-ascii
-        assert_render(&upper("aBcＡｂｃ"), "ABCＡＢＣ");
-        assert_render(&upper("tschüß"), "TSCHÜSS");
+/// `std::io::Write` adapter which writes the serializer output into the buffer
+#[cfg(feature = "json")]
+struct Writer<'a> {
+    buf: &'a mut Buffer,
+    escape: bool,
+}
+
+#[cfg(feature = "json")]
+impl<'a> std::io::Write for Writer<'a> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let buf = unsafe { std::str::from_utf8_unchecked(buf) };
+        if self.escape {
+            super::escape::escape_to_buf(buf, self.buf);
+        } else {
+            self.buf.push_str(buf);
+        }
+        Ok(buf.len())
     }
 
-    #[test]
-    fn test_trim() {
-        assert_render(&trim(""), "");
-        assert_render_escaped(&trim(""), "");
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.write(buf).map(|_| {})
+    }
 
-        assert_render(&trim("\n \t\r\x0C"), "");
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+cfg_json! {
+    /// Helper struct for 'json' filter
+    pub struct Json<'a, T: ?Sized>(&'a T);
+
+    impl<'a, T: serde::Serialize + ?Sized> Render for Json<'a, T> {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            serde_json::to_writer(Writer { buf: b, escape: false }, self.0)
+                .map_err(|e| RenderError::new(&e.to_string()))
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            serde_json::to_writer(Writer { buf: b, escape: true }, self.0)
+                .map_err(|e| RenderError::new(&e.to_string()))
+        }
+    }
+
+    /// Serialize the given data structure as JSON into the buffer
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// {
+    ///     "name": "JSON example",
+    ///     "data": <%- data | json %>
+    /// }
+    /// ```
+    #[inline]
+    pub fn json<T: serde::Serialize + ?Sized>(expr: &T) -> Json<T> {
+        Json(expr)
+    }
+}
+
+cfg_json! {
+    /// Helper struct for 'json_pretty' and 'json_pretty_indent' filters
+    pub struct JsonPretty<'a, T: ?Sized> {
+        expr: &'a T,
+        indent: usize,
+    }
+
+    impl<'a, T: serde::Serialize + ?Sized> JsonPretty<'a, T> {
+        fn render_impl(&self, b: &mut Buffer, escape: bool) -> Result<(), RenderError> {
+            let indent = vec![b' '; self.indent];
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+            let mut ser = serde_json::Serializer::with_formatter(
+                Writer { buf: b, escape },
+                formatter,
+            );
+            self.expr
+                .serialize(&mut ser)
+                .map_err(|e| RenderError::new(&e.to_string()))
+        }
+    }
+
+    impl<'a, T: serde::Serialize + ?Sized> Render for JsonPretty<'a, T> {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            self.render_impl(b, false)
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            self.render_impl(b, true)
+        }
+    }
+
+    /// Serialize the given data structure as pretty-printed JSON into the buffer
+    ///
+    /// Nested values are indented with 2 spaces.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <pre><%= data | json_pretty %></pre>
+    /// ```
+    #[inline]
+    pub fn json_pretty<T: serde::Serialize + ?Sized>(expr: &T) -> JsonPretty<'_, T> {
+        JsonPretty { expr, indent: 2 }
+    }
+
+    /// Serialize the given data structure as pretty-printed JSON, indenting nested
+    /// values with `indent` spaces
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <pre><%= data | json_pretty_indent(4) %></pre>
+    /// ```
+    #[inline]
+    pub fn json_pretty_indent<T: serde::Serialize + ?Sized>(
+        expr: &T,
+        indent: usize,
+    ) -> JsonPretty<'_, T> {
+        JsonPretty { expr, indent }
+    }
+}
+
+cfg_yaml! {
+    /// Helper struct for 'yaml' filter
+    pub struct Yaml<'a, T: ?Sized>(&'a T);
+
+    impl<'a, T: serde::Serialize + ?Sized> Render for Yaml<'a, T> {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            let s = serde_yaml_ng::to_string(self.0)
+                .map_err(|e| RenderError::new(&e.to_string()))?;
+            b.push_str(&s);
+            Ok(())
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            let s = serde_yaml_ng::to_string(self.0)
+                .map_err(|e| RenderError::new(&e.to_string()))?;
+            super::escape::escape_to_buf(&s, b);
+            Ok(())
+        }
+    }
+
+    /// Serialize the given data structure as YAML into the buffer
+    ///
+    /// The output always ends with a newline.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// apiVersion: v1
+    /// kind: ConfigMap
+    /// metadata:
+    ///   name: <%= name %>
+    /// data:
+    /// <%- data | yaml | indent(2) %>
+    /// ```
+    #[inline]
+    pub fn yaml<T: serde::Serialize + ?Sized>(expr: &T) -> Yaml<'_, T> {
+        Yaml(expr)
+    }
+}
+
+/// Builder for the space-separated `class` attribute value
+///
+/// Class names are rendered in insertion order, duplicates and empty names are
+/// skipped, and the output is always HTML escaped so that it can be safely placed
+/// inside an attribute value.
+#[derive(Clone, Debug, Default)]
+pub struct ClassList<'a> {
+    classes: Vec<Cow<'a, str>>,
+}
+
+impl<'a> ClassList<'a> {
+    /// Create an empty class list
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a class name
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<S: Into<Cow<'a, str>>>(mut self, class: S) -> Self {
+        let class = class.into();
+        if !class.is_empty() && !self.classes.contains(&class) {
+            self.classes.push(class);
+        }
+        self
+    }
+
+    /// Append a class name only if `cond` is `true`
+    #[inline]
+    pub fn add_if<S: Into<Cow<'a, str>>>(self, class: S, cond: bool) -> Self {
+        if cond {
+            self.add(class)
+        } else {
+            self
+        }
+    }
+
+    /// Finish building the class list
+    ///
+    /// This method exists for readability. `ClassList` can be rendered without
+    /// calling it.
+    #[inline]
+    pub fn build(self) -> Self {
+        self
+    }
+}
+
+impl<'a> Render for ClassList<'a> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_escaped(b)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        use super::escape::escape_to_buf;
+
+        for (i, class) in self.classes.iter().enumerate() {
+            if i > 0 {
+                b.push(' ');
+            }
+            escape_to_buf(class, b);
+        }
+        Ok(())
+    }
+}
+
+/// create a new `ClassList` builder
+///
+/// # Examples
+///
+/// ```text
+/// <div class="<%= classlist().add("btn").add_if("active", is_active).build() %>">
+/// ```
+///
+/// result (if `is_active` is `true`):
+///
+/// ```text
+/// <div class="btn active">
+/// ```
+#[inline]
+pub fn classlist<'a>() -> ClassList<'a> {
+    ClassList::new()
+}
+
+/// Helper struct for 'flatten_or' filter
+pub struct FlattenOr<'a, T, E>(&'a Option<Result<T, E>>, &'a str);
+
+impl<'a, T: Render, E> Render for FlattenOr<'a, T, E> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        match self.0 {
+            Some(Ok(ref value)) => value.render(b),
+            _ => self.1.render(b),
+        }
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        match self.0 {
+            Some(Ok(ref value)) => value.render_escaped(b),
+            _ => self.1.render_escaped(b),
+        }
+    }
+}
+
+/// render the value inside `Some(Ok(_))`, or `fallback` for `None` and `Some(Err(_))`
+///
+/// # Examples
+///
+/// ```text
+/// <%= user.nickname | flatten_or("anonymous") %>
+/// ```
+///
+/// result (if `user.nickname` is `None` or `Some(Err(_))`):
+///
+/// ```text
+/// anonymous
+/// ```
+#[inline]
+pub fn flatten_or<'a, T: Render, E>(
+    val: &'a Option<Result<T, E>>,
+    fallback: &'a str,
+) -> FlattenOr<'a, T, E> {
+    FlattenOr(val, fallback)
+}
+
+/// Helper struct for 'default' and 'default_if_blank' filter
+pub struct DefaultValue<'a, T: ?Sized> {
+    expr: &'a T,
+    fallback: &'a str,
+    blank: bool,
+}
+
+impl<'a, T: Render + ?Sized> DefaultValue<'a, T> {
+    #[inline]
+    fn is_empty(&self, b: &Buffer, old_len: usize) -> bool {
+        let new_contents = &b.as_str()[old_len..];
+        if self.blank {
+            new_contents.trim().is_empty()
+        } else {
+            new_contents.is_empty()
+        }
+    }
+}
+
+impl<'a, T: Render + ?Sized> Render for DefaultValue<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        if b.len() < old_len {
+            return Err(RenderError::BufSize);
+        }
+        if self.is_empty(b, old_len) {
+            unsafe { b._set_len(old_len) };
+            b.push_str(self.fallback);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render_escaped(b)?;
+        if b.len() < old_len {
+            return Err(RenderError::BufSize);
+        }
+        if self.is_empty(b, old_len) {
+            unsafe { b._set_len(old_len) };
+            super::escape::escape_to_buf(self.fallback, b);
+        }
+        Ok(())
+    }
+}
+
+/// render `fallback` if the expression is rendered to an empty string
+///
+/// `None` is rendered to an empty string, so this filter also works for
+/// `Option` values.
+///
+/// # Examples
+///
+/// ```text
+/// <%= user.nickname | default("anonymous") %>
+/// ```
+///
+/// result (if `user.nickname` is `None` or `Some("")`):
+///
+/// ```text
+/// anonymous
+/// ```
+#[inline]
+pub fn default<'a, T: Render + ?Sized>(
+    expr: &'a T,
+    fallback: &'a str,
+) -> DefaultValue<'a, T> {
+    DefaultValue {
+        expr,
+        fallback,
+        blank: false,
+    }
+}
+
+/// render `fallback` if the expression is rendered to an empty or whitespace-only
+/// string
+///
+/// # Examples
+///
+/// ```text
+/// <%= post.summary | default_if_blank("No summary") %>
+/// ```
+///
+/// result (if `post.summary` is `"  \n"`):
+///
+/// ```text
+/// No summary
+/// ```
+#[inline]
+pub fn default_if_blank<'a, T: Render + ?Sized>(
+    expr: &'a T,
+    fallback: &'a str,
+) -> DefaultValue<'a, T> {
+    DefaultValue {
+        expr,
+        fallback,
+        blank: true,
+    }
+}
+
+/// Helper struct for 'humanize_duration' filter
+pub struct HumanizeDuration<'a>(&'a Duration, usize);
+
+impl<'a> Render for HumanizeDuration<'a> {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        const UNITS: [(u64, &str, &str); 4] = [
+            (86400, "day", "days"),
+            (3600, "hour", "hours"),
+            (60, "minute", "minutes"),
+            (1, "second", "seconds"),
+        ];
+
+        let mut remain = self.0.as_secs();
+        let mut written = 0;
+
+        for &(secs, singular, plural) in UNITS.iter() {
+            if written >= self.1.max(1) {
+                break;
+            }
+
+            let count = remain / secs;
+            remain %= secs;
+            if count == 0 {
+                continue;
+            }
+
+            if written > 0 {
+                b.push(' ');
+            }
+            count.render(b)?;
+            b.push(' ');
+            b.push_str(if count == 1 { singular } else { plural });
+            written += 1;
+        }
+
+        if written == 0 {
+            b.push_str("0 seconds");
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // escape is not required
+        self.render(b)
+    }
+}
+
+/// render a duration as human readable text such as `2 days 3 hours`
+///
+/// At most `max_units` non-zero components are rendered, and smaller units are
+/// dropped. Durations shorter than a second are rendered as `0 seconds`.
+///
+/// # Examples
+///
+/// ```text
+/// <%= Duration::from_secs(183900) | humanize_duration(2) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 2 days 3 hours
+/// ```
+#[inline]
+pub fn humanize_duration(expr: &Duration, max_units: usize) -> HumanizeDuration<'_> {
+    HumanizeDuration(expr, max_units)
+}
+
+/// Table of the phrases used by 'timeago' filter
+#[derive(Clone, Copy, Debug)]
+pub struct TimeAgoLocale {
+    /// Phrase for the time within 10 seconds
+    pub just_now: &'static str,
+    /// Phrase for the past time. `{}` is replaced with the amount, which is placed
+    /// before the phrase if `{}` is omitted
+    pub past: &'static str,
+    /// Phrase for the future time. `{}` is replaced with the amount, which is
+    /// placed before the phrase if `{}` is omitted
+    pub future: &'static str,
+    /// Singular and plural names of second, minute, hour, day, week, month and year
+    pub units: [(&'static str, &'static str); 7],
+}
+
+impl TimeAgoLocale {
+    /// English phrases
+    pub const ENGLISH: TimeAgoLocale = TimeAgoLocale {
+        just_now: "just now",
+        past: "{} ago",
+        future: "in {}",
+        units: [
+            ("second", "seconds"),
+            ("minute", "minutes"),
+            ("hour", "hours"),
+            ("day", "days"),
+            ("week", "weeks"),
+            ("month", "months"),
+            ("year", "years"),
+        ],
+    };
+}
+
+impl Default for TimeAgoLocale {
+    #[inline]
+    fn default() -> Self {
+        Self::ENGLISH
+    }
+}
+
+/// Points in time which can be rendered by 'timeago' filter
+pub trait Elapsed {
+    /// Returns the time elapsed since `self`, and whether `self` is in the future
+    fn elapsed(&self) -> (Duration, bool);
+}
+
+impl Elapsed for Duration {
+    #[inline]
+    fn elapsed(&self) -> (Duration, bool) {
+        (*self, false)
+    }
+}
+
+impl Elapsed for SystemTime {
+    #[inline]
+    fn elapsed(&self) -> (Duration, bool) {
+        match SystemTime::now().duration_since(*self) {
+            Ok(d) => (d, false),
+            Err(e) => (e.duration(), true),
+        }
+    }
+}
+
+cfg_chrono! {
+    impl<Tz: chrono::TimeZone> Elapsed for chrono::DateTime<Tz> {
+        #[inline]
+        fn elapsed(&self) -> (Duration, bool) {
+            Elapsed::elapsed(&SystemTime::from(self.clone()))
+        }
+    }
+}
+
+cfg_time! {
+    impl Elapsed for time::OffsetDateTime {
+        #[inline]
+        fn elapsed(&self) -> (Duration, bool) {
+            Elapsed::elapsed(&SystemTime::from(*self))
+        }
+    }
+}
+
+/// Helper struct for 'timeago' filter
+pub struct TimeAgo<'a, T: ?Sized> {
+    expr: &'a T,
+    locale: &'a TimeAgoLocale,
+}
+
+impl<'a, T: Elapsed + ?Sized> Render for TimeAgo<'a, T> {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        const UNITS: [u64; 7] = [1, 60, 3600, 86400, 7 * 86400, 30 * 86400, 365 * 86400];
+        const LIMITS: [u64; 7] = [
+            60,
+            3600,
+            86400,
+            7 * 86400,
+            30 * 86400,
+            365 * 86400,
+            u64::MAX,
+        ];
+
+        let (elapsed, future) = self.expr.elapsed();
+        let secs = elapsed.as_secs();
+        if secs < 10 {
+            b.push_str(self.locale.just_now);
+            return Ok(());
+        }
+
+        let unit = LIMITS.iter().position(|&limit| secs < limit).unwrap();
+        let count = secs / UNITS[unit];
+        let (singular, plural) = self.locale.units[unit];
+
+        let pattern = if future {
+            self.locale.future
+        } else {
+            self.locale.past
+        };
+        let (before, separator, after) = match pattern.find("{}") {
+            Some(pos) => (&pattern[..pos], "", &pattern[pos + 2..]),
+            // the phrase without placeholder follows the amount
+            None if !pattern.is_empty() => ("", " ", pattern),
+            None => ("", "", ""),
+        };
+        b.push_str(before);
+        count.render(b)?;
+        b.push(' ');
+        b.push_str(if count == 1 { singular } else { plural });
+        b.push_str(separator);
+        b.push_str(after);
+        Ok(())
+    }
+}
+
+/// render the point in time as a phrase relative to the current time, such as
+/// `3 hours ago`
+///
+/// `Duration` is treated as the time elapsed until now. `SystemTime` is also
+/// accepted, and `chrono::DateTime` or `time::OffsetDateTime` if the
+/// corresponding feature is enabled.
+///
+/// # Examples
+///
+/// ```text
+/// <%= Duration::from_secs(3 * 3600) | timeago %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 3 hours ago
+/// ```
+#[inline]
+pub fn timeago<T: Elapsed + ?Sized>(expr: &T) -> TimeAgo<'_, T> {
+    TimeAgo {
+        expr,
+        locale: &TimeAgoLocale::ENGLISH,
+    }
+}
+
+/// render the point in time as a phrase relative to the current time with the
+/// given locale table
+///
+/// # Examples
+///
+/// ```text
+/// <%= comment.posted_at | timeago_locale(&FRENCH) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// il y a 3 heures
+/// ```
+#[inline]
+pub fn timeago_locale<'a, T: Elapsed + ?Sized>(
+    expr: &'a T,
+    locale: &'a TimeAgoLocale,
+) -> TimeAgo<'a, T> {
+    TimeAgo { expr, locale }
+}
+
+/// Runtime composition of header, body and footer contents
+///
+/// Each part is rendered in order using its own `Render` implementation, which is
+/// useful for applying a site layout around page contents without template
+/// inheritance.
+#[derive(Clone, Debug, Default)]
+pub struct Layout<H, B, F> {
+    /// contents rendered first
+    pub header: H,
+    /// contents rendered between header and footer
+    pub body: B,
+    /// contents rendered last
+    pub footer: F,
+}
+
+impl<H, B, F> Layout<H, B, F> {
+    /// Create a new layout from its parts
+    #[inline]
+    pub fn new(header: H, body: B, footer: F) -> Self {
+        Self {
+            header,
+            body,
+            footer,
+        }
+    }
+}
+
+impl<H: Render, B: Render, F: Render> Render for Layout<H, B, F> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.header.render(b)?;
+        self.body.render(b)?;
+        self.footer.render(b)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.header.render_escaped(b)?;
+        self.body.render_escaped(b)?;
+        self.footer.render_escaped(b)
+    }
+}
+
+/// Helper struct for 'gauge' filter
+pub struct Gauge {
+    value: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Render for Gauge {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let value = if self.value.is_nan() {
+            self.min
+        } else {
+            self.value.max(self.min).min(self.max)
+        };
+        let percent = if self.max > self.min {
+            ((value - self.min) / (self.max - self.min) * 10000.0).round() / 100.0
+        } else {
+            0.0
+        };
+
+        b.push_str("<div role=\"progressbar\" aria-valuenow=\"");
+        render_number(value, b)?;
+        b.push_str("\" aria-valuemin=\"");
+        render_number(self.min, b)?;
+        b.push_str("\" aria-valuemax=\"");
+        render_number(self.max, b)?;
+        b.push_str("\"><div style=\"width: ");
+        render_number(percent, b)?;
+        b.push_str("%\"></div></div>");
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // the contents are markup, so they must not be escaped
+        self.render(b)
+    }
+}
+
+/// render a float without the fractional part if it is an integer
+fn render_number(value: f64, b: &mut Buffer) -> Result<(), RenderError> {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        (value as i64).render(b)
+    } else {
+        value.render(b)
+    }
+}
+
+/// render a number as a progress bar with ARIA attributes
+///
+/// The value is clamped into `min..=max`. The markup is always rendered without
+/// escaping.
+///
+/// # Examples
+///
+/// ```text
+/// <%= 42 | gauge(0.0, 100.0) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <div role="progressbar" aria-valuenow="42" aria-valuemin="0" aria-valuemax="100"><div style="width: 42%"></div></div>
+/// ```
+#[inline]
+pub fn gauge<T: Into<f64> + Copy>(expr: &T, min: f64, max: f64) -> Gauge {
+    Gauge {
+        value: (*expr).into(),
+        min,
+        max,
+    }
+}
+
+/// write the standard base64 (RFC 4648, with padding) representation of `data`
+fn base64_encode_to(data: &[u8], b: &mut Buffer) {
+    const TABLE: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    b.reserve((data.len() + 2) / 3 * 4);
+    let mut chunks = data.chunks_exact(3);
+    for chunk in &mut chunks {
+        let n = (chunk[0] as u32) << 16 | (chunk[1] as u32) << 8 | chunk[2] as u32;
+        for shift in [18, 12, 6, 0].iter() {
+            b.push(TABLE[(n >> shift & 0x3f) as usize] as char);
+        }
+    }
+
+    match *chunks.remainder() {
+        [x] => {
+            let n = (x as u32) << 16;
+            b.push(TABLE[(n >> 18 & 0x3f) as usize] as char);
+            b.push(TABLE[(n >> 12 & 0x3f) as usize] as char);
+            b.push_str("==");
+        }
+        [x, y] => {
+            let n = (x as u32) << 16 | (y as u32) << 8;
+            b.push(TABLE[(n >> 18 & 0x3f) as usize] as char);
+            b.push(TABLE[(n >> 12 & 0x3f) as usize] as char);
+            b.push(TABLE[(n >> 6 & 0x3f) as usize] as char);
+            b.push('=');
+        }
+        _ => {}
+    }
+}
+
+/// Helper struct for 'base64' filter
+pub struct Base64<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Render for Base64<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        base64_encode_to(self.0.as_ref(), b);
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // base64 alphabet does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+/// encode the bytes with standard base64 encoding
+///
+/// # Examples
+///
+/// ```text
+/// <%= b"Hello, world!" | base64 %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// SGVsbG8sIHdvcmxkIQ==
+/// ```
+#[inline]
+pub fn base64<T: AsRef<[u8]> + ?Sized>(expr: &T) -> Base64<'_, T> {
+    Base64(expr)
+}
+
+/// Helper struct for 'hex' and 'hex_upper' filters
+pub struct Hex<'a, T: ?Sized> {
+    expr: &'a T,
+    upper: bool,
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Render for Hex<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let table: &[u8; 16] = if self.upper {
+            b"0123456789ABCDEF"
+        } else {
+            b"0123456789abcdef"
+        };
+
+        let data = self.expr.as_ref();
+        b.reserve(data.len() * 2);
+        for &byte in data {
+            b.push(table[(byte >> 4) as usize] as char);
+            b.push(table[(byte & 0xf) as usize] as char);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // hex digits do not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+/// encode the bytes as lowercase hexadecimal digits
+///
+/// # Examples
+///
+/// ```text
+/// <%= b"\x12\xab\xff" | hex %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 12abff
+/// ```
+#[inline]
+pub fn hex<T: AsRef<[u8]> + ?Sized>(expr: &T) -> Hex<'_, T> {
+    Hex { expr, upper: false }
+}
+
+/// encode the bytes as uppercase hexadecimal digits
+///
+/// # Examples
+///
+/// ```text
+/// <%= b"\x12\xab\xff" | hex_upper %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 12ABFF
+/// ```
+#[inline]
+pub fn hex_upper<T: AsRef<[u8]> + ?Sized>(expr: &T) -> Hex<'_, T> {
+    Hex { expr, upper: true }
+}
+
+/// Helper struct for 'data_uri' filter
+pub struct DataUri<'a, T: ?Sized> {
+    expr: &'a T,
+    mime: &'a str,
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Render for DataUri<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        b.push_str("data:");
+        b.push_str(self.mime);
+        b.push_str(";base64,");
+        base64_encode_to(self.expr.as_ref(), b);
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        b.push_str("data:");
+        super::escape::escape_to_buf(self.mime, b);
+        b.push_str(";base64,");
+        base64_encode_to(self.expr.as_ref(), b);
+        Ok(())
+    }
+}
+
+/// render the bytes as a base64-encoded data URI with the given MIME type
+///
+/// # Examples
+///
+/// ```text
+/// <img src="<%= icon_png | data_uri("image/png") %>">
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <img src="data:image/png;base64,iVBORw0KGgo...">
+/// ```
+#[inline]
+pub fn data_uri<'a, T: AsRef<[u8]> + ?Sized>(
+    expr: &'a T,
+    mime: &'a str,
+) -> DataUri<'a, T> {
+    DataUri { expr, mime }
+}
+
+cfg_sha2! {
+    /// Helper struct for 'sri' filter
+    pub struct Sri<'a, T: ?Sized>(&'a T);
+
+    impl<'a, T: AsRef<[u8]> + ?Sized> Render for Sri<'a, T> {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            use sha2::{Digest, Sha384};
+
+            let digest = Sha384::digest(self.0.as_ref());
+            b.push_str("sha384-");
+            base64_encode_to(&digest, b);
+            Ok(())
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // base64 alphabet does not contain any characters to be escaped
+            self.render(b)
+        }
+    }
+
+    /// render the subresource integrity hash (SHA-384) of the given bytes
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <script src="/app.js" integrity="<%= app_js | sri %>"></script>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// <script src="/app.js" integrity="sha384-..."></script>
+    /// ```
+    #[inline]
+    pub fn sri<T: AsRef<[u8]> + ?Sized>(expr: &T) -> Sri<'_, T> {
+        Sri(expr)
+    }
+}
+
+/// Helper struct for 'with_sign' filter
+pub struct WithSign(i64);
+
+impl Render for WithSign {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        if self.0 > 0 {
+            b.push('+');
+        }
+        self.0.render(b)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // signed integer does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+/// render an integer with an explicit sign
+///
+/// Positive numbers are prefixed with `+`, negative numbers with `-`, and zero is
+/// rendered without any sign.
+///
+/// # Examples
+///
+/// ```text
+/// <%= 5 | with_sign %>, <%= -3 | with_sign %>, <%= 0 | with_sign %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// +5, -3, 0
+/// ```
+#[inline]
+pub fn with_sign<T: Into<i64> + Copy>(expr: &T) -> WithSign {
+    WithSign((*expr).into())
+}
+
+/// write ascii digits with a comma inserted between each group of thousands
+fn push_grouped_digits(digits: &str, b: &mut Buffer) {
+    let head = match digits.len() % 3 {
+        0 => digits.len().min(3),
+        n => n,
+    };
+    b.push_str(&digits[..head]);
+    for group in digits.as_bytes()[head..].chunks(3) {
+        b.push(',');
+        b.push_str(std::str::from_utf8(group).unwrap());
+    }
+}
+
+/// Numeric types which can be formatted by 'currency' filter
+pub trait CurrencyAmount {
+    /// Returns whether the amount is negative, and its absolute value rounded to
+    /// `decimals` places
+    fn format_abs(&self, decimals: usize) -> (bool, String);
+}
+
+macro_rules! currency_amount_float {
+    ($($t:ty),*) => {
+        $(
+            impl CurrencyAmount for $t {
+                #[inline]
+                fn format_abs(&self, decimals: usize) -> (bool, String) {
+                    let value = f64::from(*self);
+                    (value.is_sign_negative(), format!("{:.*}", decimals, value.abs()))
+                }
+            }
+        )*
+    }
+}
+
+currency_amount_float!(f32, f64, i8, i16, i32, u8, u16, u32);
+
+cfg_decimal! {
+    impl CurrencyAmount for rust_decimal::Decimal {
+        #[inline]
+        fn format_abs(&self, decimals: usize) -> (bool, String) {
+            use rust_decimal::RoundingStrategy;
+
+            let value = self
+                .round_dp_with_strategy(decimals as u32, RoundingStrategy::MidpointAwayFromZero);
+            (value.is_sign_negative(), format!("{:.*}", decimals, value.abs()))
+        }
+    }
+}
+
+cfg_decimal! {
+    /// Helper struct for 'decimal' filter
+    pub struct RoundDecimal(rust_decimal::Decimal);
+
+    impl Render for RoundDecimal {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            self.0.render(b)
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            self.0.render(b)
+        }
+    }
+
+    /// round the decimal number to `scale` places, and render it with exactly
+    /// `scale` fractional digits
+    ///
+    /// Midpoint values are rounded away from zero. The scale is limited to 28.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= price | decimal(2) %>
+    /// ```
+    ///
+    /// result (`price = Decimal::new(12345, 3)`):
+    ///
+    /// ```text
+    /// 12.35
+    /// ```
+    #[inline]
+    pub fn decimal(expr: &rust_decimal::Decimal, scale: u32) -> RoundDecimal {
+        use rust_decimal::RoundingStrategy;
+
+        let scale = scale.min(28);
+        let mut value =
+            expr.round_dp_with_strategy(scale, RoundingStrategy::MidpointAwayFromZero);
+        value.rescale(scale);
+        RoundDecimal(value)
+    }
+}
+
+/// Helper struct for 'currency' and 'currency_with' filters
+pub struct Currency<'a, T: ?Sized> {
+    expr: &'a T,
+    symbol: &'a str,
+    decimals: usize,
+    // `symbol` is an ISO 4217 code which is always separated from the amount
+    code: bool,
+}
+
+impl<'a, T: ?Sized> Currency<'a, T> {
+    /// Set the number of decimal places (default: 2)
+    #[inline]
+    pub fn decimals(mut self, decimals: usize) -> Self {
+        self.decimals = decimals;
+        self
+    }
+}
+
+impl<'a, T: CurrencyAmount + ?Sized> Currency<'a, T> {
+    // returns the strings placed before and after the amount, and whether the
+    // prefix should be separated from the amount
+    fn symbol(&self) -> (&'a str, &'a str, bool) {
+        let symbol = match self.symbol {
+            "USD" => "$",
+            "EUR" => "€",
+            "GBP" => "£",
+            "JPY" | "CNY" => "¥",
+            "KRW" => "₩",
+            "INR" => "₹",
+            "RUB" => "₽",
+            symbol if self.code => return (symbol, "", true),
+            symbol => symbol,
+        };
+
+        match symbol.find("{}") {
+            Some(pos) => (&symbol[..pos], &symbol[pos + 2..], false),
+            None => (
+                symbol,
+                "",
+                matches!(symbol.chars().last(), Some(c) if c.is_alphabetic()),
+            ),
+        }
+    }
+
+    fn render_impl(&self, b: &mut Buffer, escape: bool) -> Result<(), RenderError> {
+        let (negative, formatted) = self.expr.format_abs(self.decimals);
+        let (int_part, frac_part) = match formatted.find('.') {
+            Some(pos) => formatted.split_at(pos),
+            None => (formatted.as_str(), ""),
+        };
+
+        let (prefix, suffix, separate) = self.symbol();
+        let push_symbol = |s: &str, b: &mut Buffer| {
+            if escape {
+                super::escape::escape_to_buf(s, b);
+            } else {
+                b.push_str(s);
+            }
+        };
+
+        if negative && formatted.bytes().any(|c| c > b'0' && c <= b'9') {
+            b.push('-');
+        }
+
+        push_symbol(prefix, b);
+        if separate {
+            // separate currency code from the amount
+            b.push(' ');
+        }
+        push_grouped_digits(int_part, b);
+        b.push_str(frac_part);
+        push_symbol(suffix, b);
+        Ok(())
+    }
+}
+
+impl<'a, T: CurrencyAmount + ?Sized> Render for Currency<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, true)
+    }
+}
+
+/// format the number as a currency amount
+///
+/// The amount is rendered with two decimal places and thousands separators. The
+/// currency symbol is looked up from the ISO 4217 code, and unknown codes are
+/// rendered as a prefix instead. Use `decimals` method to change the number of
+/// decimal places.
+///
+/// If `decimal` feature is enabled, `rust_decimal::Decimal` is also accepted.
+///
+/// # Examples
+///
+/// ```text
+/// <%= 1234.56 | currency("USD") %>, <%= 1234.5 | currency("CHF") %>,
+/// <%- currency(&1234.7, "JPY").decimals(0) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// $1,234.56, CHF 1,234.50,
+/// ¥1,235
+/// ```
+#[inline]
+pub fn currency<'a, T: CurrencyAmount + ?Sized>(
+    expr: &'a T,
+    code: &'a str,
+) -> Currency<'a, T> {
+    Currency {
+        expr,
+        symbol: code,
+        decimals: 2,
+        code: true,
+    }
+}
+
+/// format the number as a currency amount with the given symbol and decimal places
+///
+/// The amount is rendered with `decimals` decimal places and thousands
+/// separators. `symbol` is placed before the amount, unless it contains `{}`
+/// placeholder, in which case the amount is placed at the position of the
+/// placeholder. ISO 4217 codes of the major currencies are replaced with their
+/// symbols, and other alphabetic codes are separated from the amount with a space.
+///
+/// If `decimal` feature is enabled, `rust_decimal::Decimal` is also accepted.
+///
+/// # Examples
+///
+/// ```text
+/// <%= 1234.56 | currency_with("USD", 2) %>, <%= 1234.5 | currency_with("CHF", 2) %>,
+/// <%= 1234.56 | currency_with("{} €", 1) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// $1,234.56, CHF 1,234.50,
+/// 1,234.6 €
+/// ```
+#[inline]
+pub fn currency_with<'a, T: CurrencyAmount + ?Sized>(
+    expr: &'a T,
+    symbol: &'a str,
+    decimals: usize,
+) -> Currency<'a, T> {
+    Currency {
+        expr,
+        symbol,
+        decimals,
+        code: false,
+    }
+}
+
+/// Helper struct for 'percent' filter
+pub struct Percent {
+    value: f64,
+    precision: usize,
+}
+
+impl Render for Percent {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let value = self.value * 100.0;
+        if !value.is_finite() {
+            value.render(b)?;
+        } else {
+            let formatted = format!("{:.*}", self.precision, value.abs());
+            if value < 0.0 && formatted.bytes().any(|c| c > b'0' && c <= b'9') {
+                b.push('-');
+            }
+            b.push_str(&formatted);
+        }
+        b.push('%');
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // percentage does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+/// render the ratio as a percentage rounded to `precision` decimal places
+///
+/// # Examples
+///
+/// ```text
+/// <%= 0.4567 | percent(1) %>, <%= 1 | percent(0) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 45.7%, 100%
+/// ```
+#[inline]
+pub fn percent<T: Into<f64> + Copy>(expr: &T, precision: usize) -> Percent {
+    Percent {
+        value: (*expr).into(),
+        precision,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Parsed format spec of 'fmt' filter
+struct FormatSpec {
+    fill: char,
+    align: Option<Align>,
+    plus: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl FormatSpec {
+    // parse `{:[[fill]align][+][0][width][.precision]}`
+    fn parse(spec: &str) -> Option<FormatSpec> {
+        fn digits(s: &str) -> (Option<usize>, &str) {
+            let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+            (s[..end].parse().ok(), &s[end..])
+        }
+
+        fn align(c: char) -> Option<Align> {
+            match c {
+                '<' => Some(Align::Left),
+                '^' => Some(Align::Center),
+                '>' => Some(Align::Right),
+                _ => None,
+            }
+        }
+
+        let mut s = spec.strip_prefix("{")?.strip_suffix('}')?;
+        s = s.strip_prefix(':').unwrap_or(s);
+
+        let mut result = FormatSpec {
+            fill: ' ',
+            align: None,
+            plus: false,
+            zero: false,
+            width: 0,
+            precision: None,
+        };
+
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(fill), Some(c)) if align(c).is_some() => {
+                result.fill = fill;
+                result.align = align(c);
+                s = &s[fill.len_utf8() + 1..];
+            }
+            (Some(c), _) if align(c).is_some() => {
+                result.align = align(c);
+                s = &s[1..];
+            }
+            _ => {}
+        }
+
+        if let Some(rest) = s.strip_prefix('+') {
+            result.plus = true;
+            s = rest;
+        }
+        if let Some(rest) = s.strip_prefix('0') {
+            result.zero = true;
+            s = rest;
+        }
+
+        let (width, rest) = digits(s);
+        result.width = width.unwrap_or(0);
+        s = rest;
+
+        if let Some(rest) = s.strip_prefix('.') {
+            let (precision, rest) = digits(rest);
+            result.precision = Some(precision?);
+            s = rest;
+        }
+
+        if s.is_empty() {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+// insert `count` copies of `fill` at `pos` in the buffer
+fn insert_fill(b: &mut Buffer, pos: usize, fill: char, count: usize) {
+    let mut encoded = [0u8; 4];
+    let fill = fill.encode_utf8(&mut encoded).as_bytes();
+    let total = fill.len() * count;
+    let len = b.len();
+    b.reserve(total);
+
+    unsafe {
+        let base = b.as_mut_ptr();
+        ptr::copy(base.add(pos), base.add(pos + total), len - pos);
+        for i in 0..count {
+            ptr::copy_nonoverlapping(
+                fill.as_ptr(),
+                base.add(pos + i * fill.len()),
+                fill.len(),
+            );
+        }
+        // SAFETY: `total` bytes were reserved above, and the inserted contents are
+        // valid UTF-8
+        b._set_len(len + total);
+    }
+}
+
+/// Helper struct for 'fmt' filter
+pub struct Fmt<'a, T: ?Sized> {
+    expr: &'a T,
+    spec: &'a str,
+}
+
+impl<'a, T: fmt::Display + ?Sized> Fmt<'a, T> {
+    fn render_impl(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let spec = FormatSpec::parse(self.spec).ok_or_else(|| {
+            RenderError::new(&format!("invalid format spec: `{}`", self.spec))
+        })?;
+
+        let old_len = b.len();
+        match spec.precision {
+            Some(precision) => fmt::write(b, format_args!("{:.*}", precision, self.expr)),
+            None => fmt::write(b, format_args!("{}", self.expr)),
+        }?;
+
+        let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        let numeric = content
+            .trim_start_matches(['-', '+'])
+            .starts_with(|c: char| c.is_ascii_digit());
+        let has_sign = content.starts_with(['-', '+']);
+        if spec.plus && numeric && !has_sign {
+            insert_fill(b, old_len, '+', 1);
+        }
+
+        let len = b.as_str()[old_len..].chars().count();
+        if len >= spec.width {
+            return Ok(());
+        }
+        let pad = spec.width - len;
+
+        if spec.zero && spec.align.is_none() && numeric {
+            // pad with zeros after the sign
+            let sign_len = if has_sign || spec.plus { 1 } else { 0 };
+            insert_fill(b, old_len + sign_len, '0', pad);
+            return Ok(());
+        }
+
+        let align =
+            spec.align
+                .unwrap_or(if numeric { Align::Right } else { Align::Left });
+        let before = match align {
+            Align::Left => 0,
+            Align::Center => pad / 2,
+            Align::Right => pad,
+        };
+        insert_fill(b, old_len, spec.fill, before);
+        let end = b.len();
+        insert_fill(b, end, spec.fill, pad - before);
+        Ok(())
+    }
+}
+
+impl<'a, T: fmt::Display + ?Sized> Render for Fmt<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.render_impl(b)?;
+
+        let formatted = b.as_str()[old_len..].to_owned();
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&formatted, b);
+        Ok(())
+    }
+}
+
+/// format the value with the format spec of `format!` macro
+///
+/// The spec is written as `{:[[fill]align][+][0][width][.precision]}` and parsed at
+/// render time, so the width and precision can be changed without allocating a
+/// `String` with `format!`. As in `format!`, numbers are right-aligned and other
+/// values are left-aligned by default. An error is returned if the spec is
+/// invalid.
+///
+/// # Examples
+///
+/// ```text
+/// [<%= 3.14159 | fmt("{:>8.2}") %>] [<%= 42 | fmt("{:+05}") %>] [<%= "ab" | fmt("{:*^6}") %>]
+/// ```
+///
+/// result:
+///
+/// ```text
+/// [    3.14] [+0042] [**ab**]
+/// ```
+#[inline]
+pub fn fmt<'a, T: fmt::Display + ?Sized>(expr: &'a T, spec: &'a str) -> Fmt<'a, T> {
+    Fmt { expr, spec }
+}
+
+/// Helper struct for 'filesizeformat' and 'filesizeformat_si' filter
+pub struct FileSizeFormat {
+    bytes: Option<u64>,
+    si: bool,
+}
+
+impl Render for FileSizeFormat {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let bytes = self
+            .bytes
+            .ok_or_else(|| RenderError::new("file size out of range"))?;
+        let (base, units) = if self.si {
+            (1000.0, ["kB", "MB", "GB", "TB", "PB", "EB"])
+        } else {
+            (1024.0, ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"])
+        };
+
+        if (bytes as f64) < base {
+            bytes.render(b)?;
+            b.push_str(if bytes == 1 { " byte" } else { " bytes" });
+            return Ok(());
+        }
+
+        let mut value = bytes as f64 / base;
+        let mut unit = 0;
+        // move to the next unit if the value is rounded up to `base`
+        while (value * 10.0).round() >= base * 10.0 && unit + 1 < units.len() {
+            value /= base;
+            unit += 1;
+        }
+
+        fmt::write(b, format_args!("{:.1} {}", value, units[unit]))
+            .map_err(|e| RenderError::from(e))
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // file size does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+/// render the number of bytes as a human-readable file size in binary units
+///
+/// # Examples
+///
+/// ```text
+/// <%= 1 | filesizeformat %>, <%= 1500000 | filesizeformat %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 1 byte, 1.4 MiB
+/// ```
+#[inline]
+pub fn filesizeformat<T: TryInto<u64> + Copy>(expr: &T) -> FileSizeFormat {
+    FileSizeFormat {
+        bytes: (*expr).try_into().ok(),
+        si: false,
+    }
+}
+
+/// render the number of bytes as a human-readable file size in SI units
+///
+/// # Examples
+///
+/// ```text
+/// <%= 999 | filesizeformat_si %>, <%= 1400000 | filesizeformat_si %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 999 bytes, 1.4 MB
+/// ```
+#[inline]
+pub fn filesizeformat_si<T: TryInto<u64> + Copy>(expr: &T) -> FileSizeFormat {
+    FileSizeFormat {
+        bytes: (*expr).try_into().ok(),
+        si: true,
+    }
+}
+
+/// Numeric types which can be formatted by 'humanize' filter
+pub trait HumanizeNumber {
+    /// Returns the value as `f64`
+    fn to_f64(&self) -> f64;
+}
+
+macro_rules! humanize_number {
+    ($($t:ty),*) => {
+        $(
+            impl HumanizeNumber for $t {
+                #[inline]
+                fn to_f64(&self) -> f64 {
+                    *self as f64
+                }
+            }
+        )*
+    }
+}
+
+humanize_number!(
+    f32, f64, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+/// Helper struct for 'humanize' and 'humanize_with' filters
+pub struct Humanize {
+    value: f64,
+    precision: usize,
+}
+
+impl Render for Humanize {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        const UNITS: [&str; 5] = ["", "k", "M", "B", "T"];
+
+        if !self.value.is_finite() {
+            return self.value.render(b);
+        }
+
+        let mut value = self.value.abs();
+        let mut unit = 0;
+        while value >= 1000.0 && unit + 1 < UNITS.len() {
+            value /= 1000.0;
+            unit += 1;
+        }
+
+        let mut formatted = format!("{:.*}", self.precision, value);
+        // move to the next unit if the value is rounded up to 1000
+        if unit + 1 < UNITS.len() && formatted.parse::<f64>().unwrap_or(0.0) >= 1000.0 {
+            value /= 1000.0;
+            unit += 1;
+            formatted = format!("{:.*}", self.precision, value);
+        }
+
+        if formatted.contains('.') {
+            let len = formatted.trim_end_matches('0').trim_end_matches('.').len();
+            formatted.truncate(len);
+        }
+
+        if self.value < 0.0 && formatted.bytes().any(|c| c > b'0' && c <= b'9') {
+            b.push('-');
+        }
+        b.push_str(&formatted);
+        b.push_str(UNITS[unit]);
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // humanized number does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+/// abbreviate the large number with k/M/B/T suffixes, rounded to 1 decimal place
+///
+/// Trailing zeros after the decimal point are omitted.
+///
+/// # Examples
+///
+/// ```text
+/// <%= 999 | humanize %>, <%= 1200 | humanize %>, <%= 3400000 | humanize %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 999, 1.2k, 3.4M
+/// ```
+#[inline]
+pub fn humanize<T: HumanizeNumber + ?Sized>(expr: &T) -> Humanize {
+    Humanize {
+        value: expr.to_f64(),
+        precision: 1,
+    }
+}
+
+/// abbreviate the large number with k/M/B/T suffixes, rounded to `precision`
+/// decimal places
+///
+/// # Examples
+///
+/// ```text
+/// <%= 1234567 | humanize_with(2) %>, <%= 1500 | humanize_with(0) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 1.23M, 2k
+/// ```
+#[inline]
+pub fn humanize_with<T: HumanizeNumber + ?Sized>(expr: &T, precision: usize) -> Humanize {
+    Humanize {
+        value: expr.to_f64(),
+        precision,
+    }
+}
+
+/// Rules for the suffixes appended by 'ordinal' filter
+///
+/// This trait is implemented for closures, so other locales can be supported
+/// without defining a new type.
+///
+/// ```
+/// use sailfish::runtime::filter::ordinal_locale;
+/// use sailfish::runtime::{Buffer, Render};
+///
+/// let french = |n: u128| if n == 1 { "er" } else { "e" };
+/// let mut buf = Buffer::new();
+/// ordinal_locale(&2, &french).render(&mut buf).unwrap();
+/// assert_eq!(buf.as_str(), "2e");
+/// ```
+pub trait OrdinalLocale {
+    /// Returns the suffix for the absolute value `n`
+    fn suffix(&self, n: u128) -> &str;
+}
+
+impl<F: Fn(u128) -> &'static str> OrdinalLocale for F {
+    #[inline]
+    fn suffix(&self, n: u128) -> &str {
+        self(n)
+    }
+}
+
+/// English ordinal suffixes (1st, 2nd, 3rd, 4th, 11th, ...)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EnglishOrdinal;
+
+impl OrdinalLocale for EnglishOrdinal {
+    fn suffix(&self, n: u128) -> &str {
+        match (n % 10, n % 100) {
+            (_, 11..=13) => "th",
+            (1, _) => "st",
+            (2, _) => "nd",
+            (3, _) => "rd",
+            _ => "th",
+        }
+    }
+}
+
+/// Helper struct for 'ordinal' and 'ordinal_locale' filters
+pub struct Ordinal<'a, L: ?Sized> {
+    value: Option<i128>,
+    locale: &'a L,
+}
+
+impl<'a, L: OrdinalLocale + ?Sized> Render for Ordinal<'a, L> {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let value = self
+            .value
+            .ok_or_else(|| RenderError::new("ordinal number out of range"))?;
+        value.render(b)?;
+        b.push_str(self.locale.suffix(value.unsigned_abs()));
+        Ok(())
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let value = self
+            .value
+            .ok_or_else(|| RenderError::new("ordinal number out of range"))?;
+        value.render(b)?;
+        super::escape::escape_to_buf(self.locale.suffix(value.unsigned_abs()), b);
+        Ok(())
+    }
+}
+
+/// append the English ordinal suffix to the integer
+///
+/// # Examples
+///
+/// ```text
+/// <%= 1 | ordinal %>, <%= 2 | ordinal %>, <%= 13 | ordinal %>, <%= 23 | ordinal %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 1st, 2nd, 13th, 23rd
+/// ```
+#[inline]
+pub fn ordinal<T: TryInto<i128> + Copy>(expr: &T) -> Ordinal<'static, EnglishOrdinal> {
+    Ordinal {
+        value: (*expr).try_into().ok(),
+        locale: &EnglishOrdinal,
+    }
+}
+
+/// append the ordinal suffix of the given locale to the integer
+///
+/// # Examples
+///
+/// ```text
+/// <%= rank | ordinal_locale(&|_| ".") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 3.
+/// ```
+#[inline]
+pub fn ordinal_locale<'a, T: TryInto<i128> + Copy, L: OrdinalLocale + ?Sized>(
+    expr: &T,
+    locale: &'a L,
+) -> Ordinal<'a, L> {
+    Ordinal {
+        value: (*expr).try_into().ok(),
+        locale,
+    }
+}
+
+/// Date and time types which can be formatted by 'date' filter
+///
+/// This trait is implemented for `chrono` types if `chrono` feature is enabled,
+/// and for `time::OffsetDateTime` if `time` feature is enabled.
+pub trait FormatDate {
+    /// Write the date formatted with strftime-like `fmt` to the buffer
+    fn format_date(&self, fmt: &str, b: &mut Buffer) -> Result<(), RenderError>;
+}
+
+cfg_chrono! {
+    impl FormatDate for chrono::NaiveDate {
+        #[inline]
+        fn format_date(&self, fmt: &str, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            write!(b, "{}", self.format(fmt)).map_err(|e| RenderError::from(e))
+        }
+    }
+
+    impl FormatDate for chrono::NaiveDateTime {
+        #[inline]
+        fn format_date(&self, fmt: &str, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            write!(b, "{}", self.format(fmt)).map_err(|e| RenderError::from(e))
+        }
+    }
+
+    impl<Tz: chrono::TimeZone> FormatDate for chrono::DateTime<Tz>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        #[inline]
+        fn format_date(&self, fmt: &str, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            write!(b, "{}", self.format(fmt)).map_err(|e| RenderError::from(e))
+        }
+    }
+}
+
+cfg_time! {
+    impl FormatDate for time::OffsetDateTime {
+        fn format_date(&self, fmt: &str, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            let mut chars = fmt.chars();
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    b.push(c);
+                    continue;
+                }
+
+                let spec = chars
+                    .next()
+                    .ok_or_else(|| RenderError::new("incomplete date format"))?;
+                let (hour12, pm) = match self.hour() {
+                    0 => (12, false),
+                    h @ 1..=11 => (h, false),
+                    12 => (12, true),
+                    h => (h - 12, true),
+                };
+                let offset = self.offset();
+                let r = match spec {
+                    'Y' => write!(b, "{}", self.year()),
+                    'C' => write!(b, "{:02}", self.year().div_euclid(100)),
+                    'y' => write!(b, "{:02}", self.year().rem_euclid(100)),
+                    'm' => write!(b, "{:02}", self.month() as u8),
+                    'b' | 'h' => write!(b, "{:.3}", self.month()),
+                    'B' => write!(b, "{}", self.month()),
+                    'd' => write!(b, "{:02}", self.day()),
+                    'e' => write!(b, "{:2}", self.day()),
+                    'j' => write!(b, "{:03}", self.ordinal()),
+                    'a' => write!(b, "{:.3}", self.weekday()),
+                    'A' => write!(b, "{}", self.weekday()),
+                    'u' => write!(b, "{}", self.weekday().number_from_monday()),
+                    'w' => write!(b, "{}", self.weekday().number_days_from_sunday()),
+                    'H' => write!(b, "{:02}", self.hour()),
+                    'k' => write!(b, "{:2}", self.hour()),
+                    'I' => write!(b, "{:02}", hour12),
+                    'l' => write!(b, "{:2}", hour12),
+                    'p' => write!(b, "{}", if pm { "PM" } else { "AM" }),
+                    'P' => write!(b, "{}", if pm { "pm" } else { "am" }),
+                    'M' => write!(b, "{:02}", self.minute()),
+                    'S' => write!(b, "{:02}", self.second()),
+                    'f' => write!(b, "{:09}", self.nanosecond()),
+                    'z' => write!(
+                        b,
+                        "{}{:02}{:02}",
+                        if offset.is_negative() { '-' } else { '+' },
+                        offset.whole_hours().unsigned_abs(),
+                        offset.minutes_past_hour().unsigned_abs()
+                    ),
+                    's' => write!(b, "{}", self.unix_timestamp()),
+                    'F' => write!(
+                        b,
+                        "{}-{:02}-{:02}",
+                        self.year(),
+                        self.month() as u8,
+                        self.day()
+                    ),
+                    'D' => write!(
+                        b,
+                        "{:02}/{:02}/{:02}",
+                        self.month() as u8,
+                        self.day(),
+                        self.year().rem_euclid(100)
+                    ),
+                    'T' => write!(
+                        b,
+                        "{:02}:{:02}:{:02}",
+                        self.hour(),
+                        self.minute(),
+                        self.second()
+                    ),
+                    'R' => write!(b, "{:02}:{:02}", self.hour(), self.minute()),
+                    '%' => write!(b, "%"),
+                    _ => {
+                        return Err(RenderError::new(&format!(
+                            "unsupported date format specifier: %{}",
+                            spec
+                        )))
+                    }
+                };
+                r.map_err(|e| RenderError::from(e))?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Helper struct for 'date' filter
+pub struct Date<'a, T: ?Sized> {
+    expr: &'a T,
+    fmt: &'a str,
+}
+
+impl<'a, T: FormatDate + ?Sized> Render for Date<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.expr.format_date(self.fmt, b)
+    }
+}
+
+/// format the date and time with strftime-like format string
+///
+/// `chrono` types are formatted with `chrono::format::strftime` syntax. For
+/// `time::OffsetDateTime`, the commonly used subset of the specifiers
+/// (`%Y %C %y %m %b %h %B %d %e %j %a %A %u %w %H %k %I %l %p %P %M %S %f %z %s
+/// %F %D %T %R %%`) is supported.
+///
+/// # Examples
+///
+/// ```text
+/// <%= created_at | date("%Y-%m-%d %H:%M") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 2024-01-31 12:34
+/// ```
+#[inline]
+pub fn date<'a, T: FormatDate + ?Sized>(expr: &'a T, fmt: &'a str) -> Date<'a, T> {
+    Date { expr, fmt }
+}
+
+/// Helper struct for 'pluralize' and 'pluralize_s' filter
+pub struct Pluralize<'a> {
+    word: &'a str,
+    suffix: &'a str,
+}
+
+impl<'a> Render for Pluralize<'a> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        b.push_str(self.word);
+        b.push_str(self.suffix);
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        super::escape::escape_to_buf(self.word, b);
+        super::escape::escape_to_buf(self.suffix, b);
+        Ok(())
+    }
+}
+
+/// render `singular` if the count is 1, otherwise `plural`
+///
+/// # Examples
+///
+/// ```text
+/// <%= items.len() %> <%= items.len() | pluralize("entry", "entries") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 3 entries
+/// ```
+#[inline]
+pub fn pluralize<'a, T: TryInto<i64> + Copy>(
+    expr: &T,
+    singular: &'a str,
+    plural: &'a str,
+) -> Pluralize<'a> {
+    let word = match (*expr).try_into() {
+        Ok(1) => singular,
+        _ => plural,
+    };
+    Pluralize { word, suffix: "" }
+}
+
+/// render `singular` if the count is 1, otherwise `singular` followed by `s`
+///
+/// # Examples
+///
+/// ```text
+/// <%= items.len() %> <%= items.len() | pluralize_s("item") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 1 item
+/// ```
+#[inline]
+pub fn pluralize_s<'a, T: TryInto<i64> + Copy>(
+    expr: &T,
+    singular: &'a str,
+) -> Pluralize<'a> {
+    let suffix = match (*expr).try_into() {
+        Ok(1) => "",
+        _ => "s",
+    };
+    Pluralize {
+        word: singular,
+        suffix,
+    }
+}
+
+/// Helper struct for 'join' filter
+pub struct Join<'a, I: ?Sized> {
+    expr: &'a I,
+    sep: &'a str,
+}
+
+impl<'a, I: ?Sized> Render for Join<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        join_impl(self.expr, self.sep, b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        join_impl(self.expr, self.sep, b, true)
+    }
+}
+
+fn join_impl<I>(
+    items: I,
+    sep: &str,
+    b: &mut Buffer,
+    escape: bool,
+) -> Result<(), RenderError>
+where
+    I: IntoIterator,
+    I::Item: Render,
+{
+    for (i, item) in items.into_iter().enumerate() {
+        if escape {
+            if i > 0 {
+                super::escape::escape_to_buf(sep, b);
+            }
+            item.render_escaped(b)?;
+        } else {
+            if i > 0 {
+                b.push_str(sep);
+            }
+            item.render(b)?;
+        }
+    }
+    Ok(())
+}
+
+/// render each element of the collection with the separator in between
+///
+/// Elements are rendered directly into the buffer with their own `Render`
+/// implementation, so they don't have to be strings.
+///
+/// # Examples
+///
+/// ```text
+/// <%= tags | join(", ") %>
+/// ```
+///
+/// result (when `tags` is `vec!["rust", "<html>"]`):
+///
+/// ```text
+/// rust, &lt;html&gt;
+/// ```
+#[inline]
+pub fn join<'a, I: ?Sized>(expr: &'a I, sep: &'a str) -> Join<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    Join { expr, sep }
+}
+
+/// Helper struct for 'sort' filter
+pub struct Sort<'a, I: ?Sized> {
+    expr: &'a I,
+    sep: &'a str,
+}
+
+impl<'a, I: ?Sized> Render for Sort<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Ord,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let mut items: Vec<_> = self.expr.into_iter().collect();
+        items.sort();
+        join_impl(items, self.sep, b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let mut items: Vec<_> = self.expr.into_iter().collect();
+        items.sort();
+        join_impl(items, self.sep, b, true)
+    }
+}
+
+/// render the elements of the collection in ascending order with the separator
+/// in between
+///
+/// # Examples
+///
+/// ```text
+/// <%= vec![3, 1, 2] | sort(", ") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 1, 2, 3
+/// ```
+#[inline]
+pub fn sort<'a, I: ?Sized>(expr: &'a I, sep: &'a str) -> Sort<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Ord,
+{
+    Sort { expr, sep }
+}
+
+/// Helper struct for 'sort_by_key' filter
+pub struct SortByKey<'a, I: ?Sized, F> {
+    expr: &'a I,
+    key: F,
+    sep: &'a str,
+}
+
+impl<'a, I: ?Sized, F, K> SortByKey<'a, I, F>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy,
+    F: Fn(<&'a I as IntoIterator>::Item) -> K,
+    K: Ord,
+{
+    #[inline]
+    fn sorted(&self) -> Vec<<&'a I as IntoIterator>::Item> {
+        let mut items: Vec<_> = self.expr.into_iter().collect();
+        items.sort_by_key(|&item| (self.key)(item));
+        items
+    }
+}
+
+impl<'a, I: ?Sized, F, K> Render for SortByKey<'a, I, F>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy,
+    F: Fn(<&'a I as IntoIterator>::Item) -> K,
+    K: Ord,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        join_impl(self.sorted(), self.sep, b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        join_impl(self.sorted(), self.sep, b, true)
+    }
+}
+
+/// render the elements of the collection in ascending order of the key with the
+/// separator in between
+///
+/// The sort is stable, so the elements with the same key keep their order.
+///
+/// # Examples
+///
+/// ```text
+/// <%= users | sort_by_key(|u| u.age, ", ") %>
+/// ```
+///
+/// result (if `User` renders its name):
+///
+/// ```text
+/// Bob, Alice, Carol
+/// ```
+#[inline]
+pub fn sort_by_key<'a, I: ?Sized, F, K>(
+    expr: &'a I,
+    key: F,
+    sep: &'a str,
+) -> SortByKey<'a, I, F>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy,
+    F: Fn(<&'a I as IntoIterator>::Item) -> K,
+    K: Ord,
+{
+    SortByKey { expr, key, sep }
+}
+
+/// Helper struct for 'unique' filter
+pub struct Unique<'a, I: ?Sized> {
+    expr: &'a I,
+    sep: &'a str,
+}
+
+impl<'a, I: ?Sized> Render for Unique<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy + Eq + Hash,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let mut seen = HashSet::new();
+        let items = self.expr.into_iter().filter(|&item| seen.insert(item));
+        join_impl(items, self.sep, b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let mut seen = HashSet::new();
+        let items = self.expr.into_iter().filter(|&item| seen.insert(item));
+        join_impl(items, self.sep, b, true)
+    }
+}
+
+/// render the distinct elements of the collection with the separator in between
+///
+/// Only the first occurrence of each element is rendered, and the order of the
+/// elements is preserved.
+///
+/// # Examples
+///
+/// ```text
+/// <%= vec!["rust", "web", "rust"] | unique(", ") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// rust, web
+/// ```
+#[inline]
+pub fn unique<'a, I: ?Sized>(expr: &'a I, sep: &'a str) -> Unique<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy + Eq + Hash,
+{
+    Unique { expr, sep }
+}
+
+/// Helper struct for 'unique_by_key' filter
+pub struct UniqueByKey<'a, I: ?Sized, F> {
+    expr: &'a I,
+    key: F,
+    sep: &'a str,
+}
+
+impl<'a, I: ?Sized, F, K> UniqueByKey<'a, I, F>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy,
+    F: Fn(<&'a I as IntoIterator>::Item) -> K,
+    K: Eq + Hash,
+{
+    fn render_impl(&self, b: &mut Buffer, escape: bool) -> Result<(), RenderError> {
+        let mut seen = HashSet::new();
+        let items = self
+            .expr
+            .into_iter()
+            .filter(|&item| seen.insert((self.key)(item)));
+        join_impl(items, self.sep, b, escape)
+    }
+}
+
+impl<'a, I: ?Sized, F, K> Render for UniqueByKey<'a, I, F>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy,
+    F: Fn(<&'a I as IntoIterator>::Item) -> K,
+    K: Eq + Hash,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, true)
+    }
+}
+
+/// render the elements of the collection with distinct keys with the separator
+/// in between
+///
+/// Only the first element for each key is rendered, and the order of the
+/// elements is preserved.
+///
+/// # Examples
+///
+/// ```text
+/// <%= vec!["Rust", "rust", "Web"] | unique_by_key(|t| t.to_lowercase(), ", ") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Rust, Web
+/// ```
+#[inline]
+pub fn unique_by_key<'a, I: ?Sized, F, K>(
+    expr: &'a I,
+    key: F,
+    sep: &'a str,
+) -> UniqueByKey<'a, I, F>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render + Copy,
+    F: Fn(<&'a I as IntoIterator>::Item) -> K,
+    K: Eq + Hash,
+{
+    UniqueByKey { expr, key, sep }
+}
+
+/// group the elements of the collection by the key for nested loops
+///
+/// The groups are returned in order of the first appearance of their keys, and
+/// the elements in each group keep their order. Unlike the other filters, this
+/// function is intended to be called inside the code blocks.
+///
+/// # Examples
+///
+/// ```text
+/// <% for (date, posts) in sailfish::runtime::filter::group_by(&posts, |p| p.date) { %>
+/// <h2><%= date %></h2>
+/// <% for post in posts { %>
+/// <p><%= post.title %></p>
+/// <% } %>
+/// <% } %>
+/// ```
+pub fn group_by<'a, I: ?Sized, F, K>(
+    expr: &'a I,
+    key: F,
+) -> Vec<(K, Vec<<&'a I as IntoIterator>::Item>)>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Copy,
+    F: Fn(<&'a I as IntoIterator>::Item) -> K,
+    K: Eq + Hash + Clone,
+{
+    let mut groups: Vec<(K, Vec<_>)> = Vec::new();
+    let mut indices: HashMap<K, usize> = HashMap::new();
+    for item in expr {
+        let k = key(item);
+        match indices.get(&k) {
+            Some(&i) => groups[i].1.push(item),
+            None => {
+                indices.insert(k.clone(), groups.len());
+                groups.push((k, vec![item]));
+            }
+        }
+    }
+    groups
+}
+
+/// split the elements of the collection into chunks of `size` elements for grid
+/// layouts
+///
+/// The last chunk may contain fewer elements. Unlike the other filters, this
+/// function is intended to be called inside the code blocks.
+///
+/// # Panics
+///
+/// Panics if `size` is 0.
+///
+/// # Examples
+///
+/// ```text
+/// <% for row in sailfish::runtime::filter::chunks(&products, 3) { %>
+/// <div class="row">
+/// <% for product in row { %>
+/// <div class="col-4"><%= product.name %></div>
+/// <% } %>
+/// </div>
+/// <% } %>
+/// ```
+pub fn chunks<'a, I: ?Sized>(
+    expr: &'a I,
+    size: usize,
+) -> Vec<Vec<<&'a I as IntoIterator>::Item>>
+where
+    &'a I: IntoIterator,
+{
+    assert!(size != 0, "chunk size must be non-zero");
+
+    let mut result = Vec::new();
+    let mut chunk = Vec::with_capacity(size);
+    for item in expr {
+        chunk.push(item);
+        if chunk.len() == size {
+            result.push(std::mem::replace(&mut chunk, Vec::with_capacity(size)));
+        }
+    }
+    if !chunk.is_empty() {
+        result.push(chunk);
+    }
+    result
+}
+
+/// collect the entries of the map sorted by key
+///
+/// This is useful for iterating over `HashMap` in deterministic order. Unlike the
+/// other filters, this function is intended to be called inside the code blocks.
+///
+/// # Examples
+///
+/// ```text
+/// <% for (name, score) in sailfish::runtime::filter::dictsort(&scores) { %>
+/// <li><%= name %>: <%= score %></li>
+/// <% } %>
+/// ```
+pub fn dictsort<'a, M: ?Sized, K, V>(expr: &'a M) -> Vec<(&'a K, &'a V)>
+where
+    &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+    K: Ord + 'a,
+    V: 'a,
+{
+    let mut entries: Vec<_> = expr.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+/// collect the entries of the map sorted by the key function
+///
+/// The sort is stable. Unlike the other filters, this function is intended to be
+/// called inside the code blocks.
+///
+/// # Examples
+///
+/// ```text
+/// <% for (name, score) in sailfish::runtime::filter::dictsort_by_key(&scores, |_, &s| std::cmp::Reverse(s)) { %>
+/// <li><%= name %>: <%= score %></li>
+/// <% } %>
+/// ```
+pub fn dictsort_by_key<'a, M: ?Sized, K, V, F, O>(
+    expr: &'a M,
+    key: F,
+) -> Vec<(&'a K, &'a V)>
+where
+    &'a M: IntoIterator<Item = (&'a K, &'a V)>,
+    K: 'a,
+    V: 'a,
+    F: Fn(&'a K, &'a V) -> O,
+    O: Ord,
+{
+    let mut entries: Vec<_> = expr.into_iter().collect();
+    entries.sort_by_key(|&(k, v)| key(k, v));
+    entries
+}
+
+/// Helper struct for 'reverse' filter
+pub struct Reverse<'a, I: ?Sized> {
+    expr: &'a I,
+    sep: &'a str,
+}
+
+impl<'a, I: ?Sized> Render for Reverse<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::IntoIter: DoubleEndedIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        join_impl(self.expr.into_iter().rev(), self.sep, b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        join_impl(self.expr.into_iter().rev(), self.sep, b, true)
+    }
+}
+
+/// render the elements of the collection in reverse order with the separator in
+/// between
+///
+/// # Examples
+///
+/// ```text
+/// <%= vec![1, 2, 3] | reverse(", ") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 3, 2, 1
+/// ```
+#[inline]
+pub fn reverse<'a, I: ?Sized>(expr: &'a I, sep: &'a str) -> Reverse<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::IntoIter: DoubleEndedIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    Reverse { expr, sep }
+}
+
+cfg_unicode_segmentation! {
+    /// Helper struct for 'reverse_str' filter
+    pub struct ReverseStr<'a, T: ?Sized>(&'a T);
+
+    impl<'a, T: Render + ?Sized> Render for ReverseStr<'a, T> {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            let old_len = b.len();
+            self.0.render(b)?;
+            replace_tail(b, old_len, reverse_str_impl)
+        }
+
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // reverse the contents before escaping not to break the entities
+            let old_len = b.len();
+            self.0.render(b)?;
+
+            let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+            let mut result = String::with_capacity(new_contents.len());
+            reverse_str_impl(new_contents, &mut result);
+
+            unsafe { b._set_len(old_len) };
+            super::escape::escape_to_buf(&result, b);
+            Ok(())
+        }
+    }
+
+    fn reverse_str_impl(s: &str, result: &mut String) {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        result.extend(s.graphemes(true).rev());
+    }
+
+    /// reverse the rendered contents by grapheme clusters
+    ///
+    /// Combining marks and emoji sequences are kept together with their base
+    /// characters.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= "noe\u{308}l" | reverse_str %>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// lëon
+    /// ```
+    #[inline]
+    pub fn reverse_str<T: Render + ?Sized>(expr: &T) -> ReverseStr<'_, T> {
+        ReverseStr(expr)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Position {
+    First,
+    Last,
+    Nth(usize),
+}
+
+/// Helper struct for 'first', 'last' and 'nth' filter
+pub struct Nth<'a, I: ?Sized> {
+    expr: &'a I,
+    position: Position,
+}
+
+impl<'a, I: ?Sized> Nth<'a, I>
+where
+    &'a I: IntoIterator,
+{
+    #[inline]
+    fn item(&self) -> Option<<&'a I as IntoIterator>::Item> {
+        let mut iter = self.expr.into_iter();
+        match self.position {
+            Position::First => iter.next(),
+            Position::Last => iter.last(),
+            Position::Nth(n) => iter.nth(n),
+        }
+    }
+}
+
+impl<'a, I: ?Sized> Render for Nth<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.item().render(b)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.item().render_escaped(b)
+    }
+}
+
+/// render the first element of the collection
+///
+/// Nothing is rendered if the collection is empty.
+///
+/// # Examples
+///
+/// ```text
+/// <%= vec!["a", "b", "c"] | first %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// a
+/// ```
+#[inline]
+pub fn first<'a, I: ?Sized>(expr: &'a I) -> Nth<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    Nth {
+        expr,
+        position: Position::First,
+    }
+}
+
+/// render the last element of the collection
+///
+/// Nothing is rendered if the collection is empty.
+///
+/// # Examples
+///
+/// ```text
+/// <%= vec!["a", "b", "c"] | last %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// c
+/// ```
+#[inline]
+pub fn last<'a, I: ?Sized>(expr: &'a I) -> Nth<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    Nth {
+        expr,
+        position: Position::Last,
+    }
+}
+
+/// render the `n`-th (zero-based) element of the collection
+///
+/// Nothing is rendered if the collection has `n` or less elements.
+///
+/// # Examples
+///
+/// ```text
+/// <%= vec!["a", "b", "c"] | nth(1) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// b
+/// ```
+#[inline]
+pub fn nth<'a, I: ?Sized>(expr: &'a I, n: usize) -> Nth<'a, I>
+where
+    &'a I: IntoIterator,
+    <&'a I as IntoIterator>::Item: Render,
+{
+    Nth {
+        expr,
+        position: Position::Nth(n),
+    }
+}
+
+/// Helper struct for 'repeat' filter
+pub struct Repeat<'a, T: ?Sized> {
+    expr: &'a T,
+    n: usize,
+}
+
+impl<'a, T: Render + ?Sized> Repeat<'a, T> {
+    // copy the contents rendered after `old_len` until they appear `n` times
+    fn repeat_tail(&self, b: &mut Buffer, old_len: usize) -> Result<(), RenderError> {
+        let len = b.len().checked_sub(old_len).ok_or(RenderError::BufSize)?;
+        if self.n == 0 {
+            unsafe { b._set_len(old_len) };
+            return Ok(());
+        }
+
+        let total = len
+            .checked_mul(self.n - 1)
+            .ok_or_else(|| RenderError::new("repeated contents too large"))?;
+        b.reserve(total);
+        unsafe {
+            let src = b.as_mut_ptr().add(old_len);
+            let mut dst = b.as_mut_ptr().add(b.len());
+            for _ in 1..self.n {
+                // SAFETY: `total` bytes were reserved above, and the source does not
+                // overlap with the destination
+                std::ptr::copy_nonoverlapping(src, dst, len);
+                dst = dst.add(len);
+            }
+            b._set_len(b.len() + total);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Render + ?Sized> Render for Repeat<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        self.repeat_tail(b, old_len)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render_escaped(b)?;
+        self.repeat_tail(b, old_len)
+    }
+}
+
+/// render the contents `n` times
+///
+/// The contents are rendered only once, and then copied.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "★" | repeat(rating) %><%= "☆" | repeat(5 - rating) %>
+/// ```
+///
+/// result (`rating = 3`):
+///
+/// ```text
+/// ★★★☆☆
+/// ```
+#[inline]
+pub fn repeat<T: Render + ?Sized>(expr: &T, n: usize) -> Repeat<'_, T> {
+    Repeat { expr, n }
+}
+
+/// Helper struct for 'ul' and 'ol' filters
+pub struct HtmlList<'a, T> {
+    tag: &'static str,
+    items: &'a [T],
+}
+
+impl<'a, T: Render> Render for HtmlList<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_escaped(b)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        b.push('<');
+        b.push_str(self.tag);
+        b.push('>');
+        for item in self.items {
+            b.push_str("<li>");
+            item.render_escaped(b)?;
+            b.push_str("</li>");
+        }
+        b.push_str("</");
+        b.push_str(self.tag);
+        b.push('>');
+        Ok(())
+    }
+}
+
+/// render the items as an unordered HTML list
+///
+/// Each item is always HTML-escaped. An empty slice is rendered as `<ul></ul>`.
+///
+/// # Examples
+///
+/// ```text
+/// <%- items | ul %>
+/// ```
+///
+/// result (when `items` is `vec!["foo", "<bar>"]`):
+///
+/// ```text
+/// <ul><li>foo</li><li>&lt;bar&gt;</li></ul>
+/// ```
+#[inline]
+pub fn ul<T: Render>(expr: &[T]) -> HtmlList<'_, T> {
+    HtmlList {
+        tag: "ul",
+        items: expr,
+    }
+}
+
+/// render the items as an ordered HTML list
+///
+/// Each item is always HTML-escaped. An empty slice is rendered as `<ol></ol>`.
+///
+/// # Examples
+///
+/// ```text
+/// <%- items | ol %>
+/// ```
+///
+/// result (when `items` is `vec!["foo", "<bar>"]`):
+///
+/// ```text
+/// <ol><li>foo</li><li>&lt;bar&gt;</li></ol>
+/// ```
+#[inline]
+pub fn ol<T: Render>(expr: &[T]) -> HtmlList<'_, T> {
+    HtmlList {
+        tag: "ol",
+        items: expr,
+    }
+}
+
+/// Helper struct for 'once' filter
+pub struct Once<'a, T: ?Sized> {
+    expr: &'a T,
+    index: usize,
+}
+
+impl<'a, T: Render + ?Sized> Render for Once<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        if self.index == 0 {
+            self.expr.render(b)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        if self.index == 0 {
+            self.expr.render_escaped(b)?;
+        }
+        Ok(())
+    }
+}
+
+/// render the contents only on the first loop iteration
+///
+/// # Examples
+///
+/// ```text
+/// <% for (i, row) in rows.iter().enumerate() { %>
+/// <%- "<tr><th>Name</th></tr>" | once(i) %>
+/// <tr><td><%= row %></td></tr>
+/// <% } %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <tr><th>Name</th></tr>
+/// <tr><td>foo</td></tr>
+///
+/// <tr><td>bar</td></tr>
+/// ```
+#[inline]
+pub fn once<T: Render + ?Sized>(expr: &T, index: usize) -> Once<'_, T> {
+    Once { expr, index }
+}
+
+/// Helper struct for 'mask' and 'mask_email' filters
+pub struct Mask<'a, T: ?Sized> {
+    expr: &'a T,
+    kind: MaskKind,
+}
+
+enum MaskKind {
+    Visible(usize),
+    Email,
+}
+
+impl<'a, T: Render + ?Sized> Mask<'a, T> {
+    fn render_impl(&self, b: &mut Buffer, escape: bool) -> Result<(), RenderError> {
+        // render without escaping so that characters are counted correctly
+        let old_len = b.len();
+        self.expr.render(b)?;
+        let contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+
+        // range of characters to be masked
+        let total = contents.chars().count();
+        let (start, end) = match self.kind {
+            MaskKind::Visible(visible) => (0, total.saturating_sub(visible)),
+            MaskKind::Email => match contents.rfind('@') {
+                Some(idx) => (1, contents[..idx].chars().count()),
+                None => (0, total),
+            },
+        };
+
+        let masked: String = contents
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i >= start && i < end { '*' } else { c })
+            .collect();
+
+        unsafe { b._set_len(old_len) };
+        if escape {
+            super::escape::escape_to_buf(&masked, b);
+        } else {
+            b.push_str(&masked);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: Render + ?Sized> Render for Mask<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, true)
+    }
+}
+
+/// replace all but the last `visible` characters of the rendered contents with `*`
+///
+/// # Examples
+///
+/// ```text
+/// <%= "4111111111111234" | mask(4) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// ************1234
+/// ```
+#[inline]
+pub fn mask<T: Render + ?Sized>(expr: &T, visible: usize) -> Mask<'_, T> {
+    Mask {
+        expr,
+        kind: MaskKind::Visible(visible),
+    }
+}
+
+/// mask the local part of an email address except for its first character
+///
+/// If the rendered contents does not contain `@`, the whole contents are masked.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "john.doe@example.com" | mask_email %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// j*******@example.com
+/// ```
+#[inline]
+pub fn mask_email<T: Render + ?Sized>(expr: &T) -> Mask<'_, T> {
+    Mask {
+        expr,
+        kind: MaskKind::Email,
+    }
+}
+
+cfg_number_words! {
+    /// Helper struct for 'number_to_words' filter
+    pub struct NumberToWords(i64);
+
+    impl NumberToWords {
+        const ONES: [&'static str; 20] = [
+            "zero", "one", "two", "three", "four", "five", "six", "seven", "eight",
+            "nine", "ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen",
+            "sixteen", "seventeen", "eighteen", "nineteen",
+        ];
+        const TENS: [&'static str; 10] = [
+            "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty",
+            "ninety",
+        ];
+        const SCALES: [&'static str; 7] = [
+            "", "thousand", "million", "billion", "trillion", "quadrillion",
+            "quintillion",
+        ];
+
+        // write number less than 1000
+        fn push_hundreds(n: u64, b: &mut Buffer) {
+            let (hundreds, rest) = (n / 100, n % 100);
+            if hundreds > 0 {
+                b.push_str(Self::ONES[hundreds as usize]);
+                b.push_str(" hundred");
+                if rest == 0 {
+                    return;
+                }
+                b.push(' ');
+            }
+
+            if rest < 20 {
+                b.push_str(Self::ONES[rest as usize]);
+            } else {
+                b.push_str(Self::TENS[(rest / 10) as usize]);
+                if rest % 10 != 0 {
+                    b.push('-');
+                    b.push_str(Self::ONES[(rest % 10) as usize]);
+                }
+            }
+        }
+    }
+
+    impl Render for NumberToWords {
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            if self.0 == 0 {
+                b.push_str("zero");
+                return Ok(());
+            }
+            if self.0 < 0 {
+                b.push_str("negative ");
+            }
+
+            let mut n = self.0.unsigned_abs();
+            let mut groups = Vec::with_capacity(Self::SCALES.len());
+            while n > 0 {
+                groups.push(n % 1000);
+                n /= 1000;
+            }
+
+            let mut first = true;
+            for (scale, &group) in groups.iter().enumerate().rev() {
+                if group == 0 {
+                    continue;
+                }
+                if !first {
+                    b.push(' ');
+                }
+                first = false;
+
+                Self::push_hundreds(group, b);
+                if scale > 0 {
+                    b.push(' ');
+                    b.push_str(Self::SCALES[scale]);
+                }
+            }
+
+            Ok(())
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // english words does not contain any characters to be escaped
+            self.render(b)
+        }
+    }
+
+    /// spell out the integer in English words
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= 1234 | number_to_words %>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// one thousand two hundred thirty-four
+    /// ```
+    #[inline]
+    pub fn number_to_words<T: Into<i64> + Copy>(expr: &T) -> NumberToWords {
+        NumberToWords((*expr).into())
+    }
+}
+
+/// Helper struct for 'active_if' and 'active_if_prefix' filters
+pub struct ActiveIf<'a> {
+    active: bool,
+    class: &'a str,
+}
+
+impl<'a> Render for ActiveIf<'a> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_escaped(b)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        if self.active {
+            b.push_str(" class=\"");
+            super::escape::escape_to_buf(self.class, b);
+            b.push('"');
+        }
+        Ok(())
+    }
+}
+
+/// render ` class="{class}"` attribute if the current path equals to the target path
+///
+/// The class name is always HTML-escaped.
+///
+/// # Examples
+///
+/// ```text
+/// <a href="/about"<%- current_path | active_if("/about", "active") %>>About</a>
+/// ```
+///
+/// result (when `current_path` is `"/about"`):
+///
+/// ```text
+/// <a href="/about" class="active">About</a>
+/// ```
+#[inline]
+pub fn active_if<'a>(current: &str, target: &str, class: &'a str) -> ActiveIf<'a> {
+    ActiveIf {
+        active: current == target,
+        class,
+    }
+}
+
+/// render ` class="{class}"` attribute if the current path starts with the target path
+///
+/// The class name is always HTML-escaped.
+///
+/// # Examples
+///
+/// ```text
+/// <a href="/blog"<%- current_path | active_if_prefix("/blog", "active") %>>Blog</a>
+/// ```
+///
+/// result (when `current_path` is `"/blog/2020/hello"`):
+///
+/// ```text
+/// <a href="/blog" class="active">Blog</a>
+/// ```
+#[inline]
+pub fn active_if_prefix<'a>(current: &str, target: &str, class: &'a str) -> ActiveIf<'a> {
+    ActiveIf {
+        active: current.starts_with(target),
+        class,
+    }
+}
+
+/// Helper struct for 'attr' filter
+pub struct Attr<'a> {
+    name: &'a str,
+    enabled: bool,
+}
+
+impl<'a> Render for Attr<'a> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_escaped(b)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        if self.enabled {
+            b.push(' ');
+            super::escape::escape_to_buf(self.name, b);
+        }
+        Ok(())
+    }
+}
+
+/// render boolean attribute ` {name}` only if the condition is true
+///
+/// The attribute name is always HTML-escaped.
+///
+/// # Examples
+///
+/// ```text
+/// <input type="text"<%- "disabled" | attr(readonly) %>>
+/// ```
+///
+/// result (when `readonly` is `true`):
+///
+/// ```text
+/// <input type="text" disabled>
+/// ```
+#[inline]
+pub fn attr(name: &str, cond: bool) -> Attr<'_> {
+    Attr {
+        name,
+        enabled: cond,
+    }
+}
+
+/// render boolean attribute ` {name}` only if the expression is true
+///
+/// This is same as `attr` filter, but takes the attribute name as the first
+/// argument so that it reads naturally as a function call.
+///
+/// # Examples
+///
+/// ```text
+/// <input type="checkbox"<%- bool_attr("checked", self.agreed) %>>
+/// ```
+///
+/// result (when `self.agreed` is `true`):
+///
+/// ```text
+/// <input type="checkbox" checked>
+/// ```
+#[inline]
+pub fn bool_attr(name: &str, expr: bool) -> Attr<'_> {
+    attr(name, expr)
+}
+
+/// Helper struct for 'aria_attr' filter
+pub struct AriaAttr<'a> {
+    name: &'a str,
+    value: bool,
+}
+
+impl<'a> Render for AriaAttr<'a> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_escaped(b)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        b.push(' ');
+        super::escape::escape_to_buf(self.name, b);
+        b.push_str(if self.value {
+            "=\"true\""
+        } else {
+            "=\"false\""
+        });
+        Ok(())
+    }
+}
+
+/// render ` {name}="true"` or ` {name}="false"` attribute depending on the
+/// expression
+///
+/// This is useful for the attributes such as `aria-expanded` which must be
+/// present with an explicit value. The attribute name is always HTML-escaped.
+///
+/// # Examples
+///
+/// ```text
+/// <button<%- aria_attr("aria-expanded", self.open) %>>Menu</button>
+/// ```
+///
+/// result (when `self.open` is `false`):
+///
+/// ```text
+/// <button aria-expanded="false">Menu</button>
+/// ```
+#[inline]
+pub fn aria_attr(name: &str, expr: bool) -> AriaAttr<'_> {
+    AriaAttr { name, value: expr }
+}
+
+/// Helper struct for 'attr_opt' filter
+pub struct AttrOpt<'a, T: ?Sized> {
+    name: &'a str,
+    value: Option<&'a T>,
+}
+
+impl<'a, T: Render + ?Sized> Render for AttrOpt<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_escaped(b)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        if let Some(value) = self.value {
+            b.push(' ');
+            super::escape::escape_to_buf(self.name, b);
+            b.push_str("=\"");
+            value.render_escaped(b)?;
+            b.push('"');
+        }
+        Ok(())
+    }
+}
+
+/// render ` {name}="{value}"` attribute only if the value is `Some`
+///
+/// Both the attribute name and value are always HTML-escaped.
+///
+/// # Examples
+///
+/// ```text
+/// <a<%- "href" | attr_opt(&link) %>>Home</a>
+/// ```
+///
+/// result (when `link` is `Some("/")`):
+///
+/// ```text
+/// <a href="/">Home</a>
+/// ```
+#[inline]
+pub fn attr_opt<'a, T: Render>(name: &'a str, value: &'a Option<T>) -> AttrOpt<'a, T> {
+    AttrOpt {
+        name,
+        value: value.as_ref(),
+    }
+}
+
+/// Helper struct for 'escape_attr' filter
+pub struct EscapeAttr<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for EscapeAttr<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        replace_tail(b, old_len, escape_attr_impl)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // the result does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+// replace the contents rendered after `old_len` with the output of `f`
+fn replace_tail<F: FnOnce(&str, &mut String)>(
+    b: &mut Buffer,
+    old_len: usize,
+    f: F,
+) -> Result<(), RenderError> {
+    let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+
+    let mut result = String::with_capacity(new_contents.len());
+    f(new_contents, &mut result);
+
+    unsafe { b._set_len(old_len) };
+    b.push_str(&result);
+    Ok(())
+}
+
+fn escape_attr_impl(s: &str, result: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#039;"),
+            '`' | '=' | ' ' | '\t' | '\n' | '\x0C' | '\r' | '\0' => {
+                fmt::write(result, format_args!("&#x{:X};", c as u32)).unwrap()
+            }
+            _ => result.push(c),
+        }
+    }
+}
+
+/// escape the rendered contents for HTML attribute values
+///
+/// In addition to the characters escaped by default (`&"'<>`), backticks, `=`
+/// and whitespaces are escaped, so the result can be safely placed inside
+/// unquoted or single-quoted attribute values. The result is never escaped twice.
+///
+/// # Examples
+///
+/// ```text
+/// <input value=<%- "a b=`c`" | escape_attr %>>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <input value=a&#x20;b&#x3D;&#x60;c&#x60;>
+/// ```
+#[inline]
+pub fn escape_attr<T: Render + ?Sized>(expr: &T) -> EscapeAttr<'_, T> {
+    EscapeAttr(expr)
+}
+
+/// Helper struct for 'xml' filter
+pub struct Xml<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Xml<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        replace_tail(b, old_len, xml_impl)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // the result does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+fn xml_impl(s: &str, result: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+}
+
+/// escape the rendered contents for XML documents
+///
+/// Only the five predefined entities of XML (`&amp;`, `&lt;`, `&gt;`, `&quot;` and
+/// `&apos;`) are used. The result is never escaped twice.
+///
+/// # Examples
+///
+/// ```text
+/// <title><%- "Tom's <blog>" | xml %></title>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <title>Tom&apos;s &lt;blog&gt;</title>
+/// ```
+#[inline]
+pub fn xml<T: Render + ?Sized>(expr: &T) -> Xml<'_, T> {
+    Xml(expr)
+}
+
+/// Helper struct for 'csv' filter
+pub struct Csv<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Csv<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+
+        let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        if content.contains([',', '"', '\n', '\r']) {
+            replace_tail(b, old_len, csv_impl)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.render(b)?;
+
+        let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        let result = new_contents.to_owned();
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&result, b);
+        Ok(())
+    }
+}
+
+fn csv_impl(s: &str, result: &mut String) {
+    result.push('"');
+    for c in s.chars() {
+        if c == '"' {
+            result.push('"');
+        }
+        result.push(c);
+    }
+    result.push('"');
+}
+
+/// escape the rendered contents as a CSV field
+///
+/// The field is quoted when it contains commas, double quotes or line breaks, and
+/// the double quotes inside the field are doubled, as described in RFC 4180.
+///
+/// # Examples
+///
+/// ```text
+/// <%- name | csv %>,<%- note | csv %>
+/// ```
+///
+/// result (`name = "Smith, John"` and `note = r#"say "hi""#`):
+///
+/// ```text
+/// "Smith, John","say ""hi"""
+/// ```
+#[inline]
+pub fn csv<T: Render + ?Sized>(expr: &T) -> Csv<'_, T> {
+    Csv(expr)
+}
+
+/// Helper struct for 'obfuscate_email' and 'obfuscate_email_rtl' filters
+pub struct ObfuscateEmail<'a, T: ?Sized> {
+    expr: &'a T,
+    rtl: bool,
+}
+
+impl<'a, T: Render + ?Sized> Render for ObfuscateEmail<'a, T> {
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let rtl = self.rtl;
+        let old_len = b.len();
+        self.expr.render(b)?;
+        replace_tail(b, old_len, |s, result| {
+            if rtl {
+                result.push_str(
+                    "<span style=\"unicode-bidi:bidi-override;direction:rtl\">",
+                );
+                obfuscate_email_impl(s.chars().rev(), result);
+                result.push_str("</span>");
+            } else {
+                obfuscate_email_impl(s.chars(), result);
+            }
+        })
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // all characters are encoded as character references
+        self.render(b)
+    }
+}
+
+fn obfuscate_email_impl<I: Iterator<Item = char>>(chars: I, result: &mut String) {
+    // mix decimal and hexadecimal references so that the address cannot be
+    // decoded with a single pattern
+    for (i, c) in chars.enumerate() {
+        if i % 2 == 0 {
+            fmt::write(result, format_args!("&#{};", c as u32)).unwrap();
+        } else {
+            fmt::write(result, format_args!("&#x{:x};", c as u32)).unwrap();
+        }
+    }
+}
+
+/// encode every character of the email address as HTML character reference to
+/// deter the scrapers
+///
+/// Browsers display the address as usual.
+///
+/// # Examples
+///
+/// ```text
+/// <a href="mailto:<%= email | obfuscate_email %>"><%= email | obfuscate_email %></a>
+/// ```
+///
+/// result (`email = "a@b.c"`):
+///
+/// ```text
+/// <a href="mailto:&#97;&#x40;&#98;&#x2e;&#99;">&#97;&#x40;&#98;&#x2e;&#99;</a>
+/// ```
+#[inline]
+pub fn obfuscate_email<T: Render + ?Sized>(expr: &T) -> ObfuscateEmail<'_, T> {
+    ObfuscateEmail { expr, rtl: false }
+}
+
+/// render the email address in reverse order inside a right-to-left `<span>`
+///
+/// The characters are also encoded as HTML character references. Browsers display
+/// the address as usual, but the source contains the reversed address. This
+/// filter must not be used inside attribute values.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "a@b.c" | obfuscate_email_rtl %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <span style="unicode-bidi:bidi-override;direction:rtl">&#99;&#x2e;&#98;&#x40;&#97;</span>
+/// ```
+#[inline]
+pub fn obfuscate_email_rtl<T: Render + ?Sized>(expr: &T) -> ObfuscateEmail<'_, T> {
+    ObfuscateEmail { expr, rtl: true }
+}
+
+/// Helper struct for 'js' filter
+pub struct Js<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Js<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        replace_tail(b, old_len, js_impl)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // the result does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+fn js_impl(s: &str, result: &mut String) {
+    for c in s.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '"' | '\'' | '`' | '<' | '>' | '&' | '/' | '\u{2028}' | '\u{2029}' => {
+                fmt::write(result, format_args!("\\u{:04X}", c as u32)).unwrap()
+            }
+            c if c.is_ascii_control() => {
+                fmt::write(result, format_args!("\\u{:04X}", c as u32)).unwrap()
+            }
+            _ => result.push(c),
+        }
+    }
+}
+
+/// escape the rendered contents for JavaScript string literals
+///
+/// Quotes, backslashes, control characters, line terminators (U+2028 and U+2029)
+/// and the characters which may close the `<script>` element (`<>&/`) are escaped
+/// with backslash sequences. The result can be placed inside both single-quoted
+/// and double-quoted strings, and is never HTML-escaped.
+///
+/// # Examples
+///
+/// ```text
+/// <script>const name = "<%- "</script>\"" | js %>";</script>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <script>const name = "\u003C\u002Fscript\u003E\u0022";</script>
+/// ```
+#[inline]
+pub fn js<T: Render + ?Sized>(expr: &T) -> Js<'_, T> {
+    Js(expr)
+}
+
+/// Helper struct for 'urlencode' and 'urlencode_path' filters
+pub struct UrlEncode<'a, T: ?Sized> {
+    expr: &'a T,
+    keep_slash: bool,
+}
+
+impl<'a, T: Render + ?Sized> Render for UrlEncode<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        replace_tail(b, old_len, |s, result| {
+            urlencode_impl(s, self.keep_slash, result)
+        })
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // the result does not contain any characters to be escaped
+        self.render(b)
+    }
+}
+
+fn urlencode_impl(s: &str, keep_slash: bool, result: &mut String) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    for &byte in s.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                result.push(byte as char)
+            }
+            b'/' if keep_slash => result.push('/'),
+            _ => {
+                result.push('%');
+                result.push(HEX[(byte >> 4) as usize] as char);
+                result.push(HEX[(byte & 15) as usize] as char);
+            }
+        }
+    }
+}
+
+/// percent-encode the rendered contents (RFC 3986)
+///
+/// All characters except the unreserved characters (`A-Z`, `a-z`, `0-9`, `-._~`)
+/// are encoded, so the result can be used as a query parameter or a path segment.
+///
+/// # Examples
+///
+/// ```text
+/// <a href="/search?q=<%- "rust & café" | urlencode %>">Search</a>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <a href="/search?q=rust%20%26%20caf%C3%A9">Search</a>
+/// ```
+#[inline]
+pub fn urlencode<T: Render + ?Sized>(expr: &T) -> UrlEncode<'_, T> {
+    UrlEncode {
+        expr,
+        keep_slash: false,
+    }
+}
+
+/// percent-encode the rendered contents except for `/`
+///
+/// This filter is the same as `urlencode` filter, but path separators are kept.
+///
+/// # Examples
+///
+/// ```text
+/// <a href="/files/<%- "docs/my file.txt" | urlencode_path %>">Download</a>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <a href="/files/docs/my%20file.txt">Download</a>
+/// ```
+#[inline]
+pub fn urlencode_path<T: Render + ?Sized>(expr: &T) -> UrlEncode<'_, T> {
+    UrlEncode {
+        expr,
+        keep_slash: true,
+    }
+}
+
+/// Helper struct for 'css' filter
+pub struct Css<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Css<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        replace_tail(b, old_len, css_impl)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+
+        let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        let mut result = String::with_capacity(new_contents.len());
+        css_impl(new_contents, &mut result);
+
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&result, b);
+        Ok(())
+    }
+}
+
+// https://drafts.csswg.org/cssom/#serialize-an-identifier
+fn css_impl(s: &str, result: &mut String) {
+    let first = s.chars().next();
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            '\0' => result.push('\u{FFFD}'),
+            '\x01'..='\x1F' | '\x7F' => {
+                fmt::write(result, format_args!("\\{:x} ", c as u32)).unwrap()
+            }
+            '0'..='9' if i == 0 || (i == 1 && first == Some('-')) => {
+                fmt::write(result, format_args!("\\{:x} ", c as u32)).unwrap()
+            }
+            '-' if i == 0 && s.len() == 1 => result.push_str("\\-"),
+            c if c >= '\u{80}' || c == '-' || c == '_' || c.is_ascii_alphanumeric() => {
+                result.push(c)
+            }
+            _ => {
+                result.push('\\');
+                result.push(c);
+            }
+        }
+    }
+}
+
+/// escape the rendered contents for CSS identifiers and strings
+///
+/// This filter follows the semantics of `CSS.escape()` method in CSSOM. When used
+/// with HTML escaping, the result is HTML-escaped after CSS escaping, which is
+/// appropriate for `style` attributes.
+///
+/// # Examples
+///
+/// ```text
+/// <style>#<%- "1 item" | css %> { color: red; }</style>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <style>#\31 \ item { color: red; }</style>
+/// ```
+#[inline]
+pub fn css<T: Render + ?Sized>(expr: &T) -> Css<'_, T> {
+    Css(expr)
+}
+
+/// Helper struct for 'capitalize' and 'title' filters
+pub struct Capitalize<'a, T: ?Sized> {
+    expr: &'a T,
+    each_word: bool,
+}
+
+impl<'a, T: Render + ?Sized> Render for Capitalize<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        replace_tail(b, old_len, |s, result| {
+            capitalize_impl(s, self.each_word, result)
+        })
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // convert the contents before escaping not to modify the entities
+        let old_len = b.len();
+        self.expr.render(b)?;
+
+        let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        let mut result = String::with_capacity(new_contents.len());
+        capitalize_impl(new_contents, self.each_word, &mut result);
+
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&result, b);
+        Ok(())
+    }
+}
+
+fn capitalize_impl(s: &str, each_word: bool, result: &mut String) {
+    let mut word_start = true;
+    for c in s.chars() {
+        if word_start && c.is_alphabetic() {
+            result.extend(c.to_uppercase());
+            word_start = false;
+        } else {
+            result.push(c);
+            if each_word {
+                word_start = !(c.is_alphanumeric() || c == '\'');
+            } else if c.is_alphanumeric() {
+                word_start = false;
+            }
+        }
+    }
+}
+
+/// convert the first letter of the rendered contents to uppercase
+///
+/// The rest of the contents are not modified. If the contents start with a digit,
+/// nothing is converted.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "hello world" | capitalize %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Hello world
+/// ```
+#[inline]
+pub fn capitalize<T: Render + ?Sized>(expr: &T) -> Capitalize<'_, T> {
+    Capitalize {
+        expr,
+        each_word: false,
+    }
+}
+
+/// convert the first letter of each word in the rendered contents to uppercase
+///
+/// The rest of the contents are not modified.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "the lord of the rings" | title %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// The Lord Of The Rings
+/// ```
+#[inline]
+pub fn title<T: Render + ?Sized>(expr: &T) -> Capitalize<'_, T> {
+    Capitalize {
+        expr,
+        each_word: true,
+    }
+}
+
+/// Helper struct for 'replace' filter
+pub struct Replace<'a, T: ?Sized> {
+    expr: &'a T,
+    from: &'a str,
+    to: &'a str,
+}
+
+impl<'a, T: Render + ?Sized> Render for Replace<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        replace_impl(b, old_len, self.from, self.to)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // replace the contents before escaping not to match the entities
+        let old_len = b.len();
+        self.expr.render(b)?;
+        replace_impl(b, old_len, self.from, self.to)?;
+
+        let replaced = b.as_str()[old_len..].to_owned();
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&replaced, b);
+        Ok(())
+    }
+}
+
+fn replace_impl(
+    b: &mut Buffer,
+    old_len: usize,
+    from: &str,
+    to: &str,
+) -> Result<(), RenderError> {
+    let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+    if from.is_empty() {
+        return Ok(());
+    }
+
+    let positions: Vec<usize> = new_contents
+        .match_indices(from)
+        .map(|(i, _)| old_len + i)
+        .collect();
+    if positions.is_empty() {
+        return Ok(());
+    }
+
+    let total_len = b.len();
+    if to.len() <= from.len() {
+        // performs inplace replacement from the front
+        let base = b.as_mut_ptr();
+        let mut write = positions[0];
+        for (i, &pos) in positions.iter().enumerate() {
+            let rest = pos + from.len();
+            let rest_end = positions.get(i + 1).copied().unwrap_or(total_len);
+            unsafe {
+                ptr::copy_nonoverlapping(to.as_ptr(), base.add(write), to.len());
+                write += to.len();
+                ptr::copy(base.add(rest), base.add(write), rest_end - rest);
+                write += rest_end - rest;
+            }
+        }
+
+        // SAFETY: the contents only shrink, and `write` is on the char boundary
+        unsafe { b._set_len(write) };
+    } else {
+        // performs inplace replacement from the back
+        let new_len = total_len + positions.len() * (to.len() - from.len());
+        b.reserve(new_len - total_len);
+
+        let base = b.as_mut_ptr();
+        let mut write_end = new_len;
+        let mut read_end = total_len;
+        for &pos in positions.iter().rev() {
+            let rest = pos + from.len();
+            unsafe {
+                write_end -= read_end - rest;
+                ptr::copy(base.add(rest), base.add(write_end), read_end - rest);
+                write_end -= to.len();
+                ptr::copy_nonoverlapping(to.as_ptr(), base.add(write_end), to.len());
+            }
+            read_end = pos;
+        }
+
+        // SAFETY: `new_len` bytes were reserved and initialized above
+        unsafe { b._set_len(new_len) };
+    }
+
+    Ok(())
+}
+
+/// replace all occurrences of `from` in the rendered contents with `to`
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Hello, world!" | replace("world", "sailfish") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Hello, sailfish!
+/// ```
+#[inline]
+pub fn replace<'a, T: Render + ?Sized>(
+    expr: &'a T,
+    from: &'a str,
+    to: &'a str,
+) -> Replace<'a, T> {
+    Replace { expr, from, to }
+}
+
+/// Helper struct for 'slugify' filter
+pub struct Slugify<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Slugify<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        slugify_impl(b, old_len)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // slug only contains ascii alphanumerics and hyphens
+        self.render(b)
+    }
+}
+
+/// transliterate the lowercase latin letter into ascii
+fn transliterate(c: char) -> Option<&'static str> {
+    let s = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ð' | 'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĳ' => "ij",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(s)
+}
+
+fn slugify_impl(b: &mut Buffer, old_len: usize) -> Result<(), RenderError> {
+    let total_len = b.len();
+    if b.as_str().get(old_len..).is_none() {
+        return Err(RenderError::BufSize);
+    }
+
+    // The slug is written over the rendered contents. Each character is
+    // converted into at most as many bytes as it occupies, and hyphens are
+    // written only after skipping separators, so the write position never
+    // passes the read position.
+    let base = b.as_mut_ptr();
+    let mut read = old_len;
+    let mut write = old_len;
+    let mut hyphen = false;
+    while read < total_len {
+        let c = unsafe {
+            let rest = std::slice::from_raw_parts(base.add(read), total_len - read);
+            std::str::from_utf8_unchecked(rest).chars().next().unwrap()
+        };
+        read += c.len_utf8();
+
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        let mut ascii = [0u8; 1];
+        let replacement = if lower.is_ascii_alphanumeric() {
+            &*lower.encode_utf8(&mut ascii)
+        } else if let Some(s) = transliterate(lower) {
+            s
+        } else {
+            if (c.is_whitespace() || c == '-' || c == '_') && write > old_len {
+                hyphen = true;
+            }
+            continue;
+        };
+
+        unsafe {
+            if hyphen {
+                *base.add(write) = b'-';
+                write += 1;
+                hyphen = false;
+            }
+            ptr::copy_nonoverlapping(
+                replacement.as_ptr(),
+                base.add(write),
+                replacement.len(),
+            );
+        }
+        write += replacement.len();
+    }
+
+    // SAFETY: the slug only contains ascii characters
+    unsafe { b._set_len(write) };
+    Ok(())
+}
+
+/// convert the rendered contents into a URL slug
+///
+/// The contents are converted to lowercase, latin letters with diacritics are
+/// transliterated into ascii, runs of whitespaces, hyphens and underscores are
+/// replaced with a single hyphen, and other characters are removed.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Crème Brûlée: A How-To Guide!" | slugify %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// creme-brulee-a-how-to-guide
+/// ```
+#[inline]
+pub fn slugify<T: Render + ?Sized>(expr: &T) -> Slugify<'_, T> {
+    Slugify(expr)
+}
+
+/// Helper struct for 'nl2br' filter
+pub struct Nl2br<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Nl2br<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        replace_impl(b, old_len, "\n", "<br>\n")
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // insert the tags after escaping not to escape them
+        let old_len = b.len();
+        self.0.render_escaped(b)?;
+        replace_impl(b, old_len, "\n", "<br>\n")
+    }
+}
+
+/// insert `<br>` tag before each newline in the rendered contents
+///
+/// The contents are escaped before the tags are inserted when the filter is used
+/// with `<%= %>`.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Hello,\nworld & all" | nl2br %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Hello,<br>
+/// world &amp; all
+/// ```
+#[inline]
+pub fn nl2br<T: Render + ?Sized>(expr: &T) -> Nl2br<'_, T> {
+    Nl2br(expr)
+}
+
+/// Helper struct for 'linebreaks' filter
+pub struct Linebreaks<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Linebreaks<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        replace_tail(b, old_len, linebreaks_impl)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // insert the tags after escaping not to escape them
+        let old_len = b.len();
+        self.0.render_escaped(b)?;
+        replace_tail(b, old_len, linebreaks_impl)
+    }
+}
+
+fn linebreaks_impl(s: &str, result: &mut String) {
+    let mut in_paragraph = false;
+    let mut blank = false;
+
+    for line in s.lines() {
+        if line.trim().is_empty() {
+            blank = true;
+            continue;
+        }
+
+        if !in_paragraph {
+            result.push_str("<p>");
+            in_paragraph = true;
+        } else if blank {
+            result.push_str("</p>\n\n<p>");
+        } else {
+            result.push_str("<br>\n");
+        }
+        result.push_str(line);
+        blank = false;
+    }
+
+    if in_paragraph {
+        result.push_str("</p>");
+    }
+}
+
+/// wrap the paragraphs separated by blank lines with `<p>` tags, and insert `<br>`
+/// tag between the other lines
+///
+/// The contents are escaped before the tags are inserted when the filter is used
+/// with `<%= %>`.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Hello,\nworld\n\nBye & see you" | linebreaks %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <p>Hello,<br>
+/// world</p>
+///
+/// <p>Bye &amp; see you</p>
+/// ```
+#[inline]
+pub fn linebreaks<T: Render + ?Sized>(expr: &T) -> Linebreaks<'_, T> {
+    Linebreaks(expr)
+}
+
+/// Helper struct for 'urlize' and 'urlize_with' filters
+pub struct Urlize<'a, T: ?Sized> {
+    expr: &'a T,
+    rel: &'a str,
+    target: &'a str,
+}
+
+impl<'a, T: Render + ?Sized> Render for Urlize<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        replace_tail(b, old_len, |s, result| {
+            urlize_impl(s, self.rel, self.target, result)
+        })
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // insert the tags after escaping not to escape them
+        let old_len = b.len();
+        self.expr.render_escaped(b)?;
+        replace_tail(b, old_len, |s, result| {
+            urlize_impl(s, self.rel, self.target, result)
+        })
+    }
+}
+
+/// returns true if the position is the start of an entity produced by escaping
+/// quotes or angle brackets
+fn is_quote_entity(s: &str, i: usize) -> bool {
+    let rest = &s[i..];
+    rest.starts_with("&quot;")
+        || rest.starts_with("&#039;")
+        || rest.starts_with("&lt;")
+        || rest.starts_with("&gt;")
+}
+
+/// returns the end of the URL starting at `start`
+fn url_end(s: &str, start: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    if start > 0 && bytes[start - 1].is_ascii_alphanumeric() {
+        return None;
+    }
+
+    let rest = &bytes[start..];
+    let scheme_len = if rest.len() >= 7 && rest[..7].eq_ignore_ascii_case(b"http://") {
+        7
+    } else if rest.len() >= 8 && rest[..8].eq_ignore_ascii_case(b"https://") {
+        8
+    } else {
+        return None;
+    };
+
+    let mut end = start + scheme_len;
+    while end < bytes.len() {
+        match bytes[end] {
+            b'<' | b'>' | b'"' | b'\'' | b'`' => break,
+            b'&' if is_quote_entity(s, end) => break,
+            c if c.is_ascii_whitespace() => break,
+            _ => end += 1,
+        }
+    }
+
+    // exclude the trailing punctuations and the unbalanced closing parenthesis
+    loop {
+        let url = &s[start..end];
+        match url.as_bytes().last() {
+            Some(b'.' | b',' | b':' | b';' | b'!' | b'?') => end -= 1,
+            Some(b')') if url.matches(')').count() > url.matches('(').count() => end -= 1,
+            _ => break,
+        }
+    }
+
+    // at least one character is required for the host
+    if end > start + scheme_len {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+/// returns the range of the email address which contains '@' at `at`
+fn email_span(s: &str, lower: usize, at: usize) -> Option<(usize, usize)> {
+    let bytes = s.as_bytes();
+    let is_local = |c: u8| c.is_ascii_alphanumeric() || b"._%+-".contains(&c);
+    let is_domain = |c: u8| c.is_ascii_alphanumeric() || c == b'.' || c == b'-';
+
+    let mut start = at;
+    while start > lower && is_local(bytes[start - 1]) {
+        start -= 1;
+    }
+    while start < at && !bytes[start].is_ascii_alphanumeric() {
+        start += 1;
+    }
+
+    let mut end = at + 1;
+    while end < bytes.len() && is_domain(bytes[end]) {
+        end += 1;
+    }
+    while end > at + 1 && !bytes[end - 1].is_ascii_alphanumeric() {
+        end -= 1;
+    }
+
+    let domain = &s[at + 1..end];
+    let valid_domain = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty() && !label.starts_with('-') && !label.ends_with('-')
+        });
+
+    if start < at && valid_domain {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+fn push_link(
+    result: &mut String,
+    href_prefix: &str,
+    text: &str,
+    rel: &str,
+    target: &str,
+) {
+    result.push_str("<a href=\"");
+    result.push_str(href_prefix);
+    result.push_str(text);
+    result.push('"');
+    if !rel.is_empty() {
+        result.push_str(" rel=\"");
+        super::escape::escape_to_string(rel, result);
+        result.push('"');
+    }
+    if !target.is_empty() {
+        result.push_str(" target=\"");
+        super::escape::escape_to_string(target, result);
+        result.push('"');
+    }
+    result.push('>');
+    result.push_str(text);
+    result.push_str("</a>");
+}
+
+fn urlize_impl(s: &str, rel: &str, target: &str, result: &mut String) {
+    let bytes = s.as_bytes();
+    let mut last = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if matches!(bytes[i], b'h' | b'H') {
+            if let Some(end) = url_end(s, i) {
+                result.push_str(&s[last..i]);
+                push_link(result, "", &s[i..end], rel, target);
+                last = end;
+                i = end;
+                continue;
+            }
+        } else if bytes[i] == b'@' {
+            if let Some((start, end)) = email_span(s, last, i) {
+                result.push_str(&s[last..start]);
+                push_link(result, "mailto:", &s[start..end], rel, target);
+                last = end;
+                i = end;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    result.push_str(&s[last..]);
+}
+
+/// convert the http(s) URLs and email addresses into links
+///
+/// The links have `rel="nofollow"` attribute. Use `urlize_with` filter to
+/// configure the `rel` and `target` attributes.
+///
+/// The contents are escaped before the tags are inserted when the filter is used
+/// with `<%= %>`.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "See https://example.com/?a=1&b=2." | urlize %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// See <a href="https://example.com/?a=1&amp;b=2" rel="nofollow">https://example.com/?a=1&amp;b=2</a>.
+/// ```
+#[inline]
+pub fn urlize<T: Render + ?Sized>(expr: &T) -> Urlize<'_, T> {
+    Urlize {
+        expr,
+        rel: "nofollow",
+        target: "",
+    }
+}
+
+/// convert the http(s) URLs and email addresses into links with the given `rel`
+/// and `target` attributes
+///
+/// The attributes are omitted when the empty string is given.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Mail to admin@example.com" | urlize_with("noopener", "_blank") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Mail to <a href="mailto:admin@example.com" rel="noopener" target="_blank">admin@example.com</a>
+/// ```
+#[inline]
+pub fn urlize_with<'a, T: Render + ?Sized>(
+    expr: &'a T,
+    rel: &'a str,
+    target: &'a str,
+) -> Urlize<'a, T> {
+    Urlize { expr, rel, target }
+}
+
+/// Helper struct for 'squeeze' filter
+pub struct Squeeze<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for Squeeze<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        squeeze_tail(b, old_len)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // escaping does not produce any whitespaces
+        let old_len = b.len();
+        self.0.render_escaped(b)?;
+        squeeze_tail(b, old_len)
+    }
+}
+
+// collapse the whitespaces rendered after `old_len` in place
+fn squeeze_tail(b: &mut Buffer, old_len: usize) -> Result<(), RenderError> {
+    let len = b.len().checked_sub(old_len).ok_or(RenderError::BufSize)?;
+    let bytes =
+        unsafe { std::slice::from_raw_parts_mut(b.as_mut_ptr().add(old_len), len) };
+
+    let (mut read, mut write) = (0, 0);
+    let mut in_space = false;
+    while read < len {
+        // SAFETY: the contents after `read` are never modified, and `read` is
+        // always on the char boundary
+        let c = unsafe { std::str::from_utf8_unchecked(&bytes[read..]) }
+            .chars()
+            .next()
+            .unwrap();
+        let char_len = c.len_utf8();
+
+        if !c.is_whitespace() {
+            bytes.copy_within(read..read + char_len, write);
+            write += char_len;
+            in_space = false;
+        } else if !in_space {
+            bytes[write] = b' ';
+            write += 1;
+            in_space = true;
+        }
+        read += char_len;
+    }
+
+    // SAFETY: the contents only shrink, and `write` is on the char boundary
+    unsafe { b._set_len(old_len + write) };
+    Ok(())
+}
+
+/// collapse each run of whitespaces (including newlines) in the rendered contents
+/// into a single space
+///
+/// Leading and trailing whitespaces are also collapsed, but not removed.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Hello,\n    \tworld!" | squeeze %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Hello, world!
+/// ```
+#[inline]
+pub fn squeeze<T: Render + ?Sized>(expr: &T) -> Squeeze<'_, T> {
+    Squeeze(expr)
+}
+
+/// Helper struct for 'strip_ansi' filter
+pub struct StripAnsi<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for StripAnsi<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+
+        let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        if content.contains(['\x1b', '\u{9b}']) {
+            replace_tail(b, old_len, strip_ansi_impl)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+
+        let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        let mut result = String::with_capacity(new_contents.len());
+        strip_ansi_impl(new_contents, &mut result);
+
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&result, b);
+        Ok(())
+    }
+}
+
+fn strip_ansi_impl(s: &str, result: &mut String) {
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let csi = match c {
+            '\x1b' => match chars.peek() {
+                Some('[') => {
+                    chars.next();
+                    true
+                }
+                Some(']') => {
+                    // OSC sequence terminated by BEL or ST (ESC \)
+                    chars.next();
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        } else if c == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                Some(&c) if ('\x20'..='\x7e').contains(&c) => {
+                    // two-character sequence, optionally with intermediate bytes
+                    while chars.next_if(|c| ('\x20'..='\x2f').contains(c)).is_some() {}
+                    chars.next();
+                    continue;
+                }
+                _ => continue,
+            },
+            '\u{9b}' => true,
+            _ => false,
+        };
+
+        if csi {
+            // parameter and intermediate bytes, followed by the final byte
+            while chars.next_if(|c| ('\x20'..='\x3f').contains(c)).is_some() {}
+            chars.next_if(|c| ('\x40'..='\x7e').contains(c));
+        } else {
+            result.push(c);
+        }
+    }
+}
+
+/// remove ANSI escape sequences (e.g. colors and cursor movements) from the
+/// rendered contents
+///
+/// # Examples
+///
+/// ```text
+/// <pre><%= "\x1b[1;31merror\x1b[0m: not found" | strip_ansi %></pre>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <pre>error: not found</pre>
+/// ```
+#[inline]
+pub fn strip_ansi<T: Render + ?Sized>(expr: &T) -> StripAnsi<'_, T> {
+    StripAnsi(expr)
+}
+
+/// Helper struct for 'wordwrap' filter
+pub struct WordWrap<'a, T: ?Sized> {
+    expr: &'a T,
+    width: usize,
+    brk: &'a str,
+}
+
+impl<'a, T: Render + ?Sized> WordWrap<'a, T> {
+    fn render_impl(&self, b: &mut Buffer, escape: bool) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        let contents = b
+            .as_str()
+            .get(old_len..)
+            .ok_or(RenderError::BufSize)?
+            .to_owned();
+        unsafe { b._set_len(old_len) };
+
+        for (i, line) in contents.split('\n').enumerate() {
+            if i > 0 {
+                b.push('\n');
+            }
+
+            let mut column = 0;
+            for word in line.split_whitespace() {
+                let word_width = word.chars().count();
+                if column > 0 {
+                    if column + 1 + word_width > self.width {
+                        // the break string is markup, so it is never escaped
+                        b.push_str(self.brk);
+                        column = 0;
+                    } else {
+                        b.push(' ');
+                        column += 1;
+                    }
+                }
+
+                if escape {
+                    super::escape::escape_to_buf(word, b);
+                } else {
+                    b.push_str(word);
+                }
+                column += word_width;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T: Render + ?Sized> Render for WordWrap<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render_impl(b, true)
+    }
+}
+
+/// wrap the rendered text so that each line fits in `width` characters
+///
+/// Lines are broken at whitespaces by inserting `brk`, and consecutive
+/// whitespaces are collapsed into a single space. Existing newlines are kept,
+/// and words longer than `width` are not split.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "The quick brown fox jumps over the lazy dog" | wordwrap(15, "\n") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// The quick brown
+/// fox jumps over
+/// the lazy dog
+/// ```
+#[inline]
+pub fn wordwrap<'a, T: Render + ?Sized>(
+    expr: &'a T,
+    width: usize,
+    brk: &'a str,
+) -> WordWrap<'a, T> {
+    WordWrap { expr, width, brk }
+}
+
+/// Helper struct for 'indent' and 'indent_skip_first' filter
+pub struct Indent<'a, T: ?Sized> {
+    expr: &'a T,
+    width: usize,
+    first: bool,
+}
+
+impl<'a, T: Render + ?Sized> Render for Indent<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        replace_tail(b, old_len, |s, result| {
+            indent_impl(s, self.width, self.first, result)
+        })
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // spaces are not affected by escaping
+        let old_len = b.len();
+        self.expr.render_escaped(b)?;
+        replace_tail(b, old_len, |s, result| {
+            indent_impl(s, self.width, self.first, result)
+        })
+    }
+}
+
+fn indent_impl(s: &str, width: usize, first: bool, result: &mut String) {
+    result.reserve(s.len() + width * (s.matches('\n').count() + 1));
+    for (i, line) in s.split_inclusive('\n').enumerate() {
+        // empty lines are not indented not to leave trailing whitespaces
+        if (first || i > 0) && line != "\n" && line != "\r\n" {
+            for _ in 0..width {
+                result.push(' ');
+            }
+        }
+        result.push_str(line);
+    }
+}
+
+/// indent each line of the rendered contents with `width` spaces
+///
+/// Empty lines are left as-is.
+///
+/// # Examples
+///
+/// ```text
+/// spec:
+/// <%= "replicas: 3\nimage: app" | indent(2) %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// spec:
+///   replicas: 3
+///   image: app
+/// ```
+#[inline]
+pub fn indent<T: Render + ?Sized>(expr: &T, width: usize) -> Indent<'_, T> {
+    Indent {
+        expr,
+        width,
+        first: true,
+    }
+}
+
+/// indent each line except the first one of the rendered contents with `width`
+/// spaces
+///
+/// This is useful when the tag itself is already indented in the template.
+/// Empty lines are left as-is.
+///
+/// # Examples
+///
+/// ```text
+/// fn main() {
+///     <%= "let x = 1;\nprintln!(\"{}\", x);" | indent_skip_first(4) %>
+/// }
+/// ```
+///
+/// result:
+///
+/// ```text
+/// fn main() {
+///     let x = 1;
+///     println!("{}", x);
+/// }
+/// ```
+#[inline]
+pub fn indent_skip_first<T: Render + ?Sized>(expr: &T, width: usize) -> Indent<'_, T> {
+    Indent {
+        expr,
+        width,
+        first: false,
+    }
+}
+
+/// Helper struct for 'striptags' filter
+pub struct StripTags<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Render + ?Sized> Render for StripTags<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.0.render(b)?;
+        replace_tail(b, old_len, striptags_impl)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // the entities are decoded, so the text must be escaped again
+        let old_len = b.len();
+        self.0.render(b)?;
+
+        let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+        let mut result = String::with_capacity(new_contents.len());
+        striptags_impl(new_contents, &mut result);
+
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&result, b);
+        Ok(())
+    }
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{A0}',
+        _ => {
+            let code = if let Some(hex) = entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                u32::from_str_radix(hex, 16).ok()?
+            } else {
+                entity.strip_prefix('#')?.parse().ok()?
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some(c)
+}
+
+fn striptags_impl(s: &str, result: &mut String) {
+    let mut rest = s;
+    let mut space = false;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = if rest.starts_with("<!--") {
+                rest.find("-->").map(|i| i + 3)
+            } else {
+                rest.find('>').map(|i| i + 1)
+            };
+            match end {
+                Some(end) => {
+                    rest = &rest[end..];
+                    continue;
+                }
+                None => break,
+            }
+        }
+
+        let (c, len) = if c == '&' {
+            match rest[1..]
+                .find(';')
+                .filter(|&i| i <= 10)
+                .and_then(|i| Some((decode_entity(&rest[1..=i])?, i + 2)))
+            {
+                Some(decoded) => decoded,
+                None => (c, 1),
+            }
+        } else {
+            (c, c.len_utf8())
+        };
+        rest = &rest[len..];
+
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space && !result.is_empty() {
+                result.push(' ');
+            }
+            space = false;
+            result.push(c);
+        }
+    }
+}
+
+/// remove HTML tags and comments from the rendered contents
+///
+/// Runs of whitespaces are collapsed into a single space, and character
+/// references such as `&amp;` are decoded.
+///
+/// # Examples
+///
+/// ```text
+/// <meta name="description" content="<%= "<p>Fish &amp; <b>chips</b></p>" | striptags %>">
+/// ```
+///
+/// result:
+///
+/// ```text
+/// <meta name="description" content="Fish &amp; chips">
+/// ```
+#[inline]
+pub fn striptags<T: Render + ?Sized>(expr: &T) -> StripTags<'_, T> {
+    StripTags(expr)
+}
+
+#[derive(Clone, Copy)]
+enum PadSide {
+    Left,
+    Right,
+    Both,
+}
+
+/// Helper struct for 'pad_left', 'pad_right' and 'center' filter
+pub struct Pad<'a, T: ?Sized> {
+    expr: &'a T,
+    width: usize,
+    fill: char,
+    side: PadSide,
+}
+
+impl<'a, T: Render + ?Sized> Render for Pad<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        pad_impl(b, old_len, self.width, self.fill, self.side)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        // count the width before escaping not to count the entities
+        let old_len = b.len();
+        self.expr.render(b)?;
+        pad_impl(b, old_len, self.width, self.fill, self.side)?;
+
+        let padded = b.as_str()[old_len..].to_owned();
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&padded, b);
+        Ok(())
+    }
+}
+
+fn pad_impl(
+    b: &mut Buffer,
+    old_len: usize,
+    width: usize,
+    fill: char,
+    side: PadSide,
+) -> Result<(), RenderError> {
+    let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+    let count = new_contents.chars().count();
+    if count >= width {
+        return Ok(());
+    }
+
+    let padding = width - count;
+    let (left, right) = match side {
+        PadSide::Left => (padding, 0),
+        PadSide::Right => (0, padding),
+        PadSide::Both => (padding / 2, padding - padding / 2),
+    };
+
+    if left > 0 {
+        replace_tail(b, old_len, |s, result| {
+            for _ in 0..left {
+                result.push(fill);
+            }
+            result.push_str(s);
+        })?;
+    }
+    for _ in 0..right {
+        b.push(fill);
+    }
+    Ok(())
+}
+
+/// pad the rendered contents on the left with `fill` up to `width` characters
+///
+/// The contents longer than `width` are rendered as-is.
+///
+/// # Examples
+///
+/// ```text
+/// <%= 42 | pad_left(6, '0') %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 000042
+/// ```
+#[inline]
+pub fn pad_left<T: Render + ?Sized>(expr: &T, width: usize, fill: char) -> Pad<'_, T> {
+    Pad {
+        expr,
+        width,
+        fill,
+        side: PadSide::Left,
+    }
+}
+
+/// pad the rendered contents on the right with `fill` up to `width` characters
+///
+/// The contents longer than `width` are rendered as-is.
+///
+/// # Examples
+///
+/// ```text
+/// <%= "Name" | pad_right(8, '.') %>|
+/// ```
+///
+/// result:
+///
+/// ```text
+/// Name....|
+/// ```
+#[inline]
+pub fn pad_right<T: Render + ?Sized>(expr: &T, width: usize, fill: char) -> Pad<'_, T> {
+    Pad {
+        expr,
+        width,
+        fill,
+        side: PadSide::Right,
+    }
+}
+
+/// pad the rendered contents on both sides with `fill` up to `width` characters
+///
+/// If the padding cannot be split evenly, the right side gets one more
+/// character. The contents longer than `width` are rendered as-is.
+///
+/// # Examples
+///
+/// ```text
+/// [<%= "title" | center(9, ' ') %>]
+/// ```
+///
+/// result:
+///
+/// ```text
+/// [  title  ]
+/// ```
+#[inline]
+pub fn center<T: Render + ?Sized>(expr: &T, width: usize, fill: char) -> Pad<'_, T> {
+    Pad {
+        expr,
+        width,
+        fill,
+        side: PadSide::Both,
+    }
+}
+
+/// Helper struct for 'num_fmt' and 'group_digits' filter
+pub struct NumFmt<'a, T: ?Sized> {
+    expr: &'a T,
+    group_sep: &'a str,
+    decimal_sep: &'a str,
+}
+
+impl<'a, T: Render + ?Sized> Render for NumFmt<'a, T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        num_fmt_impl(b, old_len, self.group_sep, self.decimal_sep)
+    }
+
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        let old_len = b.len();
+        self.expr.render(b)?;
+        num_fmt_impl(b, old_len, self.group_sep, self.decimal_sep)?;
+
+        // separators may contain the characters to be escaped
+        let formatted = b.as_str()[old_len..].to_owned();
+        unsafe { b._set_len(old_len) };
+        super::escape::escape_to_buf(&formatted, b);
+        Ok(())
+    }
+}
+
+fn num_fmt_impl(
+    b: &mut Buffer,
+    old_len: usize,
+    group_sep: &str,
+    decimal_sep: &str,
+) -> Result<(), RenderError> {
+    let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+    let sign_len = new_contents.starts_with('-') as usize;
+    let int_len = new_contents[sign_len..]
+        .bytes()
+        .take_while(u8::is_ascii_digit)
+        .count();
+    let rest = &new_contents[sign_len + int_len..];
+
+    if int_len <= 3 && (decimal_sep == "." || !rest.starts_with('.')) {
+        return Ok(());
+    }
+
+    replace_tail(b, old_len, |s, result| {
+        let (sign, s) = s.split_at(sign_len);
+        let (digits, rest) = s.split_at(int_len);
+        result.push_str(sign);
+
+        let head = match digits.len() % 3 {
+            0 => digits.len().min(3),
+            n => n,
+        };
+        result.push_str(&digits[..head]);
+        for group in digits.as_bytes()[head..].chunks(3) {
+            result.push_str(group_sep);
+            result.push_str(std::str::from_utf8(group).unwrap());
+        }
+
+        match rest.strip_prefix('.') {
+            Some(frac) => {
+                result.push_str(decimal_sep);
+                result.push_str(frac);
+            }
+            None => result.push_str(rest),
+        }
+    })
+}
+
+/// format the number with the given group and decimal separators
+///
+/// The number is rendered with its own `Render` implementation first, and then
+/// separators are inserted into the integer part.
+///
+/// # Examples
+///
+/// ```text
+/// <%= 1234567.89 | num_fmt(".", ",") %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 1.234.567,89
+/// ```
+#[inline]
+pub fn num_fmt<'a, T: Render + ?Sized>(
+    expr: &'a T,
+    group_sep: &'a str,
+    decimal_sep: &'a str,
+) -> NumFmt<'a, T> {
+    NumFmt {
+        expr,
+        group_sep,
+        decimal_sep,
+    }
+}
+
+/// format the number with a comma inserted between each group of thousands
+///
+/// # Examples
+///
+/// ```text
+/// <%= 1234567 | group_digits %>, <%= -9876.5 | group_digits %>
+/// ```
+///
+/// result:
+///
+/// ```text
+/// 1,234,567, -9,876.5
+/// ```
+#[inline]
+pub fn group_digits<T: Render + ?Sized>(expr: &T) -> NumFmt<'_, T> {
+    num_fmt(expr, ",", ".")
+}
+
+cfg_markdown! {
+    /// Helper struct for 'markdown' filter
+    pub struct Markdown<'a, T: ?Sized>(&'a T);
+
+    fn markdown_parser(source: &str) -> pulldown_cmark::Parser<'_> {
+        use pulldown_cmark::{Options, Parser};
+
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS;
+        Parser::new_ext(source, options)
+    }
+
+    impl<'a, T: AsRef<str> + ?Sized> Render for Markdown<'a, T> {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            let parser = markdown_parser(self.0.as_ref());
+            pulldown_cmark::html::write_html_fmt(b, parser).map_err(|e| RenderError::from(e))
+        }
+
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            use pulldown_cmark::Event;
+
+            // render raw HTML in the source as text
+            let parser = markdown_parser(self.0.as_ref()).map(|event| match event {
+                Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+                event => event,
+            });
+            pulldown_cmark::html::write_html_fmt(b, parser).map_err(|e| RenderError::from(e))
+        }
+    }
+
+    /// render the CommonMark source as HTML
+    ///
+    /// Tables, footnotes, strikethrough and task lists are enabled. When the
+    /// filter is used with `<%= %>`, raw HTML inside the source is escaped,
+    /// while `<%- %>` passes it through as-is.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= "# Title\n\nSome *emphasis* and <b>html</b>" | markdown %>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// <h1>Title</h1>
+    /// <p>Some <em>emphasis</em> and &lt;b&gt;html&lt;/b&gt;</p>
+    /// ```
+    #[inline]
+    pub fn markdown<T: AsRef<str> + ?Sized>(expr: &T) -> Markdown<'_, T> {
+        Markdown(expr)
+    }
+
+    /// Helper struct for 'markdown_sanitized' filter
+    pub struct MarkdownSanitized<'a, T: ?Sized, F> {
+        expr: &'a T,
+        sanitizer: F,
+    }
+
+    impl<'a, T: AsRef<str> + ?Sized, F: Fn(&str) -> String> Render
+        for MarkdownSanitized<'a, T, F>
+    {
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            let mut html = String::new();
+            pulldown_cmark::html::push_html(&mut html, markdown_parser(self.expr.as_ref()));
+            b.push_str(&(self.sanitizer)(&html));
+            Ok(())
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // the sanitizer is responsible for removing the unsafe markup
+            self.render(b)
+        }
+    }
+
+    /// render the CommonMark source as HTML, and pass the result through the
+    /// sanitizer
+    ///
+    /// The sanitizer receives the whole HTML and returns the sanitized one, e.g.
+    /// `ammonia::clean`.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= comment.body | markdown_sanitized(ammonia::clean) %>
+    /// ```
+    #[inline]
+    pub fn markdown_sanitized<T: AsRef<str> + ?Sized, F: Fn(&str) -> String>(
+        expr: &T,
+        sanitizer: F,
+    ) -> MarkdownSanitized<'_, T, F> {
+        MarkdownSanitized { expr, sanitizer }
+    }
+}
+
+cfg_unicode_normalization! {
+    #[derive(Clone, Copy)]
+    enum NormalizationForm {
+        Nfc,
+        Nfkc,
+    }
+
+    /// Helper struct for 'nfc' and 'nfkc' filters
+    pub struct Normalize<'a, T: ?Sized> {
+        expr: &'a T,
+        form: NormalizationForm,
+    }
+
+    impl<'a, T: Render + ?Sized> Normalize<'a, T> {
+        // normalize the contents rendered after `old_len`, returns `None` if the
+        // contents are already normalized
+        fn normalize_tail(&self, b: &Buffer, old_len: usize) -> Result<Option<String>, RenderError> {
+            use unicode_normalization::{is_nfc_quick, is_nfkc_quick, IsNormalized};
+            use unicode_normalization::UnicodeNormalization;
+
+            let content = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+            let normalized = match self.form {
+                NormalizationForm::Nfc => {
+                    if is_nfc_quick(content.chars()) == IsNormalized::Yes {
+                        return Ok(None);
+                    }
+                    content.nfc().collect()
+                }
+                NormalizationForm::Nfkc => {
+                    if is_nfkc_quick(content.chars()) == IsNormalized::Yes {
+                        return Ok(None);
+                    }
+                    content.nfkc().collect()
+                }
+            };
+            Ok(Some(normalized))
+        }
+    }
+
+    impl<'a, T: Render + ?Sized> Render for Normalize<'a, T> {
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            let old_len = b.len();
+            self.expr.render(b)?;
+
+            if let Some(s) = self.normalize_tail(b, old_len)? {
+                unsafe { b._set_len(old_len) };
+                b.push_str(&s);
+            }
+            Ok(())
+        }
+
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // compatibility decomposition may produce the characters to be
+            // escaped (e.g. U+FF1C to '<'), so the contents are escaped afterwards
+            let old_len = b.len();
+            self.expr.render(b)?;
+
+            let s = match self.normalize_tail(b, old_len)? {
+                Some(s) => s,
+                None => b.as_str()[old_len..].to_owned(),
+            };
+            unsafe { b._set_len(old_len) };
+            super::escape::escape_to_buf(&s, b);
+            Ok(())
+        }
+    }
+
+    /// normalize the rendered contents to Unicode Normalization Form C
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= "Cafe\u{301}" | nfc %>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// Café
+    /// ```
+    #[inline]
+    pub fn nfc<T: Render + ?Sized>(expr: &T) -> Normalize<'_, T> {
+        Normalize {
+            expr,
+            form: NormalizationForm::Nfc,
+        }
+    }
+
+    /// normalize the rendered contents to Unicode Normalization Form KC
+    ///
+    /// Compatibility characters such as ligatures and full-width letters are
+    /// replaced with their canonical equivalents.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= "ｆｉﬁ①" | nfkc %>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// fifi1
+    /// ```
+    #[inline]
+    pub fn nfkc<T: Render + ?Sized>(expr: &T) -> Normalize<'_, T> {
+        Normalize {
+            expr,
+            form: NormalizationForm::Nfkc,
+        }
+    }
+}
+
+cfg_syntect! {
+    /// Helper struct for 'highlight' filter
+    pub struct Highlight<'a, T: ?Sized> {
+        expr: &'a T,
+        lang: &'a str,
+    }
+
+    impl<'a, T: Render + ?Sized> Render for Highlight<'a, T> {
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            use once_cell::sync::Lazy;
+            use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+            use syntect::parsing::SyntaxSet;
+            use syntect::util::LinesWithEndings;
+
+            static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+
+            let old_len = b.len();
+            self.expr.render(b)?;
+
+            let code = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+            let syntax = SYNTAX_SET
+                .find_syntax_by_token(self.lang)
+                .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+            let mut generator = ClassedHTMLGenerator::new_with_class_style(
+                syntax,
+                &SYNTAX_SET,
+                ClassStyle::Spaced,
+            );
+            for line in LinesWithEndings::from(code) {
+                generator
+                    .parse_html_for_line_which_includes_newline(line)
+                    .map_err(|e| RenderError::new(&e.to_string()))?;
+            }
+            let html = generator.finalize();
+
+            unsafe { b._set_len(old_len) };
+            b.push_str(&html);
+            Ok(())
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // the code is escaped by the highlighter
+            self.render(b)
+        }
+    }
+
+    /// highlight the source code written in `lang` as HTML
+    ///
+    /// The tokens are wrapped with `<span>` tags whose classes are the scope names
+    /// (e.g. `<span class="keyword other">`), so the colors can be specified with
+    /// CSS. `lang` is either the name of the language or the file extension, and
+    /// the code is rendered as plain text if the language is unknown. The syntax
+    /// definitions are loaded on first use.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <pre><code><%= "let x = 1;" | highlight("rust") %></code></pre>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// <pre><code><span class="source rust"><span class="storage type rust">let</span> x ...
+    /// ```
+    #[inline]
+    pub fn highlight<'a, T: Render + ?Sized>(expr: &'a T, lang: &'a str) -> Highlight<'a, T> {
+        Highlight { expr, lang }
+    }
+}
+
+cfg_regex! {
+    /// Helper struct for 'regex_replace' filter
+    pub struct RegexReplace<'a, T: ?Sized> {
+        expr: &'a T,
+        pattern: &'a str,
+        replacement: &'a str,
+    }
+
+    impl<'a, T: Render + ?Sized> Render for RegexReplace<'a, T> {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            let old_len = b.len();
+            self.expr.render(b)?;
+            regex_replace_impl(b, old_len, self.pattern, self.replacement)
+        }
+
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // replace the contents before escaping not to match the entities
+            let old_len = b.len();
+            self.expr.render(b)?;
+            regex_replace_impl(b, old_len, self.pattern, self.replacement)?;
+
+            let replaced = b.as_str()[old_len..].to_owned();
+            unsafe { b._set_len(old_len) };
+            super::escape::escape_to_buf(&replaced, b);
+            Ok(())
+        }
+    }
+
+    fn cached_regex(pattern: &str) -> Result<regex::Regex, RenderError> {
+        use once_cell::sync::Lazy;
+        use std::collections::HashMap;
+        use std::sync::Mutex;
+
+        static CACHE: Lazy<Mutex<HashMap<String, regex::Regex>>> =
+            Lazy::new(|| Mutex::new(HashMap::new()));
+
+        let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(re) = cache.get(pattern) {
+            return Ok(re.clone());
+        }
+
+        let re = regex::Regex::new(pattern)
+            .map_err(|e| RenderError::new(&e.to_string()))?;
+        cache.insert(pattern.to_owned(), re.clone());
+        Ok(re)
+    }
+
+    fn regex_replace_impl(
+        b: &mut Buffer,
+        old_len: usize,
+        pattern: &str,
+        replacement: &str,
+    ) -> Result<(), RenderError> {
+        let re = cached_regex(pattern)?;
+        let new_contents = b.as_str().get(old_len..).ok_or(RenderError::BufSize)?;
+
+        let replaced = match re.replace_all(new_contents, replacement) {
+            Cow::Borrowed(_) => return Ok(()),
+            Cow::Owned(s) => s,
+        };
+
+        unsafe { b._set_len(old_len) };
+        b.push_str(&replaced);
+        Ok(())
+    }
+
+    /// replace all matches of the regular expression `pattern` in the rendered
+    /// contents with `replacement`
+    ///
+    /// `replacement` may refer to the capture groups with `$1` or `${name}`.
+    /// Compiled patterns are cached, so that the same pattern is compiled only
+    /// once.
+    ///
+    /// # Examples
+    ///
+    /// ```text
+    /// <%= "2024-01-31" | regex_replace(r"(\d+)-(\d+)-(\d+)", "$3/$2/$1") %>
+    /// ```
+    ///
+    /// result:
+    ///
+    /// ```text
+    /// 31/01/2024
+    /// ```
+    #[inline]
+    pub fn regex_replace<'a, T: Render + ?Sized>(
+        expr: &'a T,
+        pattern: &'a str,
+        replacement: &'a str,
+    ) -> RegexReplace<'a, T> {
+        RegexReplace {
+            expr,
+            pattern,
+            replacement,
+        }
+    }
+}
+
+/// Extension trait which provides the chainable filter API
+///
+/// Filters are usually nested from the inside out when applied in Rust code, e.g.
+/// `trim(&upper(&truncate(&x, 10)))`. This trait allows applying them from left
+/// to right instead.
+///
+/// ```
+/// use sailfish::runtime::filter::FilterExt;
+/// use sailfish::runtime::{Buffer, Render};
+///
+/// let mut buf = Buffer::new();
+/// "  hello, world  ".filtered().trim().upper().truncate(5).render(&mut buf).unwrap();
+/// assert_eq!(buf.as_str(), "HELLO...");
+/// ```
+pub trait FilterExt: Render {
+    /// Start the filter chain
+    #[inline]
+    fn filtered(&self) -> Chain<&Self> {
+        Chain(self)
+    }
+}
+
+impl<T: Render + ?Sized> FilterExt for T {}
+
+/// Helper struct for the chainable filter API
+///
+/// The filters applied by the methods are zero-sized wrappers, so that the chain
+/// is as cheap as the nested filter calls.
+pub struct Chain<T>(T);
+
+/// Filter applied in the filter chain
+#[doc(hidden)]
+pub struct Chained<T, F>(T, F);
+
+impl<T, F> Render for Chained<T, F>
+where
+    F: Fn(&T, &mut Buffer, bool) -> Result<(), RenderError>,
+{
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        (self.1)(&self.0, b, false)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        (self.1)(&self.0, b, true)
+    }
+}
+
+#[inline]
+fn render_chained<R: Render>(
+    r: R,
+    b: &mut Buffer,
+    escape: bool,
+) -> Result<(), RenderError> {
+    if escape {
+        r.render_escaped(b)
+    } else {
+        r.render(b)
+    }
+}
+
+impl<T: Render> Render for Chain<T> {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.0.render(b)
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.0.render_escaped(b)
+    }
+}
+
+macro_rules! chain_methods {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[doc = concat!("Apply [`", stringify!($name), "`] filter")]
+            #[inline]
+            pub fn $name<'a>(self, $($arg: $ty),*) -> Chain<impl Render + 'a>
+            where
+                T: 'a,
+            {
+                Chain(Chained(self.0, move |x: &T, b: &mut Buffer, escape: bool| {
+                    render_chained($name(x, $($arg),*), b, escape)
+                }))
+            }
+        )*
+    }
+}
+
+impl<T: Render> Chain<T> {
+    chain_methods! {
+        upper();
+        lower();
+        trim();
+        spaceless();
+        truncate(limit: usize);
+        truncate_chars(limit: usize);
+        truncate_words(limit: usize);
+        truncate_with(limit: usize, suffix: &'a str, count_suffix: bool);
+        capitalize();
+        title();
+        replace(from: &'a str, to: &'a str);
+        pad_left(width: usize, fill: char);
+        pad_right(width: usize, fill: char);
+        center(width: usize, fill: char);
+        num_fmt(group_sep: &'a str, decimal_sep: &'a str);
+        group_digits();
+        nl2br();
+        wordwrap(width: usize, brk: &'a str);
+        indent(width: usize);
+        indent_skip_first(width: usize);
+        striptags();
+        slugify();
+        default(fallback: &'a str);
+        default_if_blank(fallback: &'a str);
+        escape_attr();
+        js();
+        css();
+        urlencode();
+        urlencode_path();
+        mask(visible: usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_render<T: Render>(expr: &T, expected: &str) {
+        let mut buf = Buffer::new();
+        Render::render(expr, &mut buf).unwrap();
+        assert_eq!(buf.as_str(), expected);
+    }
+
+    fn assert_render_escaped<T: Render>(expr: &T, expected: &str) {
+        let mut buf = Buffer::new();
+        Render::render_escaped(expr, &mut buf).unwrap();
+        assert_eq!(buf.as_str(), expected);
+    }
+
+    #[test]
+    fn test_lower() {
+        assert_render(&lower(""), "");
+        assert_render_escaped(&lower(""), "");
+
+        assert_render(&lower("lorem ipsum"), "lorem ipsum");
+        assert_render(&lower("LOREM IPSUM"), "lorem ipsum");
+
+        assert_render_escaped(&lower("hElLo, WOrLd!"), "hello, world!");
+        assert_render_escaped(&lower("hElLo, WOrLd!"), "hello, world!");
+
+        assert_render_escaped(&lower("<h1>TITLE</h1>"), "&lt;h1&gt;title&lt;/h1&gt;");
+        assert_render_escaped(&lower("<<&\"\">>"), "&lt;&lt;&amp;&quot;&quot;&gt;&gt;");
+
+        // non-ascii
+        assert_render(&lower("aBcＡｂｃ"), "abcａｂｃ");
+        assert_render(&lower("ὈΔΥΣΣΕΎΣ"), "ὀδυσσεύς");
+    }
+
+    #[test]
+    fn test_upper() {
+        assert_render(&upper(""), "");
+        assert_render_escaped(&upper(""), "");
+
+        assert_render(&upper("lorem ipsum"), "LOREM IPSUM");
+        assert_render(&upper("LOREM IPSUM"), "LOREM IPSUM");
+
+        assert_render(&upper("hElLo, WOrLd!"), "HELLO, WORLD!");
+        assert_render(&upper("hElLo, WOrLd!"), "HELLO, WORLD!");
+
+        // non-ascii
+        assert_render(&upper("aBcＡｂｃ"), "ABCＡＢＣ");
+        assert_render(&upper("tschüß"), "TSCHÜSS");
+    }
+
+    #[test]
+    fn test_trim() {
+        assert_render(&trim(""), "");
+        assert_render_escaped(&trim(""), "");
+
+        assert_render(&trim("\n \t\r\x0C"), "");
+
+        assert_render(&trim("hello world!"), "hello world!");
+        assert_render(&trim("hello world!\n"), "hello world!");
+        assert_render(&trim("\thello world!"), "hello world!");
+        assert_render(&trim("\thello world!\r\n"), "hello world!");
+
+        assert_render_escaped(&trim(" <html>  "), "&lt;html&gt;");
+        assert_render_escaped(&lower("<<&\"\">>"), "&lt;&lt;&amp;&quot;&quot;&gt;&gt;");
+
+        // non-ascii whitespace
+        assert_render(&trim("\u{A0}空白\u{3000}\u{205F}"), "空白");
+    }
+
+    #[test]
+    fn test_spaceless() {
+        assert_render(&spaceless(""), "");
+        assert_render(
+            &spaceless("<p>\n  <a> b </a>\n</p>\n"),
+            "<p><a> b </a></p>\n",
+        );
+        assert_render(&spaceless("a > b  c"), "a > b  c");
+        assert_render_escaped(&spaceless("<p> </p>"), "&lt;p&gt; &lt;/p&gt;");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_render(&truncate("", 0), "");
+        assert_render(&truncate("", 5), "");
+
+        assert_render(&truncate("apple ", 0), "...");
+        assert_render(&truncate("apple ", 1), "a...");
+        assert_render(&truncate("apple ", 2), "ap...");
+        assert_render(&truncate("apple ", 3), "app...");
+        assert_render(&truncate("apple ", 4), "appl...");
+        assert_render(&truncate("apple ", 5), "apple...");
+        assert_render(&truncate("apple ", 6), "apple ");
+        assert_render(&truncate("apple ", 7), "apple ");
+
+        assert_render(&truncate(&std::f64::consts::PI, 10), "3.14159265...");
+        assert_render(&truncate(&std::f64::consts::PI, 20), "3.141592653589793");
+
+        assert_render_escaped(&truncate("foo<br>bar", 10), "foo&lt;br&...");
+        assert_render_escaped(&truncate("foo<br>bar", 20), "foo&lt;br&gt;bar");
+
+        // non-ascii
+        assert_render(&truncate("魑魅魍魎", 0), "...");
+        assert_render(&truncate("魑魅魍魎", 1), "魑...");
+        assert_render(&truncate("魑魅魍魎", 2), "魑魅...");
+        assert_render(&truncate("魑魅魍魎", 3), "魑魅魍...");
+        assert_render(&truncate("魑魅魍魎", 4), "魑魅魍魎");
+        assert_render(&truncate("魑魅魍魎", 5), "魑魅魍魎");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() {
+        assert_render(&json(""), "\"\"");
+        assert_render(&json(&serde_json::json!({})), "{}");
+
+        assert_render_escaped(&json(&123_i32), "123");
+        assert_render_escaped(&json("Pokémon"), "&quot;Pokémon&quot;");
+    }
+
+    #[test]
+    fn compine() {
+        assert_render(
+            &lower(&upper("Li Europan lingues es membres del sam familie.")),
+            "li europan lingues es membres del sam familie.",
+        );
+        assert_render(&lower(&lower("ハートのＡ")), "ハートのａ");
+        assert_render(&upper(&upper("ハートのＡ")), "ハートのＡ");
+
+        assert_render(&truncate(&trim("\t起来！\r\n"), 1), "起...");
+        assert_render(&truncate(&trim("\t起来！\r\n"), 3), "起来！");
+
+        assert_render(&truncate(&lower("Was möchtest du?"), 10), "was möchte...");
+        assert_render(&truncate(&upper("Was möchtest du?"), 10), "WAS MÖCHTE...");
+    }
+
+    #[test]
+    fn test_classlist() {
+        assert_render(&classlist(), "");
+
+        let list = classlist()
+            .add("btn")
+            .add_if("active", true)
+            .add_if("disabled", false)
+            .add(String::from("btn-primary"))
+            .add("btn")
+            .add("")
+            .build();
+        assert_render(&list, "btn active btn-primary");
+        assert_render_escaped(&list, "btn active btn-primary");
+
+        // class names are always escaped
+        assert_render(&classlist().add("a\"b").add("<c>"), "a&quot;b &lt;c&gt;");
+    }
+
+    #[test]
+    fn test_flatten_or() {
+        let ok: Option<Result<i32, ()>> = Some(Ok(42));
+        let err: Option<Result<i32, ()>> = Some(Err(()));
+        let none: Option<Result<i32, ()>> = None;
+
+        assert_render(&flatten_or(&ok, "n/a"), "42");
+        assert_render(&flatten_or(&err, "n/a"), "n/a");
+        assert_render(&flatten_or(&none, "n/a"), "n/a");
+
+        let ok: Option<Result<&str, ()>> = Some(Ok("<b>"));
+        assert_render_escaped(&flatten_or(&ok, "n/a"), "&lt;b&gt;");
+        assert_render_escaped(&flatten_or(&none, "<none>"), "&lt;none&gt;");
+    }
+
+    #[test]
+    fn test_humanize_duration() {
+        let d = Duration::from_secs(2 * 86400 + 3 * 3600 + 5 * 60 + 7);
+        assert_render(&humanize_duration(&d, 1), "2 days");
+        assert_render(&humanize_duration(&d, 2), "2 days 3 hours");
+        assert_render(
+            &humanize_duration(&d, 4),
+            "2 days 3 hours 5 minutes 7 seconds",
+        );
+
+        let d = Duration::from_secs(86400 + 1);
+        assert_render(&humanize_duration(&d, 2), "1 day 1 second");
+
+        assert_render(&humanize_duration(&Duration::from_secs(0), 2), "0 seconds");
+        assert_render(
+            &humanize_duration(&Duration::from_millis(999), 2),
+            "0 seconds",
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        let page =
+            Layout::new("<header>", String::from("<main>body</main>"), "</footer>");
+        assert_render(&page, "<header><main>body</main></footer>");
+        assert_render_escaped(
+            &page,
+            "&lt;header&gt;&lt;main&gt;body&lt;/main&gt;&lt;/footer&gt;",
+        );
+
+        let nested = Layout::new(1, Layout::new('a', "b", 'c'), 2.5);
+        assert_render(&nested, "1abc2.5");
+    }
+
+    #[test]
+    fn test_gauge() {
+        assert_render_escaped(
+            &gauge(&42, 0.0, 100.0),
+            "<div role=\"progressbar\" aria-valuenow=\"42\" aria-valuemin=\"0\" \
+             aria-valuemax=\"100\"><div style=\"width: 42%\"></div></div>",
+        );
+        assert_render(
+            &gauge(&0.5f32, 0.0, 2.0),
+            "<div role=\"progressbar\" aria-valuenow=\"0.5\" aria-valuemin=\"0\" \
+             aria-valuemax=\"2\"><div style=\"width: 25%\"></div></div>",
+        );
+
+        // out-of-range values are clamped
+        assert_render(
+            &gauge(&150u8, 0.0, 100.0),
+            "<div role=\"progressbar\" aria-valuenow=\"100\" aria-valuemin=\"0\" \
+             aria-valuemax=\"100\"><div style=\"width: 100%\"></div></div>",
+        );
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_sri() {
+        assert_render(
+            &sri(""),
+            "sha384-OLBgp1GsljhM2TJ+sbHjaiH9txEUvgdDTAzHv2P24donTt6/529l+9Ua0vFImLlb",
+        );
+        assert_render_escaped(
+            &sri(b"alert('Hello, world.');"),
+            "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO",
+        );
+    }
+
+    #[test]
+    fn test_with_sign() {
+        assert_render(&with_sign(&5), "+5");
+        assert_render(&with_sign(&-3), "-3");
+        assert_render(&with_sign(&0), "0");
+        assert_render_escaped(&with_sign(&i64::MIN), "-9223372036854775808");
+        assert_render_escaped(&with_sign(&42u32), "+42");
+    }
+
+    #[test]
+    fn test_currency() {
+        assert_render(&currency(&1234.56, "USD"), "$1,234.56");
+        assert_render(&currency(&1234567.891, "EUR"), "€1,234,567.89");
+        assert_render(&currency(&-42, "GBP"), "-£42.00");
+        assert_render(&currency(&0.999, "USD"), "$1.00");
+        assert_render(&currency(&-0.001, "USD"), "$0.00");
+        assert_render(&currency(&1234.5, "CHF"), "CHF 1,234.50");
+        assert_render_escaped(&currency(&1, "<X>"), "&lt;X&gt; 1.00");
+    }
+
+    #[test]
+    fn test_currency_with() {
+        assert_render(&currency_with(&1234.56, "USD", 2), "$1,234.56");
+        assert_render(&currency_with(&1234.5, "CHF", 2), "CHF 1,234.50");
+        assert_render(&currency_with(&1234.7, "JPY", 0), "¥1,235");
+        assert_render(&currency_with(&0.1234f32, "฿", 3), "฿0.123");
+        assert_render(&currency_with(&-1234.5, "{} €", 2), "-1,234.50 €");
+        assert_render(&currency_with(&5u8, "", 1), "5.0");
+        assert_render_escaped(&currency_with(&1, "<X>", 2), "&lt;X&gt;1.00");
+        assert_render_escaped(&currency_with(&1, "X", 2), "X 1.00");
+
+        assert_render(&currency(&1234.7, "JPY").decimals(0), "¥1,235");
+        assert_render(&currency(&1.5, "CHF").decimals(3), "CHF 1.500");
+    }
+
+    #[test]
+    #[cfg(feature = "decimal")]
+    fn test_currency_decimal() {
+        use rust_decimal::Decimal;
+
+        assert_render(
+            &currency(&Decimal::new(123456789, 2), "USD"),
+            "$1,234,567.89",
+        );
+        assert_render(&currency(&Decimal::new(-12345, 3), "EUR"), "-€12.35");
+        assert_render(&currency(&Decimal::new(-1, 3), "EUR"), "€0.00");
+        assert_render(&currency_with(&Decimal::new(5, 0), "{} kr", 0), "5 kr");
+    }
+
+    #[test]
+    fn test_html_list() {
+        let items = vec!["foo", "<bar>"];
+        assert_render(&ul(&items), "<ul><li>foo</li><li>&lt;bar&gt;</li></ul>");
+        assert_render_escaped(&ol(&[1, 2]), "<ol><li>1</li><li>2</li></ol>");
+
+        let empty: &[&str] = &[];
+        assert_render(&ul(empty), "<ul></ul>");
+    }
+
+    #[test]
+    fn test_once() {
+        let mut b = Buffer::new();
+        for i in 0..3 {
+            once("<th>", i).render(&mut b).unwrap();
+            b.push_str("row");
+        }
+        assert_eq!(b.as_str(), "<th>rowrowrow");
+
+        assert_render_escaped(&once("<th>", 0), "&lt;th&gt;");
+        assert_render_escaped(&once("<th>", 1), "");
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_render(&mask("4111111111111234", 4), "************1234");
+        assert_render(&mask(&1234, 10), "1234");
+        assert_render(&mask("ä<ö>ü", 1), "****ü");
+        assert_render_escaped(&mask("<secret>", 2), "******t&gt;");
+    }
+
+    #[test]
+    fn test_mask_email() {
+        assert_render(&mask_email("john.doe@example.com"), "j*******@example.com");
+        assert_render(&mask_email("a@example.com"), "a@example.com");
+        assert_render(&mask_email("invalid"), "*******");
+        assert_render_escaped(&mask_email("<b>@x.com"), "&lt;**@x.com");
+    }
+
+    #[cfg(feature = "number-words")]
+    #[test]
+    fn test_number_to_words() {
+        assert_render(&number_to_words(&0), "zero");
+        assert_render(&number_to_words(&19), "nineteen");
+        assert_render(&number_to_words(&42), "forty-two");
+        assert_render(&number_to_words(&100), "one hundred");
+        assert_render(
+            &number_to_words(&1234),
+            "one thousand two hundred thirty-four",
+        );
+        assert_render(&number_to_words(&-7), "negative seven");
+        assert_render(&number_to_words(&2_000_000_010i64), "two billion ten");
+        assert_render_escaped(
+            &number_to_words(&i64::MIN),
+            "negative nine quintillion two hundred twenty-three quadrillion three \
+             hundred seventy-two trillion thirty-six billion eight hundred fifty-four \
+             million seven hundred seventy-five thousand eight hundred eight",
+        );
+    }
+
+    #[test]
+    fn test_active_if() {
+        assert_render(
+            &active_if("/about", "/about", "active"),
+            " class=\"active\"",
+        );
+        assert_render(&active_if("/about/team", "/about", "active"), "");
+        assert_render_escaped(&active_if("/", "/", "a\"b"), " class=\"a&quot;b\"");
+
+        assert_render(
+            &active_if_prefix("/blog/2020/hello", "/blog", "active"),
+            " class=\"active\"",
+        );
+        assert_render(&active_if_prefix("/about", "/blog", "active"), "");
+    }
+
+    #[test]
+    fn test_attr() {
+        assert_render(&attr("disabled", true), " disabled");
+        assert_render(&attr("disabled", false), "");
+        assert_render(&attr("a\"b", true), " a&quot;b");
+
+        assert_render(&bool_attr("checked", true), " checked");
+        assert_render_escaped(&bool_attr("checked", false), "");
+        assert_render(&aria_attr("aria-expanded", true), " aria-expanded=\"true\"");
+        assert_render_escaped(&aria_attr("aria-hidden", false), " aria-hidden=\"false\"");
+
+        assert_render(&attr_opt("href", &Some("/")), " href=\"/\"");
+        assert_render(&attr_opt("title", &Some("<b>")), " title=\"&lt;b&gt;\"");
+        assert_render(&attr_opt::<&str>("href", &None), "");
+        assert_render(&attr_opt("tabindex", &Some(1)), " tabindex=\"1\"");
+    }
+
+    #[test]
+    fn test_escape_attr() {
+        assert_render(&escape_attr(""), "");
+        assert_render(&escape_attr("abc"), "abc");
+        assert_render(
+            &escape_attr("a b=`c`\t'\"<&>"),
+            "a&#x20;b&#x3D;&#x60;c&#x60;&#x9;&#039;&quot;&lt;&amp;&gt;",
+        );
+        assert_render_escaped(&escape_attr("a b"), "a&#x20;b");
+    }
+
+    #[test]
+    fn test_js() {
+        assert_render(&js(""), "");
+        assert_render(&js("hello"), "hello");
+        assert_render(&js("a\\b\n\"c\""), "a\\\\b\\n\\u0022c\\u0022");
+        assert_render(&js("</script>"), "\\u003C\\u002Fscript\\u003E");
+        assert_render(&js("\u{2028}\x01"), "\\u2028\\u0001");
+        assert_render_escaped(&js("'&'"), "\\u0027\\u0026\\u0027");
+    }
+
+    #[test]
+    fn test_urlencode() {
+        assert_render(&urlencode(""), "");
+        assert_render(&urlencode("AZaz09-._~"), "AZaz09-._~");
+        assert_render(&urlencode("a b&c=d/e"), "a%20b%26c%3Dd%2Fe");
+        assert_render(&urlencode("café"), "caf%C3%A9");
+        assert_render_escaped(&urlencode("<'\">"), "%3C%27%22%3E");
+
+        assert_render(&urlencode_path("a b/c?d"), "a%20b/c%3Fd");
+    }
+
+    #[test]
+    fn test_css() {
+        assert_render(&css(""), "");
+        assert_render(&css("foo-bar_1"), "foo-bar_1");
+        assert_render(&css("1a"), "\\31 a");
+        assert_render(&css("-1a"), "-\\31 a");
+        assert_render(&css("-"), "\\-");
+        assert_render(&css("--a"), "--a");
+        assert_render(&css("a b.c#d"), "a\\ b\\.c\\#d");
+        assert_render(&css("\0\x01é"), "\u{FFFD}\\1 é");
+        assert_render(&css("</style>"), "\\<\\/style\\>");
+        assert_render_escaped(&css("</style>"), "\\&lt;\\/style\\&gt;");
+    }
+
+    #[test]
+    fn test_capitalize() {
+        assert_render(&capitalize(""), "");
+        assert_render(&capitalize("hello World"), "Hello World");
+        assert_render(&capitalize("  élan"), "  Élan");
+        assert_render(&capitalize("1st place"), "1st place");
+        assert_render_escaped(&capitalize("<b>"), "&lt;B&gt;");
+
+        assert_render(&title(""), "");
+        assert_render(&title("hello wORLD"), "Hello WORLD");
+        assert_render(&title("don't stop-me now"), "Don't Stop-Me Now");
+        assert_render(&title("ßig"), "SSig");
+        assert_render_escaped(&title("a&b"), "A&amp;B");
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_render(&replace("", "a", "b"), "");
+        assert_render(&replace("abc", "", "x"), "abc");
+        assert_render(&replace("abc", "d", "x"), "abc");
+        assert_render(&replace("a-b-c", "-", ""), "abc");
+        assert_render(&replace("aaa", "a", "bb"), "bbbbbb");
+        assert_render(&replace("xabcabcx", "abc", "d"), "xddx");
+        assert_render(&replace("héllo wörld", "ö", "oe"), "héllo woerld");
+        assert_render_escaped(&replace("a & b", "&", "<&>"), "a &lt;&amp;&gt; b");
+
+        let mut buf = Buffer::new();
+        buf.push_str("a-");
+        replace("a-b", "-", "--").render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "a-a--b");
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_regex_replace() {
+        assert_render(&regex_replace("", "a+", "b"), "");
+        assert_render(&regex_replace("abc", "d", "x"), "abc");
+        assert_render(&regex_replace("a  b   c", r"\s+", " "), "a b c");
+        assert_render(
+            &regex_replace("2024-01-31", r"(\d+)-(\d+)-(\d+)", "$3/$2/$1"),
+            "31/01/2024",
+        );
+        assert_render(
+            &regex_replace(
+                "John Smith",
+                r"(?P<first>\w+) (?P<last>\w+)",
+                "${last}, ${first}",
+            ),
+            "Smith, John",
+        );
+        assert_render_escaped(&regex_replace("a & b", "&", "<&>"), "a &lt;&amp;&gt; b");
+
+        let mut buf = Buffer::new();
+        buf.push_str("a1");
+        regex_replace("b2", r"\d", "#").render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "a1b#");
+
+        let mut buf = Buffer::new();
+        assert!(regex_replace("abc", "(", "").render(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_render(&pad_left(&42, 6, '0'), "000042");
+        assert_render(&pad_left("abc", 2, ' '), "abc");
+        assert_render(&pad_left("", 3, '*'), "***");
+        assert_render(&pad_right("Name", 8, '.'), "Name....");
+        assert_render(&pad_right("héllo", 6, ' '), "héllo ");
+        assert_render(&center("title", 9, ' '), "  title  ");
+        assert_render(&center("ab", 5, '-'), "-ab--");
+        assert_render(&center("abcdef", 5, '-'), "abcdef");
+        assert_render_escaped(&pad_left("a&b", 5, ' '), "  a&amp;b");
+        assert_render_escaped(&pad_right("x", 3, '<'), "x&lt;&lt;");
+
+        let mut buf = Buffer::new();
+        buf.push_str("ab");
+        pad_left("c", 3, '_').render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "ab__c");
+    }
+
+    #[test]
+    fn test_num_fmt() {
+        assert_render(&group_digits(&0), "0");
+        assert_render(&group_digits(&999), "999");
+        assert_render(&group_digits(&1000), "1,000");
+        assert_render(&group_digits(&-1234567i64), "-1,234,567");
+        assert_render(&group_digits(&u64::MAX), "18,446,744,073,709,551,615");
+        assert_render(&group_digits(&1234.5f64), "1,234.5");
+        assert_render(&group_digits(&f64::NAN), "NaN");
+        assert_render(&num_fmt(&1234567.89f64, ".", ","), "1.234.567,89");
+        assert_render(&num_fmt(&0.5f32, " ", ","), "0,5");
+        assert_render(
+            &num_fmt(&1234567, "\u{202F}", ","),
+            "1\u{202F}234\u{202F}567",
+        );
+        assert_render_escaped(&num_fmt(&1234, "&", "."), "1&amp;234");
+
+        let mut buf = Buffer::new();
+        buf.push_str("12345");
+        group_digits(&1000).render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "123451,000");
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_render(&percent(&0.4567, 1), "45.7%");
+        assert_render(&percent(&1, 0), "100%");
+        assert_render(&percent(&0.125f32, 2), "12.50%");
+        assert_render(&percent(&-0.25, 0), "-25%");
+        assert_render(&percent(&-0.00001, 1), "0.0%");
+        assert_render(&percent(&f64::NAN, 1), "NaN%");
+        assert_render(&percent(&f64::NEG_INFINITY, 1), "-inf%");
+        assert_render_escaped(&percent(&0.5, 0), "50%");
+    }
+
+    #[test]
+    fn test_filesizeformat() {
+        assert_render(&filesizeformat(&0), "0 bytes");
+        assert_render(&filesizeformat(&1u8), "1 byte");
+        assert_render(&filesizeformat(&1023usize), "1023 bytes");
+        assert_render(&filesizeformat(&1024u64), "1.0 KiB");
+        assert_render(&filesizeformat(&1500000), "1.4 MiB");
+        assert_render(&filesizeformat(&(1024 * 1024 - 1)), "1.0 MiB");
+        assert_render(&filesizeformat(&u64::MAX), "16.0 EiB");
+        assert_render(&filesizeformat_si(&999), "999 bytes");
+        assert_render(&filesizeformat_si(&1000), "1.0 kB");
+        assert_render(&filesizeformat_si(&1400000u32), "1.4 MB");
+        assert_render(&filesizeformat_si(&999_960), "1.0 MB");
+        assert_render_escaped(&filesizeformat(&2048), "2.0 KiB");
+
+        let mut buf = Buffer::new();
+        assert!(filesizeformat(&-1).render(&mut buf).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_date_chrono() {
+        use chrono::{FixedOffset, NaiveDate, TimeZone};
+
+        let d = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        assert_render(&date(&d, "%d/%m/%Y"), "31/01/2024");
+        let dt = d.and_hms_opt(12, 34, 56).unwrap();
+        assert_render(&date(&dt, "%Y-%m-%d %H:%M"), "2024-01-31 12:34");
+        let dt = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 1, 31, 8, 0, 0)
+            .unwrap();
+        assert_render(&date(&dt, "%a %b %e %I%p %z"), "Wed Jan 31 08AM +0900");
+        assert_render_escaped(&date(&dt, "<%Y>"), "&lt;2024&gt;");
+
+        let mut buf = Buffer::new();
+        assert!(date(&dt, "%Q").render(&mut buf).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn test_date_time() {
+        use time::{Date, Month, Time, UtcOffset};
+
+        let dt = Date::from_calendar_date(2024, Month::January, 5)
+            .unwrap()
+            .with_time(Time::from_hms_nano(15, 4, 5, 120).unwrap())
+            .assume_offset(UtcOffset::from_hms(-5, -30, 0).unwrap());
+        assert_render(&date(&dt, "%Y-%m-%d %H:%M"), "2024-01-05 15:04");
+        assert_render(&date(&dt, "%a, %e %b %y"), "Fri,  5 Jan 24");
+        assert_render(&date(&dt, "%A %B %j %u %w"), "Friday January 005 5 5");
+        assert_render(&date(&dt, "%I:%M %p %l%P %k"), "03:04 PM  3pm 15");
+        assert_render(
+            &date(&dt, "%F %T.%f %z %%"),
+            "2024-01-05 15:04:05.000000120 -0530 %",
+        );
+        assert_render(&date(&dt, "%D %R %C"), "01/05/24 15:04 20");
+        assert_render_escaped(&date(&dt, "<%Y>"), "&lt;2024&gt;");
+
+        let mut buf = Buffer::new();
+        assert!(date(&dt, "%Q").render(&mut buf).is_err());
+        assert!(date(&dt, "%").render(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_timeago() {
+        assert_render(&timeago(&Duration::from_secs(3)), "just now");
+        assert_render(&timeago(&Duration::from_secs(45)), "45 seconds ago");
+        assert_render(&timeago(&Duration::from_secs(60)), "1 minute ago");
+        assert_render(&timeago(&Duration::from_secs(3 * 3600 + 59)), "3 hours ago");
+        assert_render(&timeago(&Duration::from_secs(86400)), "1 day ago");
+        assert_render(&timeago(&Duration::from_secs(15 * 86400)), "2 weeks ago");
+        assert_render(&timeago(&Duration::from_secs(95 * 86400)), "3 months ago");
+        assert_render(&timeago(&Duration::from_secs(800 * 86400)), "2 years ago");
+
+        let future = SystemTime::now() + Duration::from_secs(2 * 86400 + 60);
+        assert_render(&timeago(&future), "in 2 days");
+        let past = SystemTime::now() - Duration::from_secs(5 * 60 + 1);
+        assert_render(&timeago(&past), "5 minutes ago");
+
+        #[cfg(feature = "chrono")]
+        {
+            let now = chrono::DateTime::<chrono::Utc>::from(SystemTime::now());
+            let past = now - chrono::Duration::seconds(3 * 86400 + 1);
+            assert_render(&timeago(&past), "3 days ago");
+        }
+        #[cfg(feature = "time")]
+        {
+            let future =
+                time::OffsetDateTime::now_utc() + time::Duration::seconds(7200 + 10);
+            assert_render(&timeago(&future), "in 2 hours");
+        }
 
-        assert_render(&trim("hello world!"), "hello world!");
-        assert_render(&trim("hello world!\n"), "hello world!");
-        assert_render(&trim("\thello world!"), "hello world!");
-        assert_render(&trim("\thello world!\r\n"), "hello world!");
+        const FRENCH: TimeAgoLocale = TimeAgoLocale {
+            just_now: "à l'instant",
+            past: "il y a {}",
+            future: "dans {}",
+            units: [
+                ("seconde", "secondes"),
+                ("minute", "minutes"),
+                ("heure", "heures"),
+                ("jour", "jours"),
+                ("semaine", "semaines"),
+                ("mois", "mois"),
+                ("an", "ans"),
+            ],
+        };
+        assert_render(
+            &timeago_locale(&Duration::from_secs(3 * 3600), &FRENCH),
+            "il y a 3 heures",
+        );
+        assert_render_escaped(
+            &timeago_locale(&Duration::ZERO, &FRENCH),
+            "à l&#039;instant",
+        );
 
-        assert_render_escaped(&trim(" <html>  "), "&lt;html&gt;");
-        assert_render_escaped(&lower("<<&\"\">>"), "&lt;&lt;&amp;&quot;&quot;&gt;&gt;");
+        // the amount is placed before the phrase without placeholder
+        const SHORT: TimeAgoLocale = TimeAgoLocale {
+            past: "ago",
+            future: "",
+            ..TimeAgoLocale::ENGLISH
+        };
+        assert_render(
+            &timeago_locale(&Duration::from_secs(120), &SHORT),
+            "2 minutes ago",
+        );
+        let future = SystemTime::now() + Duration::from_secs(3 * 3600 + 60);
+        assert_render(&timeago_locale(&future, &SHORT), "3 hours");
+    }
 
-        // non-ascii whitespace
-        assert_render(&trim("\u{A0}空白\u{3000}\u{205F}"), "空白");
+    #[test]
+    fn test_pluralize() {
+        assert_render(&pluralize(&0usize, "entry", "entries"), "entries");
+        assert_render(&pluralize(&1usize, "entry", "entries"), "entry");
+        assert_render(&pluralize(&2u8, "child", "children"), "children");
+        assert_render(&pluralize(&-1, "item", "items"), "items");
+        assert_render(&pluralize(&u64::MAX, "item", "items"), "items");
+        assert_render(&pluralize_s(&1, "item"), "item");
+        assert_render(&pluralize_s(&3usize, "item"), "items");
+        assert_render_escaped(&pluralize_s(&2, "<b>"), "&lt;b&gt;s");
     }
 
     #[test]
-    fn test_truncate() {
-        assert_render(&truncate("", 0), "");
-        assert_render(&truncate("", 5), "");
+    fn test_join() {
+        let empty: Vec<i32> = Vec::new();
+        assert_render(&join(&empty, ", "), "");
+        assert_render(&join(&vec![1, 2, 3], ", "), "1, 2, 3");
+        assert_render(&join(&[1.5, -2.0][..], "/"), "1.5/-2.0");
+        assert_render(&join(&["a"], "-"), "a");
+        assert_render_escaped(
+            &join(&vec!["<a>", "b&c"], " & "),
+            "&lt;a&gt; &amp; b&amp;c",
+        );
 
-        assert_render(&truncate("apple ", 0), "...");
-        assert_render(&truncate("apple ", 1), "a...");
-        assert_render(&truncate("apple ", 2), "ap...");
-        assert_render(&truncate("apple ", 3), "app...");
-        assert_render(&truncate("apple ", 4), "appl...");
-        assert_render(&truncate("apple ", 5), "apple...");
-        assert_render(&truncate("apple ", 6), "apple ");
-        assert_render(&truncate("apple ", 7), "apple ");
+        let set: std::collections::BTreeSet<_> = ["x", "y"].iter().copied().collect();
+        assert_render(&join(&set, "|"), "x|y");
+    }
 
-        assert_render(&truncate(&std::f64::consts::PI, 10), "3.14159265...");
-        assert_render(&truncate(&std::f64::consts::PI, 20), "3.141592653589793");
+    #[test]
+    fn test_slugify() {
+        assert_render(&slugify(""), "");
+        assert_render(&slugify("Hello World"), "hello-world");
+        assert_render(
+            &slugify("Crème Brûlée: A How-To Guide!"),
+            "creme-brulee-a-how-to-guide",
+        );
+        assert_render(&slugify("  --Straße_und  Œuvre--  "), "strasse-und-oeuvre");
+        assert_render(&slugify("日本語 title 2024"), "title-2024");
+        assert_render(&slugify("ÆØÅ Þór"), "aeoa-thor");
+        assert_render(&slugify(&1234), "1234");
+        assert_render_escaped(&slugify("<a href=\"x\">"), "a-hrefx");
 
-        assert_render_escaped(&truncate("foo<br>bar", 10), "foo&lt;br&...");
-        assert_render_escaped(&truncate("foo<br>bar", 20), "foo&lt;br&gt;bar");
+        let mut buf = Buffer::new();
+        buf.push_str("/posts/");
+        slugify("My  First Post").render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "/posts/my-first-post");
+    }
 
-        // non-ascii
-        assert_render(&truncate("魑魅魍魎", 0), "...");
-        assert_render(&truncate("魑魅魍魎", 1), "魑...");
-        assert_render(&truncate("魑魅魍魎", 2), "魑魅...");
-        assert_render(&truncate("魑魅魍魎", 3), "魑魅魍...");
-        assert_render(&truncate("魑魅魍魎", 4), "魑魅魍魎");
-        assert_render(&truncate("魑魅魍魎", 5), "魑魅魍魎");
+    #[test]
+    fn test_base64() {
+        assert_render(&base64(""), "");
+        assert_render(&base64("f"), "Zg==");
+        assert_render(&base64("fo"), "Zm8=");
+        assert_render(&base64("foo"), "Zm9v");
+        assert_render(&base64(b"Hello, world!"), "SGVsbG8sIHdvcmxkIQ==");
+        assert_render_escaped(&base64(&vec![0xfbu8, 0xff]), "+/8=");
+
+        assert_render(
+            &data_uri(b"\x89PNG", "image/png"),
+            "data:image/png;base64,iVBORw==",
+        );
+        assert_render(&data_uri("", "text/plain"), "data:text/plain;base64,");
+        assert_render_escaped(&data_uri("a", "x\"y"), "data:x&quot;y;base64,YQ==");
+    }
+
+    #[test]
+    #[cfg(feature = "markdown")]
+    fn test_markdown() {
+        assert_render(&markdown(""), "");
+        assert_render(
+            &markdown("# Title\n\nSome *emphasis* and <b>html</b>"),
+            "<h1>Title</h1>\n<p>Some <em>emphasis</em> and <b>html</b></p>\n",
+        );
+        assert_render_escaped(
+            &markdown("Some *emphasis* and <b>html</b>"),
+            "<p>Some <em>emphasis</em> and &lt;b&gt;html&lt;/b&gt;</p>\n",
+        );
+        assert_render_escaped(
+            &markdown("<script>\nalert(1)\n</script>\n\n~~a~~ & b"),
+            "&lt;script&gt;\nalert(1)\n&lt;/script&gt;\n<p><del>a</del> &amp; b</p>\n",
+        );
+        assert_render(
+            &markdown_sanitized("x <i>y</i>", |html: &str| {
+                html.replace("<i>", "").replace("</i>", "")
+            }),
+            "<p>x y</p>\n",
+        );
+    }
+
+    #[test]
+    fn test_nl2br() {
+        assert_render(&nl2br(""), "");
+        assert_render(&nl2br("no newline"), "no newline");
+        assert_render(&nl2br("a\nb\r\nc\n"), "a<br>\nb\r<br>\nc<br>\n");
+        assert_render(&nl2br("<i>\n</i>"), "<i><br>\n</i>");
+        assert_render_escaped(
+            &nl2br("Hello,\nworld & all"),
+            "Hello,<br>\nworld &amp; all",
+        );
+
+        let mut buf = Buffer::new();
+        buf.push_str("x\n");
+        nl2br("y\nz").render_escaped(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "x\ny<br>\nz");
+    }
+
+    #[test]
+    fn test_wordwrap() {
+        assert_render(&wordwrap("", 10, "\n"), "");
+        assert_render(
+            &wordwrap("The quick brown fox jumps over the lazy dog", 15, "\n"),
+            "The quick brown\nfox jumps over\nthe lazy dog",
+        );
+        assert_render(&wordwrap("a  b\n\nc   d", 3, "\n"), "a b\n\nc d");
+        assert_render(
+            &wordwrap("tiny incomprehensibilities x", 5, "\n"),
+            "tiny\nincomprehensibilities\nx",
+        );
+        assert_render(&wordwrap("über straße", 5, "\r\n"), "über\r\nstraße");
+        assert_render_escaped(&wordwrap("a&b c<d", 3, "<br>"), "a&amp;b<br>c&lt;d");
+    }
+
+    #[test]
+    fn test_indent() {
+        assert_render(&indent("", 2), "");
+        assert_render(&indent("a", 2), "  a");
+        assert_render(&indent("a\nb\n", 2), "  a\n  b\n");
+        assert_render(&indent("a\n\n\r\nb", 4), "    a\n\n\r\n    b");
+        assert_render(&indent("a\nb", 0), "a\nb");
+        assert_render(&indent_skip_first("a\nb\nc", 3), "a\n   b\n   c");
+        assert_render_escaped(&indent("<a>\n&", 1), " &lt;a&gt;\n &amp;");
+
+        let mut buf = Buffer::new();
+        buf.push_str("key:\n");
+        indent(&"x: 1\ny: 2", 2).render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "key:\n  x: 1\n  y: 2");
+    }
+
+    #[test]
+    fn test_striptags() {
+        assert_render(&striptags(""), "");
+        assert_render(&striptags("plain text"), "plain text");
+        assert_render(
+            &striptags("<p>Hello, <b>world</b>!</p>\n<p>Second</p>"),
+            "Hello, world! Second",
+        );
+        assert_render(&striptags("a<!-- <b>x</b> -->b"), "ab");
+        assert_render(&striptags("  x   <br/>  y  "), "x y");
+        assert_render(
+            &striptags("&lt;tag&gt; &#65;&#x42; &unknown; &amp"),
+            "<tag> AB &unknown; &amp",
+        );
+        assert_render(&striptags("broken <a href"), "broken");
+        assert_render_escaped(
+            &striptags("<p>Fish &amp; <b>chips</b></p>"),
+            "Fish &amp; chips",
+        );
+    }
+
+    #[test]
+    fn test_default() {
+        assert_render(&default(&None::<i32>, "none"), "none");
+        assert_render(&default(&Some(0), "none"), "0");
+        assert_render(&default("", "empty"), "empty");
+        assert_render(&default(" ", "empty"), " ");
+        assert_render(&default_if_blank(" \n\t", "blank"), "blank");
+        assert_render(&default_if_blank(&Some(" x "), "blank"), " x ");
+        assert_render_escaped(&default(&None::<&str>, "<none>"), "&lt;none&gt;");
+        assert_render_escaped(&default(&Some("<a>"), "none"), "&lt;a&gt;");
+
+        let mut buf = Buffer::new();
+        buf.push_str("name: ");
+        default(&None::<String>, "-").render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "name: -");
+    }
+
+    #[test]
+    fn test_nth() {
+        let items = vec!["a", "<b>", "c"];
+        let empty: Vec<i32> = Vec::new();
+        assert_render(&first(&items), "a");
+        assert_render(&last(&items), "c");
+        assert_render(&nth(&items, 1), "<b>");
+        assert_render(&nth(&items, 3), "");
+        assert_render(&first(&empty), "");
+        assert_render(&last(&empty), "");
+        assert_render(&first(&[1.5, 2.5][..]), "1.5");
+        assert_render_escaped(&nth(&items, 1), "&lt;b&gt;");
+
+        let set: std::collections::BTreeSet<_> = [3, 1, 2].iter().copied().collect();
+        assert_render(&first(&set), "1");
+        assert_render(&last(&set), "3");
+    }
+
+    #[test]
+    fn test_sort() {
+        let empty: Vec<i32> = Vec::new();
+        assert_render(&sort(&empty, ", "), "");
+        assert_render(&sort(&vec![3, 1, 2], ", "), "1, 2, 3");
+        assert_render(&sort(&["b", "a", "C"], " "), "C a b");
+        assert_render_escaped(&sort(&vec!["<b>", "<a>"], "&"), "&lt;a&gt;&amp;&lt;b&gt;");
+
+        let words = vec!["ccc", "a", "bb", "d"];
+        assert_render(&sort_by_key(&words, |w| w.len(), ","), "a,d,bb,ccc");
+        assert_render(
+            &sort_by_key(&words, |w| std::cmp::Reverse(*w), ","),
+            "d,ccc,bb,a",
+        );
+    }
+
+    #[test]
+    fn test_unique() {
+        let empty: Vec<i32> = Vec::new();
+        assert_render(&unique(&empty, ", "), "");
+        assert_render(
+            &unique(&vec!["rust", "web", "rust", "cli", "web"], ", "),
+            "rust, web, cli",
+        );
+        assert_render(&unique(&[3, 1, 3, 3, 2, 1], ""), "312");
+        assert_render_escaped(&unique(&vec!["<a>", "<a>", "&"], "|"), "&lt;a&gt;|&amp;");
+
+        let tags = vec!["Rust", "rust", "Web", "RUST"];
+        assert_render(
+            &unique_by_key(&tags, |t| t.to_lowercase(), ", "),
+            "Rust, Web",
+        );
+        assert_render(&unique_by_key(&[10, 21, 13, 24], |n| n % 2, " "), "10 21");
+    }
+
+    #[test]
+    fn test_reverse() {
+        let empty: Vec<i32> = Vec::new();
+        assert_render(&reverse(&empty, ", "), "");
+        assert_render(&reverse(&vec![1, 2, 3], ", "), "3, 2, 1");
+        assert_render(&reverse(&["a"], ","), "a");
+        assert_render_escaped(&reverse(&vec!["<a>", "b"], "&"), "b&amp;&lt;a&gt;");
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-segmentation")]
+    fn test_reverse_str() {
+        assert_render(&reverse_str(""), "");
+        assert_render(&reverse_str("abc"), "cba");
+        assert_render(&reverse_str("noe\u{308}l"), "le\u{308}on");
+        assert_render(&reverse_str("a👨‍👩‍👧b🇯🇵"), "🇯🇵b👨‍👩‍👧a");
+        assert_render_escaped(&reverse_str("&lt"), "tl&amp;");
+    }
+
+    #[test]
+    fn test_truncate_by() {
+        assert_render(&truncate_chars("", 0), "");
+        assert_render(&truncate_chars("apple", 5), "apple");
+        assert_render(&truncate_chars("apple", 3), "app...");
+        assert_render(&truncate_chars("ab cd", 3), "ab...");
+        assert_render(&truncate_chars("魑魅魍魎", 2), "魑魅...");
+        assert_render_escaped(&truncate_chars("foo<br>bar", 6), "foo&lt;br...");
+        assert_render_escaped(&truncate_chars("a&b", 3), "a&amp;b");
+        assert_render_escaped(&truncate_chars("<<<", 2), "&lt;&lt;...");
+
+        // rendered markup is not escaped again
+        let markup = crate::runtime::Rendered::_new(Buffer::from("<b>a&#039;b</b>"));
+        assert_render_escaped(&truncate_chars(&markup, 5), "<b>a&#039;...");
+
+        assert_render(&truncate_words("", 2), "");
+        assert_render(
+            &truncate_words("The quick brown fox jumps", 3),
+            "The quick brown...",
+        );
+        assert_render(&truncate_words("  one   two  ", 2), "  one   two  ");
+        assert_render(&truncate_words("one two three", 0), "...");
+        assert_render(&truncate_words("one\ntwo three", 1), "one...");
+        assert_render_escaped(
+            &truncate_words("<a> <b> <c>", 2),
+            "&lt;a&gt; &lt;b&gt;...",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-segmentation")]
+    fn test_truncate_graphemes() {
+        assert_render(&truncate_graphemes("", 1), "");
+        assert_render(&truncate_graphemes("noe\u{308}l", 3), "noe\u{308}...");
+        assert_render(&truncate_graphemes("👨‍👩‍👧👨‍👩‍👧", 1), "👨‍👩‍👧...");
+        assert_render(&truncate_graphemes("👨‍👩‍👧👨‍👩‍👧", 2), "👨‍👩‍👧👨‍👩‍👧");
+        assert_render_escaped(&truncate_graphemes("<é>x", 3), "&lt;é&gt;...");
+    }
+
+    #[test]
+    fn test_truncate_with() {
+        assert_render(&truncate_with("Hello, world!", 8, "…", false), "Hello, w…");
+        assert_render(&truncate_with("Hello, world!", 8, "…", true), "Hello,…");
+        assert_render(
+            &truncate_with("Hello, world!", 13, "…", true),
+            "Hello, world!",
+        );
+        assert_render(&truncate_with("Hello, world!", 2, " [more]", true), " [");
+        assert_render(&truncate_with("Hello, world!", 0, "…", true), "");
+        assert_render(&truncate_with("Hello", 3, "", false), "Hel");
+        assert_render_escaped(
+            &truncate_with("a<b>c", 3, "<…>", false),
+            "a&lt;b&lt;…&gt;",
+        );
+
+        assert_render(
+            &truncate_words("one two three", 2)
+                .suffix(" [more]")
+                .count_suffix(true),
+            "one two [more]",
+        );
+        assert_render(&truncate_chars("abcdef", 4).count_suffix(true), "a...");
+    }
+
+    #[test]
+    fn test_chain() {
+        assert_render(&"  hello  ".filtered(), "  hello  ");
+        assert_render(&"  hello  ".filtered().trim().upper(), "HELLO");
+        assert_render(
+            &"the quick brown fox"
+                .filtered()
+                .title()
+                .truncate_words(2)
+                .pad_right(16, '.'),
+            "The Quick.......",
+        );
+        assert_render(
+            &1234567.filtered().group_digits().replace(",", "_"),
+            "1_234_567",
+        );
+        assert_render(&None::<&str>.filtered().default("n/a").upper(), "N/A");
+        assert_render_escaped(&"<b>a</b>".filtered().trim(), "&lt;b&gt;a&lt;/b&gt;");
+        assert_render_escaped(&"a\nb&c".filtered().nl2br(), "a<br>\nb&amp;c");
+
+        let from = String::from("o");
+        let chained = "foo".filtered().replace(&from, "0");
+        assert_render(&chained, "f00");
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml() {
+        use std::collections::BTreeMap;
+
+        let mut data = BTreeMap::new();
+        data.insert("name", vec!["a", "b"]);
+        data.insert("tag", vec!["<x>"]);
+        assert_render(&yaml(&data), "name:\n- a\n- b\ntag:\n- <x>\n");
+        assert_render_escaped(&yaml(&data), "name:\n- a\n- b\ntag:\n- &lt;x&gt;\n");
+        assert_render(&yaml("foo"), "foo\n");
+        assert_render(&yaml(&1.5), "1.5\n");
     }
 
     #[cfg(feature = "json")]
     #[test]
-    fn test_json() {
-        assert_render(&json(""), "\"\"");
-        assert_render(&json(&serde_json::json!({})), "{}");
+    fn test_json_pretty() {
+        let data = serde_json::json!({"a": [1, "<b>"]});
+        assert_render(
+            &json_pretty(&data),
+            "{\n  \"a\": [\n    1,\n    \"<b>\"\n  ]\n}",
+        );
+        assert_render_escaped(
+            &json_pretty_indent(&data, 4),
+            "{\n    &quot;a&quot;: [\n        1,\n        &quot;&lt;b&gt;&quot;\n    ]\n}",
+        );
+        assert_render(
+            &json_pretty_indent(&data, 0),
+            "{\n\"a\": [\n1,\n\"<b>\"\n]\n}",
+        );
+        assert_render(&json_pretty(&1), "1");
+    }
 
-        assert_render_escaped(&json(&123_i32), "123");
-        assert_render_escaped(&json("Pokémon"), "&quot;Pokémon&quot;");
+    #[test]
+    fn test_xml() {
+        assert_render(
+            &xml("Tom's <b>&\"</b>"),
+            "Tom&apos;s &lt;b&gt;&amp;&quot;&lt;/b&gt;",
+        );
+        assert_render_escaped(&xml("a'b<"), "a&apos;b&lt;");
+        assert_render(&xml(&123), "123");
     }
 
     #[test]
-    fn compine() {
+    fn test_csv() {
+        assert_render(&csv("plain"), "plain");
+        assert_render(&csv(""), "");
+        assert_render(&csv("Smith, John"), "\"Smith, John\"");
+        assert_render(&csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_render(&csv("a\r\nb"), "\"a\r\nb\"");
+        assert_render(&csv(&-1.5), "-1.5");
+        assert_render_escaped(
+            &csv("<a>, \"b\""),
+            "&quot;&lt;a&gt;, &quot;&quot;b&quot;&quot;&quot;",
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_render(&hex(b""), "");
+        assert_render(&hex(b"\x00\x12\xab\xff"), "0012abff");
+        assert_render(&hex_upper(&[0x0fu8, 0xa0][..]), "0FA0");
+        assert_render_escaped(&hex("<>"), "3c3e");
+        assert_render(&hex(&vec![1u8, 2, 3]), "010203");
+    }
+
+    #[test]
+    fn test_case_conversion_in_place() {
+        let mut buf = Buffer::new();
+        buf.push_str("Prefix ");
+        upper("mixed Case").render(&mut buf).unwrap();
+        lower(" ÀND ÜNICODE").render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "Prefix MIXED CASE ànd ünicode");
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_normalize() {
+        assert_render(&nfc(""), "");
+        assert_render(&nfc("Cafe\u{301}"), "Caf\u{e9}");
+        assert_render(&nfc("Caf\u{e9}"), "Caf\u{e9}");
+        assert_render(&nfc("ﬁ"), "ﬁ");
+        assert_render(&nfkc("ｆｉﬁ①"), "fifi1");
+        assert_render_escaped(&nfc("<e\u{301}>"), "&lt;\u{e9}&gt;");
+        assert_render_escaped(&nfkc("＜b＞"), "&lt;b&gt;");
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal() {
+        use rust_decimal::Decimal;
+
+        assert_render(&decimal(&Decimal::new(12345, 3), 2), "12.35");
+        assert_render(&decimal(&Decimal::new(-12345, 3), 2), "-12.35");
+        assert_render(&decimal(&Decimal::new(5, 0), 3), "5.000");
+        assert_render(&decimal(&Decimal::new(15, 1), 0), "2");
+        assert_render_escaped(&decimal(&Decimal::new(1, 4), 2), "0.00");
         assert_render(
-            &lower(&upper("Li Europan lingues es membres del sam familie.")),
-            "li europan lingues es membres del sam familie.",
+            &decimal(&Decimal::new(1, 28), 40),
+            "0.0000000000000000000000000001",
         );
-        assert_render(&lower(&lower("ハートのＡ")), "ハートのａ");
-        assert_render(&upper(&upper("ハートのＡ")), "ハートのＡ");
+    }
 
-        assert_render(&truncate(&trim("\t起来！\r\n"), 1), "起...");
-        assert_render(&truncate(&trim("\t起来！\r\n"), 3), "起来！");
+    #[test]
+    fn test_humanize() {
+        assert_render(&humanize(&0), "0");
+        assert_render(&humanize(&999), "999");
+        assert_render(&humanize(&1000), "1k");
+        assert_render(&humanize(&1200u64), "1.2k");
+        assert_render(&humanize(&-1260i64), "-1.3k");
+        assert_render(&humanize(&999_950), "1M");
+        assert_render(&humanize(&3_400_000), "3.4M");
+        assert_render(&humanize(&2_000_000_000usize), "2B");
+        assert_render(&humanize(&5e15), "5000T");
+        assert_render(&humanize(&12.34), "12.3");
+        assert_render(&humanize(&-0.01), "0");
+        assert_render(&humanize(&f64::NAN), "NaN");
+        assert_render(&humanize_with(&1_234_567, 2), "1.23M");
+        assert_render_escaped(&humanize_with(&1500, 0), "2k");
+    }
 
-        assert_render(&truncate(&lower("Was möchtest du?"), 10), "was möchte...");
-        assert_render(&truncate(&upper("Was möchtest du?"), 10), "WAS MÖCHTE...");
+    #[test]
+    fn test_ordinal() {
+        assert_render(&ordinal(&0), "0th");
+        assert_render(&ordinal(&1), "1st");
+        assert_render(&ordinal(&2u8), "2nd");
+        assert_render(&ordinal(&3usize), "3rd");
+        assert_render(&ordinal(&4), "4th");
+        assert_render(&ordinal(&11), "11th");
+        assert_render(&ordinal(&12), "12th");
+        assert_render(&ordinal(&113), "113th");
+        assert_render(&ordinal(&101), "101st");
+        assert_render(&ordinal(&-22i64), "-22nd");
+        assert!(ordinal(&u128::MAX).render(&mut Buffer::new()).is_err());
+
+        let locale = |n: u128| {
+            if n == 1 {
+                "<sup>er</sup>"
+            } else {
+                "<sup>e</sup>"
+            }
+        };
+        assert_render(&ordinal_locale(&1, &locale), "1<sup>er</sup>");
+        assert_render_escaped(&ordinal_locale(&2, &locale), "2&lt;sup&gt;e&lt;/sup&gt;");
+    }
+
+    #[test]
+    fn test_repeat() {
+        assert_render(&repeat("ab", 3), "ababab");
+        assert_render(&repeat("ab", 1), "ab");
+        assert_render(&repeat("ab", 0), "");
+        assert_render(&repeat("", 5), "");
+        assert_render(&repeat(&7, 2), "77");
+        assert_render_escaped(&repeat("<>", 2), "&lt;&gt;&lt;&gt;");
+
+        let mut buf = Buffer::new();
+        buf.push_str("- ");
+        repeat("=", 1000).render(&mut buf).unwrap();
+        assert_eq!(buf.len(), 1002);
+        assert!(buf.as_str()[2..].bytes().all(|c| c == b'='));
+    }
+
+    #[test]
+    fn test_linebreaks() {
+        assert_render(&linebreaks(""), "");
+        assert_render(&linebreaks("\n \n"), "");
+        assert_render(&linebreaks("foo"), "<p>foo</p>");
+        assert_render(&linebreaks("a\nb\n\n\nc\n"), "<p>a<br>\nb</p>\n\n<p>c</p>");
+        assert_render(&linebreaks("\r\na\r\n \r\nb"), "<p>a</p>\n\n<p>b</p>");
+        assert_render_escaped(&linebreaks("<a>\n&"), "<p>&lt;a&gt;<br>\n&amp;</p>");
+    }
+
+    #[cfg(feature = "syntect")]
+    #[test]
+    fn test_highlight() {
+        let mut buf = Buffer::new();
+        highlight("let s = \"<b>\";", "rust")
+            .render(&mut buf)
+            .unwrap();
+        assert!(buf.as_str().starts_with("<span class=\"source rust\">"));
+        assert!(buf.as_str().contains(">let</span>"));
+        assert!(buf.as_str().contains("&lt;b&gt;"));
+        assert!(!buf.as_str().contains("<b>"));
+
+        assert_render_escaped(
+            &highlight("a < b", "no-such-language"),
+            "<span class=\"text plain\">a &lt; b</span>",
+        );
+    }
+
+    #[test]
+    fn test_obfuscate_email() {
+        assert_render(&obfuscate_email(""), "");
+        assert_render(&obfuscate_email("a@b.c"), "&#97;&#x40;&#98;&#x2e;&#99;");
+        assert_render_escaped(&obfuscate_email("<é"), "&#60;&#xe9;");
+        assert_render(
+            &obfuscate_email_rtl("a@b"),
+            "<span style=\"unicode-bidi:bidi-override;direction:rtl\">&#98;&#x40;&#97;</span>",
+        );
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_render(&strip_ansi(""), "");
+        assert_render(&strip_ansi("plain text"), "plain text");
+        assert_render(&strip_ansi("\x1b[1;31merror\x1b[0m: x"), "error: x");
+        assert_render(&strip_ansi("\x1b[2K\x1b[1Gdone"), "done");
+        assert_render(&strip_ansi("\u{9b}32mgreen"), "green");
+        assert_render(
+            &strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07"),
+            "link",
+        );
+        assert_render(&strip_ansi("\x1b(Bfoo\x1b7bar\x1b"), "foobar");
+        assert_render_escaped(&strip_ansi("\x1b[33m<warn>\x1b[m"), "&lt;warn&gt;");
+    }
+
+    #[test]
+    fn test_squeeze() {
+        assert_render(&squeeze(""), "");
+        assert_render(&squeeze("   "), " ");
+        assert_render(&squeeze("a  b\n\n\tc"), "a b c");
+        assert_render(&squeeze("\u{3000}ä\u{a0}\u{a0}ö "), " ä ö ");
+        assert_render_escaped(&squeeze("<a>\r\n  &"), "&lt;a&gt; &amp;");
+
+        let mut buf = Buffer::new();
+        buf.push_str("  ");
+        squeeze("x  y").render(&mut buf).unwrap();
+        assert_eq!(buf.as_str(), "  x y");
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_render(&truncate_middle("abcdef", 6), "abcdef");
+        assert_render(&truncate_middle("abcdefg", 6), "abc…fg");
+        assert_render(&truncate_middle("abcdefg", 5), "ab…fg");
+        assert_render(&truncate_middle("abcdefg", 1), "…");
+        assert_render(&truncate_middle("abcdefg", 0), "");
+        assert_render(&truncate_middle("あいうえおかき", 4), "あい…き");
+        assert_render(
+            &truncate_middle("/home/user/projects/sailfish/src/lib.rs", 21),
+            "/home/user…src/lib.rs",
+        );
+        assert_render_escaped(&truncate_middle("<<<<>>>>", 3), "&lt;…&gt;");
+        assert_render_escaped(&truncate_middle("<>", 3), "&lt;&gt;");
+    }
+
+    #[test]
+    fn test_fmt() {
+        assert_render(&fmt(&1.23456, "{:>8.2}"), "    1.23");
+        assert_render(&fmt(&1.23456, "{:.3}"), "1.235");
+        assert_render(&fmt(&1.23456, "{}"), "1.23456");
+        assert_render(&fmt(&42, "{:+05}"), "+0042");
+        assert_render(&fmt(&-42, "{:05}"), "-0042");
+        assert_render(&fmt(&42, "{:5}"), "   42");
+        assert_render(&fmt(&42, "{:<5}"), "42   ");
+        assert_render(&fmt("ab", "{:5}"), "ab   ");
+        assert_render(&fmt("ab", "{:*^6}"), "**ab**");
+        assert_render(&fmt("ab", "{:é>3}"), "éab");
+        assert_render(&fmt("abcdef", "{:.3}"), "abc");
+        assert_render(&fmt("long", "{:2}"), "long");
+        assert_render_escaped(&fmt("<a>", "{:>5}"), "  &lt;a&gt;");
+
+        for spec in &["", "{:", "{:x}", "{:.}", "{:>8.2f}"] {
+            assert!(fmt(&1, spec).render(&mut Buffer::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_group_by() {
+        let words = ["apple", "bean", "avocado", "cherry", "banana"];
+        let groups = group_by(&words, |w| w.chars().next());
+        assert_eq!(
+            groups,
+            vec![
+                (Some('a'), vec![&"apple", &"avocado"]),
+                (Some('b'), vec![&"bean", &"banana"]),
+                (Some('c'), vec![&"cherry"]),
+            ]
+        );
+
+        let empty: Vec<i32> = Vec::new();
+        assert!(group_by(&empty, |&n| n % 2).is_empty());
+    }
+
+    #[test]
+    fn test_chunks() {
+        let items = vec![1, 2, 3, 4, 5];
+        assert_eq!(
+            chunks(&items, 2),
+            vec![vec![&1, &2], vec![&3, &4], vec![&5]]
+        );
+        assert_eq!(chunks(&items, 5), vec![vec![&1, &2, &3, &4, &5]]);
+        assert_eq!(chunks(&items[..0], 3), Vec::<Vec<&i32>>::new());
+
+        let set: std::collections::BTreeSet<_> = ["c", "a", "b"].iter().collect();
+        assert_eq!(chunks(&set, 2), vec![vec![&&"a", &&"b"], vec![&&"c"]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_chunks_zero() {
+        chunks(&[1, 2, 3], 0);
+    }
+
+    #[test]
+    fn test_dictsort() {
+        let mut scores = HashMap::new();
+        scores.insert("carol", 70);
+        scores.insert("alice", 90);
+        scores.insert("bob", 80);
+
+        assert_eq!(
+            dictsort(&scores),
+            vec![(&"alice", &90), (&"bob", &80), (&"carol", &70)]
+        );
+        assert_eq!(
+            dictsort_by_key(&scores, |_, &v| v),
+            vec![(&"carol", &70), (&"bob", &80), (&"alice", &90)]
+        );
+        assert!(dictsort(&HashMap::<i32, i32>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_urlize() {
+        assert_render(
+            &urlize(&"See https://example.com/a_(b)?x=1&y=2."),
+            "See <a href=\"https://example.com/a_(b)?x=1&y=2\" rel=\"nofollow\">\
+             https://example.com/a_(b)?x=1&y=2</a>.",
+        );
+        assert_render_escaped(
+            &urlize(&"(<http://example.com/\"x\">) & bob@example.co.jp."),
+            "(&lt;<a href=\"http://example.com/\" rel=\"nofollow\">\
+             http://example.com/</a>&quot;x&quot;&gt;) &amp; \
+             <a href=\"mailto:bob@example.co.jp\" rel=\"nofollow\">bob@example.co.jp</a>.",
+        );
+        assert_render_escaped(
+            &urlize_with(&"http://a.b", "", "_blank"),
+            "<a href=\"http://a.b\" target=\"_blank\">http://a.b</a>",
+        );
+        assert_render_escaped(
+            &urlize_with(&"HTTPS://a.b ftp://a.b", "noopener", ""),
+            "<a href=\"HTTPS://a.b\" rel=\"noopener\">HTTPS://a.b</a> ftp://a.b",
+        );
+        assert_render(
+            &urlize(&"xhttp://a.b http:// @ a@b a@.b @b.c"),
+            "xhttp://a.b http:// @ a@b a@.b @b.c",
+        );
     }
 }
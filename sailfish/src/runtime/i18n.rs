@@ -0,0 +1,158 @@
+//! Translation backend used by `<%~ %>` tag
+//!
+//! Templates look up messages through the backend given by the `translator`
+//! option, which is an expression evaluated in the template (`self.translator` by
+//! default). Since the backend is a value owned by the template, each request can
+//! render with its own locale. Any localization library (e.g. fluent or gettext) can
+//! be plugged in by implementing [`Translate`] trait. If the backend does not know
+//! the message, the message key is rendered as-is.
+//!
+//! Only the in-memory [`Catalog`] is provided. Sailfish ships neither fluent nor
+//! gettext backends, nor a tool to extract the message keys from templates.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Named arguments passed to the translation backend
+pub type Args<'a> = [(&'a str, &'a dyn Display)];
+
+/// Backend which looks up the translated messages
+pub trait Translate {
+    /// Returns the message for `key` formatted with `args`, or `None` if the
+    /// message is not found
+    fn translate(&self, key: &str, args: &Args<'_>) -> Option<String>;
+}
+
+impl<T: Translate + ?Sized> Translate for &T {
+    #[inline]
+    fn translate(&self, key: &str, args: &Args<'_>) -> Option<String> {
+        T::translate(*self, key, args)
+    }
+}
+
+impl<T: Translate + ?Sized> Translate for Box<T> {
+    #[inline]
+    fn translate(&self, key: &str, args: &Args<'_>) -> Option<String> {
+        T::translate(&**self, key, args)
+    }
+}
+
+impl<T: Translate + ?Sized> Translate for Rc<T> {
+    #[inline]
+    fn translate(&self, key: &str, args: &Args<'_>) -> Option<String> {
+        T::translate(&**self, key, args)
+    }
+}
+
+impl<T: Translate + ?Sized> Translate for Arc<T> {
+    #[inline]
+    fn translate(&self, key: &str, args: &Args<'_>) -> Option<String> {
+        T::translate(&**self, key, args)
+    }
+}
+
+/// Look up the message for `key` with the given backend
+///
+/// Returns the key itself if the message is not found.
+pub fn translate<T: Translate + ?Sized>(
+    translator: &T,
+    key: &str,
+    args: &Args<'_>,
+) -> String {
+    translator
+        .translate(key, args)
+        .unwrap_or_else(|| key.to_owned())
+}
+
+/// Simple in-memory message catalog
+///
+/// Placeholders in the messages are written as `{name}`, and replaced with the
+/// argument of the same name. Placeholders without the corresponding argument are
+/// left as-is.
+///
+/// ```
+/// use sailfish::runtime::i18n::{Catalog, Translate};
+///
+/// let mut catalog = Catalog::new();
+/// catalog.insert("welcome.title", "Welcome, {name}!");
+///
+/// let name = "Alice";
+/// let message = catalog.translate("welcome.title", &[("name", &name)]);
+/// assert_eq!(message.as_deref(), Some("Welcome, Alice!"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// Create an empty catalog
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the message for `key`, replacing the previous one
+    #[inline]
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, message: V) {
+        self.messages.insert(key.into(), message.into());
+    }
+}
+
+impl Translate for Catalog {
+    fn translate(&self, key: &str, args: &Args<'_>) -> Option<String> {
+        let message = self.messages.get(key)?;
+        let mut result = String::with_capacity(message.len());
+        let mut rest = message.as_str();
+
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let end = match rest.find('}') {
+                Some(end) => end,
+                None => break,
+            };
+            let name = &rest[1..end];
+            match args.iter().find(|(n, _)| *n == name) {
+                Some((_, value)) => {
+                    fmt::write(&mut result, format_args!("{}", value)).unwrap()
+                }
+                None => result.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalog() {
+        let mut catalog = Catalog::new();
+        catalog.insert("greet", "Hello, {name}! {unknown} {");
+
+        assert_eq!(
+            catalog.translate("greet", &[("name", &1)]).as_deref(),
+            Some("Hello, 1! {unknown} {")
+        );
+        assert_eq!(catalog.translate("missing", &[]), None);
+    }
+
+    #[test]
+    fn translate_fallback() {
+        let mut catalog = Catalog::new();
+        catalog.insert("greet", "Hello");
+
+        let shared = Arc::new(catalog);
+        assert_eq!(translate(&shared, "greet", &[]), "Hello");
+        assert_eq!(translate(&&*shared, "missing", &[]), "missing");
+    }
+}
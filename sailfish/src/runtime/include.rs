@@ -0,0 +1,141 @@
+use super::buffer::Buffer;
+use super::render::RenderError;
+
+/// Render the template which includes itself
+///
+/// The body of the included template is given as `f`, which receives the function to
+/// include the template recursively, the arguments and the buffer. An error is
+/// returned if the same template is included more than `limit` times in the include
+/// chain.
+#[doc(hidden)]
+pub fn recursive_include<A, F>(
+    args: A,
+    buf: &mut Buffer,
+    limit: usize,
+    path: &str,
+    f: F,
+) -> Result<(), RenderError>
+where
+    F: Fn(
+        &dyn Fn(A, &mut Buffer) -> Result<(), RenderError>,
+        A,
+        &mut Buffer,
+    ) -> Result<(), RenderError>,
+{
+    call(&f, args, buf, 1, limit, path)
+}
+
+fn call<A, F>(
+    f: &F,
+    args: A,
+    buf: &mut Buffer,
+    depth: usize,
+    limit: usize,
+    path: &str,
+) -> Result<(), RenderError>
+where
+    F: Fn(
+        &dyn Fn(A, &mut Buffer) -> Result<(), RenderError>,
+        A,
+        &mut Buffer,
+    ) -> Result<(), RenderError>,
+{
+    let include = |args: A, buf: &mut Buffer| {
+        if depth >= limit {
+            Err(RenderError::Msg(format!(
+                "Recursion limit ({}) exceeded while including {:?}",
+                limit, path
+            )))
+        } else {
+            call(f, args, buf, depth + 1, limit, path)
+        }
+    };
+    f(&include, args, buf)
+}
+
+/// Discard the output of the included template unless it is rendered successfully
+///
+/// The buffer is rolled back to the position at which the guard was created when
+/// the guard is dropped without calling `commit`.
+#[doc(hidden)]
+pub struct IncludeGuard<'a> {
+    buf: &'a mut Buffer,
+    checkpoint: usize,
+    committed: bool,
+}
+
+impl<'a> IncludeGuard<'a> {
+    #[inline]
+    pub fn new(buf: &'a mut Buffer) -> Self {
+        let checkpoint = buf.checkpoint();
+        Self {
+            buf,
+            checkpoint,
+            committed: false,
+        }
+    }
+
+    #[inline]
+    pub fn buf(&mut self) -> &mut Buffer {
+        self.buf
+    }
+
+    #[inline]
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl<'a> Drop for IncludeGuard<'a> {
+    #[inline]
+    fn drop(&mut self) {
+        if !self.committed {
+            self.buf.rollback(self.checkpoint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_limit() {
+        let countdown =
+            |include: &dyn Fn(u32, &mut Buffer) -> Result<(), RenderError>,
+             n: u32,
+             buf: &mut Buffer| {
+                buf.push_str(&n.to_string());
+                if n > 0 {
+                    include(n - 1, buf)?;
+                }
+                Ok(())
+            };
+
+        let mut buf = Buffer::new();
+        recursive_include(3, &mut buf, 4, "countdown.stpl", countdown).unwrap();
+        assert_eq!(buf.as_str(), "3210");
+
+        let mut buf = Buffer::new();
+        let err =
+            recursive_include(3, &mut buf, 3, "countdown.stpl", countdown).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Recursion limit (3) exceeded while including \"countdown.stpl\""
+        );
+    }
+
+    #[test]
+    fn include_guard() {
+        let mut buf = Buffer::from("foo");
+        let mut guard = IncludeGuard::new(&mut buf);
+        guard.buf().push_str("bar");
+        guard.commit();
+        assert_eq!(buf.as_str(), "foobar");
+
+        let mut guard = IncludeGuard::new(&mut buf);
+        guard.buf().push_str("baz");
+        drop(guard);
+        assert_eq!(buf.as_str(), "foobar");
+    }
+}
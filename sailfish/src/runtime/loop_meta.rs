@@ -0,0 +1,144 @@
+use std::iter::Peekable;
+
+/// Metadata of the current loop iteration
+///
+/// This struct is yielded by the iterator returned from [`loop_meta`] function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LoopMeta {
+    index: usize,
+    length: Option<usize>,
+    last: bool,
+}
+
+impl LoopMeta {
+    /// Index of the current iteration, starting from 0
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Index of the current iteration, starting from 1
+    #[inline]
+    pub fn index1(&self) -> usize {
+        self.index + 1
+    }
+
+    /// Returns `true` if this is the first iteration
+    #[inline]
+    pub fn first(&self) -> bool {
+        self.index == 0
+    }
+
+    /// Returns `true` if this is the last iteration
+    #[inline]
+    pub fn last(&self) -> bool {
+        self.last
+    }
+
+    /// Returns `true` if the index of the current iteration is even
+    #[inline]
+    pub fn even(&self) -> bool {
+        self.index & 1 == 0
+    }
+
+    /// Returns `true` if the index of the current iteration is odd
+    #[inline]
+    pub fn odd(&self) -> bool {
+        self.index & 1 == 1
+    }
+
+    /// Total number of iterations
+    ///
+    /// Returns `None` if the iterator does not know its exact length in advance.
+    #[inline]
+    pub fn length(&self) -> Option<usize> {
+        self.length
+    }
+}
+
+/// Iterator adapter returned from [`loop_meta`] function
+pub struct LoopIter<I: Iterator> {
+    iter: Peekable<I>,
+    index: usize,
+    length: Option<usize>,
+}
+
+impl<I: Iterator> Iterator for LoopIter<I> {
+    type Item = (LoopMeta, I::Item);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let meta = LoopMeta {
+            index: self.index,
+            length: self.length,
+            last: self.iter.peek().is_none(),
+        };
+        self.index += 1;
+        Some((meta, item))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Attach loop metadata (index, first, last, length) to each item of the iterator
+///
+/// # Examples
+///
+/// ```text
+/// <% for (lp, item) in sailfish::runtime::loop_meta(&items) { %>
+///   <tr class="<%= if lp.odd() { "odd" } else { "even" } %>"><td><%= item %></td></tr>
+/// <% } %>
+/// ```
+#[inline]
+pub fn loop_meta<I: IntoIterator>(iter: I) -> LoopIter<I::IntoIter> {
+    let iter = iter.into_iter();
+    let length = match iter.size_hint() {
+        (lower, Some(upper)) if lower == upper => Some(lower),
+        _ => None,
+    };
+
+    LoopIter {
+        iter: iter.peekable(),
+        index: 0,
+        length,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata() {
+        let items = ["a", "b", "c"];
+        let metas: Vec<_> = loop_meta(&items).map(|(meta, _)| meta).collect();
+
+        assert_eq!(metas.len(), 3);
+        assert!(metas[0].first() && !metas[0].last());
+        assert!(!metas[1].first() && !metas[1].last());
+        assert!(!metas[2].first() && metas[2].last());
+        assert_eq!(metas[1].index(), 1);
+        assert_eq!(metas[1].index1(), 2);
+        assert!(metas[0].even() && metas[1].odd());
+        assert_eq!(metas[2].length(), Some(3));
+    }
+
+    #[test]
+    fn unknown_length() {
+        let iter = (0..10).filter(|&i| i < 4);
+        let metas: Vec<_> = loop_meta(iter).map(|(meta, _)| meta).collect();
+
+        assert_eq!(metas.len(), 4);
+        assert!(metas[3].last());
+        assert_eq!(metas[0].length(), None);
+    }
+
+    #[test]
+    fn empty() {
+        assert!(loop_meta(Vec::<u8>::new()).next().is_none());
+    }
+}
@@ -6,12 +6,18 @@ mod utils;
 mod buffer;
 pub mod escape;
 pub mod filter;
+pub mod i18n;
+mod include;
+mod loop_meta;
 mod macros;
 mod render;
 mod size_hint;
 
 pub use buffer::Buffer;
-pub use render::{Render, RenderError, RenderResult};
+#[doc(hidden)]
+pub use include::{recursive_include, IncludeGuard};
+pub use loop_meta::{loop_meta, LoopIter, LoopMeta};
+pub use render::{Render, RenderError, RenderResult, Rendered};
 pub use size_hint::SizeHint;
 
 #[doc(hidden)]
@@ -103,6 +103,47 @@ impl Render for str {
     }
 }
 
+/// Contents rendered by the template fragments (`def`, `capture` and `call`
+/// blocks)
+///
+/// The contents are already escaped, so they are never escaped again. This type can
+/// only be created by the code generated by the template compiler.
+#[derive(Clone, Debug)]
+pub struct Rendered(Buffer);
+
+impl Rendered {
+    #[inline]
+    #[doc(hidden)]
+    pub fn _new(buf: Buffer) -> Self {
+        Rendered(buf)
+    }
+
+    /// Extracts a string slice containing the rendered contents
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Converts the rendered contents into a `String`
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.0.into_string()
+    }
+}
+
+impl Render for Rendered {
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        b.push_str(self.0.as_str());
+        Ok(())
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        self.render(b)
+    }
+}
+
 impl Render for char {
     #[inline]
     fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
@@ -350,6 +391,25 @@ render_nonzero!(
     NonZeroUsize,
 );
 
+impl<T: Render> Render for Option<T> {
+    /// `None` is rendered as an empty string
+    #[inline]
+    fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        match self {
+            Some(value) => value.render(b),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+        match self {
+            Some(value) => value.render_escaped(b),
+            None => Ok(()),
+        }
+    }
+}
+
 impl<T: Render> Render for Wrapping<T> {
     #[inline]
     fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
@@ -362,6 +422,105 @@ impl<T: Render> Render for Wrapping<T> {
     }
 }
 
+cfg_chrono! {
+    impl Render for chrono::NaiveDate {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            write!(b, "{}", self).map_err(|e| RenderError::from(e))
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // ISO 8601 format does not contain any characters to be escaped
+            self.render(b)
+        }
+    }
+
+    impl Render for chrono::NaiveDateTime {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            write!(b, "{}", self).map_err(|e| RenderError::from(e))
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // ISO 8601 format does not contain any characters to be escaped
+            self.render(b)
+        }
+    }
+
+    impl<Tz: chrono::TimeZone> Render for chrono::DateTime<Tz>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            write!(b, "{}", self).map_err(|e| RenderError::from(e))
+        }
+    }
+}
+
+cfg_time! {
+    impl Render for time::OffsetDateTime {
+        #[inline]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            use fmt::Write;
+
+            write!(b, "{}", self).map_err(|e| RenderError::from(e))
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // ISO 8601 format does not contain any characters to be escaped
+            self.render(b)
+        }
+    }
+}
+
+cfg_decimal! {
+    impl Render for rust_decimal::Decimal {
+        #[cfg_attr(feature = "perf-inline", inline)]
+        fn render(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // digits of the mantissa (at most 29 digits)
+            let mut digits = [0u8; 40];
+            let mantissa = self.mantissa().unsigned_abs();
+            let len = unsafe { itoap::write_to_ptr(digits.as_mut_ptr(), mantissa) };
+            let digits = unsafe { std::str::from_utf8_unchecked(&digits[..len]) };
+            let scale = self.scale() as usize;
+
+            if self.is_sign_negative() {
+                b.push('-');
+            }
+            if scale == 0 {
+                b.push_str(digits);
+            } else if len > scale {
+                b.push_str(&digits[..len - scale]);
+                b.push('.');
+                b.push_str(&digits[len - scale..]);
+            } else {
+                b.push_str("0.");
+                for _ in len..scale {
+                    b.push('0');
+                }
+                b.push_str(digits);
+            }
+            Ok(())
+        }
+
+        #[inline]
+        fn render_escaped(&self, b: &mut Buffer) -> Result<(), RenderError> {
+            // decimal numbers do not contain any characters to be escaped
+            self.render(b)
+        }
+    }
+}
+
 /// The error type which is returned from template function
 #[derive(Clone, Debug)]
 pub enum RenderError {
@@ -513,6 +672,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_option() {
+        let mut b = Buffer::new();
+        Render::render(&Some(1), &mut b).unwrap();
+        Render::render(&None::<i32>, &mut b).unwrap();
+        Render::render_escaped(&Some("<"), &mut b).unwrap();
+        Render::render_escaped(&None::<&str>, &mut b).unwrap();
+        assert_eq!(b.as_str(), "1&lt;");
+    }
+
     #[test]
     fn test_nonzero() {
         let mut b = Buffer::with_capacity(2);
@@ -538,4 +707,65 @@ mod tests {
         assert!(err.source().is_none());
         format!("{}", err);
     }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn render_chrono() {
+        use chrono::{NaiveDate, TimeZone, Utc};
+
+        let mut b = Buffer::new();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        Render::render(&date, &mut b).unwrap();
+        b.push(' ');
+        Render::render_escaped(&date.and_hms_opt(12, 34, 56).unwrap(), &mut b).unwrap();
+        b.push(' ');
+        Render::render_escaped(
+            &Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap(),
+            &mut b,
+        )
+        .unwrap();
+        assert_eq!(
+            b.as_str(),
+            "2024-01-31 2024-01-31 12:34:56 2024-01-31 00:00:00 UTC"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn render_time() {
+        use time::{Date, Month, Time};
+
+        let mut b = Buffer::new();
+        let dt = Date::from_calendar_date(2024, Month::January, 31)
+            .unwrap()
+            .with_time(Time::from_hms(12, 34, 56).unwrap())
+            .assume_utc();
+        Render::render_escaped(&dt, &mut b).unwrap();
+        assert_eq!(b.as_str(), "2024-01-31 12:34:56.0 +00:00:00");
+    }
+
+    #[test]
+    #[cfg(feature = "decimal")]
+    fn render_decimal() {
+        use rust_decimal::Decimal;
+        use std::str::FromStr;
+
+        for s in &[
+            "0",
+            "1",
+            "-1",
+            "0.5",
+            "-0.05",
+            "123.4500",
+            "0.0000000001",
+            "-0.00",
+            "79228162514264337593543950335",
+            "-7.9228162514264337593543950335",
+        ] {
+            let d = Decimal::from_str(s).unwrap();
+            let mut b = Buffer::new();
+            Render::render_escaped(&d, &mut b).unwrap();
+            assert_eq!(b.as_str(), d.to_string());
+        }
+    }
 }
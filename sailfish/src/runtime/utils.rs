@@ -10,6 +10,116 @@ macro_rules! cfg_json {
     }
 }
 
+macro_rules! cfg_yaml {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "yaml")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_sha2 {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "sha2")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "sha2")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_number_words {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "number-words")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "number-words")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_decimal {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "decimal")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "decimal")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_chrono {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "chrono")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_time {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "time")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "time")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_markdown {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "markdown")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "markdown")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_unicode_segmentation {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "unicode-segmentation")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unicode-segmentation")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_unicode_normalization {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "unicode-normalization")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "unicode-normalization")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_syntect {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "syntect")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "syntect")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_regex {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "regex")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "regex")))]
+            $item
+        )*
+    }
+}
+
 #[cfg(sailfish_nightly)]
 macro_rules! likely {
     ($val:expr) => {